@@ -1,9 +1,10 @@
 use clap::{App, Arg};
-use easy_fs::{BlockDevice, EasyFileSystem};
+use easy_fs::{BlockDevice, DiskTime, EasyFileSystem};
 use std::fs::{read_dir, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const BLOCK_SZ: usize = 512;
 
@@ -29,6 +30,15 @@ fn main() {
     easy_fs_pack().expect("Error when packing easy-fs!");
 }
 
+/// Wall-clock time of the host for the timestamps of easy-fs
+fn host_clock() -> DiskTime {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    DiskTime {
+        sec: now.as_secs(),
+        nsec: now.subsec_nanos() as u64,
+    }
+}
+
 /// Write the application's ELFs to a disk file(fs.img).
 fn easy_fs_pack() -> std::io::Result<()> {
     let matches = App::new("EasyFileSystem packer")
@@ -61,6 +71,9 @@ fn easy_fs_pack() -> std::io::Result<()> {
     })));
     // 16MiB, at most 4095 files
     let efs = EasyFileSystem::create(block_file, 16 * 2048, 1);
+    efs.lock().set_clock(host_clock);
+    // an app is read sequentially as a whole, which a few extents map best
+    efs.lock().set_extents(true);
     let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
     let apps: Vec<_> = read_dir(src_path)
         .unwrap()
@@ -95,8 +108,67 @@ fn easy_fs_pack() -> std::io::Result<()> {
     Ok(())
 }
 
+/// The block cache of easy-fs is shared by all tests and does not tell block devices apart,
+/// so the tests use the same image one at a time.
+#[cfg(test)]
+static TEST_IMAGE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Take the test image and create a filesystem of `blocks` blocks on it
+///
+/// The image is used by the caller until the returned guard is dropped.
+#[cfg(test)]
+fn fresh_image(
+    blocks: usize,
+) -> std::io::Result<(std::sync::MutexGuard<'static, ()>, Arc<BlockFile>)> {
+    let guard = TEST_IMAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let block_file = Arc::new(BlockFile(Mutex::new({
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open("target/fs.img")?;
+        f.set_len((blocks * BLOCK_SZ) as u64).unwrap();
+        f
+    })));
+    EasyFileSystem::create(block_file.clone(), blocks as u32, 1);
+    Ok((guard, block_file))
+}
+
+/// A block file whose writes after the first `writes` ones never reach the file, like the power is cut off
+///
+/// The lost blocks are still read back until then, like from the cache of a disk.
+#[cfg(test)]
+struct CrashingBlockFile {
+    block_file: Arc<BlockFile>,
+    /// Number of writes before the crash
+    writes: Mutex<usize>,
+    /// The blocks written after the crash
+    lost: Mutex<std::collections::BTreeMap<usize, Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl BlockDevice for CrashingBlockFile {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        match self.lost.lock().unwrap().get(&block_id) {
+            Some(data) => buf.copy_from_slice(data),
+            None => self.block_file.read_block(block_id, buf),
+        }
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut writes = self.writes.lock().unwrap();
+        if *writes > 0 {
+            *writes -= 1;
+            self.block_file.write_block(block_id, buf);
+        } else {
+            self.lost.lock().unwrap().insert(block_id, buf.to_vec());
+        }
+    }
+}
+
 #[test]
 fn efs_test() -> std::io::Result<()> {
+    let _guard = TEST_IMAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let block_file = Arc::new(BlockFile(Mutex::new({
         let f = OpenOptions::new()
             .read(true)
@@ -159,3 +231,710 @@ fn efs_test() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn efs_dir_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    assert!(root_inode.is_dir());
+    // `.` and `..` of the root are the root itself
+    assert!(root_inode.find(".").unwrap().find("..").unwrap().is_dir());
+    assert!(root_inode.ls().is_empty());
+
+    let dir_a = root_inode.mkdir("a").unwrap();
+    assert!(root_inode.mkdir("a").is_none());
+    assert!(root_inode.create("a").is_none());
+    let dir_b = dir_a.mkdir("b").unwrap();
+    let file_c = dir_b.create("c").unwrap();
+    assert!(!file_c.is_dir());
+    // a file has no entries
+    assert!(file_c.find(".").is_none());
+    assert!(file_c.create("d").is_none());
+    assert!(dir_b.create("a_name_longer_than_27_bytes__").is_none());
+
+    let greet_str = "Hello, directory!";
+    file_c.write_at(0, greet_str.as_bytes());
+    // a/b/../b/c
+    let found = root_inode
+        .find("a")
+        .and_then(|a| a.find("b"))
+        .and_then(|b| b.find(".."))
+        .and_then(|a| a.find("b"))
+        .and_then(|b| b.find("c"))
+        .unwrap();
+    let mut buffer = [0u8; 64];
+    let len = found.read_at(0, &mut buffer);
+    assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap());
+    assert_eq!(root_inode.ls(), vec![String::from("a")]);
+    assert_eq!(dir_a.ls(), vec![String::from("b")]);
+
+    // only an empty directory can be removed
+    assert!(!dir_a.rmdir("b"));
+    assert!(!dir_b.rmdir("c"));
+    assert!(!dir_a.rmdir("."));
+    assert!(!dir_a.rmdir(".."));
+    file_c.clear();
+    dir_b.mkdir("e").unwrap();
+    assert!(dir_b.rmdir("e"));
+    assert!(dir_b.find("e").is_none());
+    assert!(!dir_b.rmdir("e"));
+    // the freed inode is allocated again
+    assert!(dir_b.mkdir("f").is_some());
+
+    // the entries are read one by one with their types
+    let mut offset = 0;
+    let mut entries = Vec::new();
+    while let Some((idx, entry)) = dir_b.read_dir(offset) {
+        entries.push((entry.name, entry.file_type));
+        offset = idx + 1;
+    }
+    assert_eq!(
+        entries,
+        vec![
+            (String::from("."), 0o040000),
+            (String::from(".."), 0o040000),
+            (String::from("c"), 0o100000),
+            (String::from("f"), 0o040000),
+        ]
+    );
+    assert_eq!(
+        dir_b.read_dir(0).unwrap().1.inode_id,
+        dir_b.metadata().inode_id
+    );
+    // a removed entry is skipped
+    drop(file_c);
+    assert!(dir_b.unlink("c"));
+    let (idx, entry) = dir_b.read_dir(2).unwrap();
+    assert_eq!((idx, entry.name.as_str()), (3, "f"));
+    assert!(dir_b.read_dir(idx + 1).is_none());
+    assert!(dir_b.find("f").unwrap().read_dir(0).is_some());
+    assert!(root_inode.create("g").unwrap().read_dir(0).is_none());
+    Ok(())
+}
+
+#[test]
+fn efs_unlink_test() -> std::io::Result<()> {
+    // 4096 blocks = 2MiB
+    let (_guard, block_file) = fresh_image(4096)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+
+    root_inode.create("a").unwrap();
+    root_inode.create("b").unwrap();
+    root_inode.create("c").unwrap();
+    assert!(root_inode.unlink("b"));
+    assert!(root_inode.find("b").is_none());
+    assert!(!root_inode.unlink("b"));
+    // the freed slot is reused
+    root_inode.create("d").unwrap();
+    assert_eq!(root_inode.ls(), vec!["a", "d", "c"]);
+    // a directory is removed by `rmdir`
+    root_inode.mkdir("e").unwrap();
+    assert!(!root_inode.unlink("e"));
+    assert!(!root_inode.unlink("."));
+    assert!(root_inode.rmdir("e"));
+
+    // 6.4MiB in total on the 2MiB image
+    let data = vec![0x5au8; 64 * 1024];
+    for i in 0..100 {
+        let file = root_inode.create("big").unwrap();
+        assert_eq!(file.write_at(0, &data), data.len(), "round {}", i);
+        assert!(root_inode.unlink("big"));
+    }
+    // the new file is empty even if it gets the same inode
+    let file = root_inode.create("big").unwrap();
+    let mut buffer = [0u8; 16];
+    assert_eq!(file.read_at(0, &mut buffer), 0);
+    Ok(())
+}
+
+#[test]
+fn efs_link_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    assert_eq!(root_inode.nlink(), 2);
+
+    // hard links
+    let file_a = root_inode.create("a").unwrap();
+    assert_eq!(file_a.nlink(), 1);
+    let greet_str = "Hello, link!";
+    file_a.write_at(0, greet_str.as_bytes());
+    assert!(root_inode.link("b", &file_a));
+    assert!(!root_inode.link("b", &file_a));
+    assert_eq!(file_a.nlink(), 2);
+    assert!(root_inode.unlink("a"));
+    assert_eq!(file_a.nlink(), 1);
+    let mut buffer = [0u8; 64];
+    let len = root_inode.find("b").unwrap().read_at(0, &mut buffer);
+    assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap());
+
+    // the data is kept while an `Inode` is left
+    assert!(root_inode.unlink("b"));
+    assert_eq!(file_a.nlink(), 0);
+    assert!(!root_inode.link("c", &file_a));
+    let len = file_a.read_at(0, &mut buffer);
+    assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap());
+    drop(file_a);
+    // the freed inode is allocated again as an empty file
+    let file_c = root_inode.create("c").unwrap();
+    assert_eq!(file_c.read_at(0, &mut buffer), 0);
+
+    // directories count `.` and `..` of subdirectories
+    let dir_d = root_inode.mkdir("d").unwrap();
+    assert_eq!(root_inode.nlink(), 3);
+    assert_eq!(dir_d.nlink(), 2);
+    assert!(!dir_d.link("e", &root_inode.find("d").unwrap()));
+    let dir_e = root_inode.mkdir("e").unwrap();
+    assert_eq!(root_inode.nlink(), 4);
+
+    // rename in a directory and across directories
+    assert!(root_inode.rename("c", &root_inode, "f"));
+    assert!(root_inode.find("c").is_none());
+    assert!(root_inode.rename("f", &dir_d, "g"));
+    assert_eq!(dir_d.ls(), vec!["g"]);
+    assert!(!root_inode.rename("f", &dir_d, "g"));
+    // a directory cannot move under itself
+    assert!(!root_inode.rename("d", &dir_d, "h"));
+    assert!(!root_inode.rename("..", &dir_d, "h"));
+    assert!(root_inode.rename("e", &dir_d, "e"));
+    assert_eq!(root_inode.nlink(), 3);
+    assert_eq!(dir_d.nlink(), 3);
+    assert!(!root_inode.rename("d", &dir_e, "d"));
+    let parent = dir_e.find("..").unwrap();
+    assert_eq!(parent.ls(), vec!["g", "e"]);
+
+    // replace an existing entry of the same type
+    dir_d.create("h").unwrap().write_at(0, b"h");
+    assert!(!dir_d.rename("h", &dir_d, "e"));
+    assert!(!dir_d.rename("e", &dir_d, "h"));
+    assert!(dir_d.rename("h", &dir_d, "g"));
+    assert_eq!(dir_d.ls(), vec!["g", "e"]);
+    let len = dir_d.find("g").unwrap().read_at(0, &mut buffer);
+    assert_eq!(&buffer[..len], b"h");
+    dir_d.mkdir("i").unwrap();
+    assert!(dir_d.rename("i", &dir_d, "e"));
+    assert_eq!(dir_d.nlink(), 3);
+    assert_eq!(dir_d.ls(), vec!["g", "e"]);
+    // the replaced directory is removed when its last `Inode` is dropped
+    assert_eq!(dir_e.nlink(), 0);
+    assert!(dir_e.create("j").is_none());
+    Ok(())
+}
+
+#[test]
+fn efs_symlink_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+
+    // a short target is stored in the inode
+    let short = root_inode.symlink("short", "a/b/c").unwrap();
+    assert!(short.is_symlink());
+    assert!(!short.is_dir());
+    assert_eq!(short.read_link().unwrap(), "a/b/c");
+    assert!(root_inode.symlink("short", "d").is_none());
+    assert!(root_inode.symlink("empty", "").is_none());
+    let file = root_inode.create("file").unwrap();
+    assert!(!file.is_symlink());
+    assert!(file.read_link().is_none());
+
+    // a long target is stored in data blocks
+    let long_target: String = (0..1000)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+    let long = root_inode.symlink("long", &long_target).unwrap();
+    assert_eq!(long.read_link().unwrap(), long_target);
+    let inline_target = "x".repeat(112);
+    let inline = root_inode.symlink("inline", &inline_target).unwrap();
+    assert_eq!(inline.read_link().unwrap(), inline_target);
+
+    // the blocks of a long target are freed
+    drop(long);
+    assert!(root_inode.unlink("long"));
+    for i in 0..100 {
+        let long = root_inode.symlink("long", &long_target.repeat(20)).unwrap();
+        assert_eq!(long.read_link().unwrap().len(), 20000, "round {}", i);
+        assert!(root_inode.unlink("long"));
+    }
+    assert!(root_inode.unlink("short"));
+    assert!(root_inode.find("short").is_none());
+    // `short` keeps its target until it is dropped
+    assert_eq!(short.read_link().unwrap(), "a/b/c");
+    Ok(())
+}
+
+#[test]
+fn efs_stat_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    efs.lock().set_clock(host_clock);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+
+    let root = root_inode.metadata();
+    assert_eq!(root.inode_id, 0);
+    assert_eq!(root.mode, 0o040755);
+    assert_eq!(root.nlink, 2);
+
+    let file = root_inode.create("file").unwrap();
+    let created = file.metadata();
+    assert_eq!(created.mode, 0o100644);
+    assert_eq!(created.nlink, 1);
+    assert_eq!((created.size, created.blocks), (0, 0));
+    assert_eq!((created.uid, created.gid), (0, 0));
+    assert_ne!(created.mtime, DiskTime::default());
+    assert_eq!(created.atime, created.mtime);
+    // the directory is modified by the new entry
+    assert!(root_inode.metadata().mtime >= created.mtime);
+
+    // 30 data blocks and an indirect block
+    file.write_at(0, &[1u8; 512 * 30]);
+    let written = file.metadata();
+    assert_eq!(written.size, 512 * 30);
+    assert_eq!(written.blocks, 31);
+    assert!(written.mtime >= created.mtime);
+    assert!(written.ctime >= created.ctime);
+    assert_eq!(written.atime, created.atime);
+    let mut buf = [0u8; 16];
+    file.read_at(0, &mut buf);
+    assert!(file.metadata().atime >= written.mtime);
+
+    // a new link only changes the status
+    assert!(root_inode.link("link", &file));
+    let linked = file.metadata();
+    assert_eq!(linked.nlink, 2);
+    assert_eq!(linked.mtime, written.mtime);
+    assert!(linked.ctime >= written.ctime);
+
+    let dir = root_inode.mkdir("dir").unwrap();
+    assert_eq!(dir.metadata().mode, 0o040755);
+    assert_eq!(root_inode.metadata().nlink, 3);
+    let symlink = root_inode.symlink("symlink", "file").unwrap();
+    let metadata = symlink.metadata();
+    assert_eq!(metadata.mode, 0o120777);
+    assert_eq!((metadata.size, metadata.blocks), (4, 0));
+
+    // the timestamps are persistent
+    let inode_id = file.metadata().inode_id;
+    drop((file, dir, symlink, root_inode));
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.find("link").unwrap();
+    let metadata = file.metadata();
+    assert_eq!(metadata.inode_id, inode_id);
+    assert_eq!(metadata.size, 512 * 30);
+    assert_eq!(metadata.ctime, linked.ctime);
+    Ok(())
+}
+
+#[test]
+fn efs_atime_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    let device = Arc::new(CrashingBlockFile {
+        block_file: block_file.clone(),
+        writes: Mutex::new(usize::MAX),
+        lost: Mutex::new(std::collections::BTreeMap::new()),
+    });
+    let efs = EasyFileSystem::open(device.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let mut buf = [0u8; 16];
+    for clock in [|| DiskTime::default(), || DiskTime { sec: 1000, nsec: 0 }] {
+        efs.lock().set_clock(clock);
+        let file = root_inode.create("file").unwrap();
+        file.write_at(0, b"Hello, atime!");
+        let atime = file.metadata().atime;
+        // reads with a clock that does not move write nothing, not even the journal
+        let writes = *device.writes.lock().unwrap();
+        assert_eq!(file.read_at(0, &mut buf), 13);
+        assert_eq!(file.read_at(0, &mut buf), 13);
+        assert_eq!(*device.writes.lock().unwrap(), writes);
+        assert_eq!(file.metadata().atime, atime);
+        drop(file);
+        assert!(root_inode.unlink("file"));
+    }
+    Ok(())
+}
+
+#[test]
+fn efs_truncate_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("file").unwrap();
+    let mut buffer = [0u8; 4096];
+
+    // shrink keeps the head and drops the tail
+    let data: Vec<u8> = (0..3000).map(|i| (i % 251) as u8 + 1).collect();
+    file.write_at(0, &data);
+    assert!(file.truncate(1000));
+    assert_eq!(file.metadata().size, 1000);
+    assert_eq!(file.metadata().blocks, 2);
+    assert_eq!(file.read_at(0, &mut buffer), 1000);
+    assert_eq!(&buffer[..1000], &data[..1000]);
+    // grow reads zeros without allocating
+    assert!(file.truncate(5000));
+    assert_eq!(file.metadata().blocks, 2);
+    assert_eq!(file.read_at(0, &mut buffer), 4096);
+    assert_eq!(&buffer[..1000], &data[..1000]);
+    assert!(buffer[1000..].iter().all(|&b| b == 0));
+
+    // a hole costs no block
+    let offset = 4 * 1024 * 1024;
+    file.write_at(offset, b"end");
+    let metadata = file.metadata();
+    assert_eq!(metadata.size as usize, offset + 3);
+    // 2 data blocks at the head, and a data block with indirect2 and an indirect1
+    assert_eq!(metadata.blocks, 2 + 3);
+    assert_eq!(file.read_at(offset - 2048, &mut buffer), 2048 + 3);
+    assert!(buffer[..2048].iter().all(|&b| b == 0));
+    assert_eq!(&buffer[2048..2051], b"end");
+    // the index blocks are freed with the data blocks
+    assert!(file.truncate(offset - 100));
+    assert_eq!(file.metadata().blocks, 2);
+
+    // the freed blocks are allocated again
+    // larger than half of the data area, so the next round must reuse the freed blocks
+    let large = vec![7u8; 768 * 1024];
+    for i in 0..3 {
+        file.write_at(0, &large);
+        assert!(file.truncate(512 * 30 + 100), "round {}", i);
+        // 31 data blocks and indirect1
+        assert_eq!(file.metadata().blocks, 32);
+        assert_eq!(file.read_at(512 * 30, &mut buffer), 100);
+        assert!(buffer[..100].iter().all(|&b| b == 7));
+        assert!(file.truncate(0));
+        assert_eq!(file.metadata().blocks, 0);
+    }
+
+    // only a regular file can be truncated, up to the max size
+    assert!(!root_inode.truncate(0));
+    assert!(!file.truncate(usize::MAX));
+    Ok(())
+}
+
+#[test]
+fn efs_large_file_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(32768)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("large").unwrap();
+
+    // 10MiB is beyond indirect2(about 8MiB)
+    let len = 10 * 1024 * 1024;
+    let chunk_len = 64 * 1024;
+    let chunk = |offset: usize| -> Vec<u8> {
+        (offset..offset + chunk_len)
+            .map(|i| (i / BLOCK_SZ + i) as u8)
+            .collect()
+    };
+    for round in 0..2 {
+        for offset in (0..len).step_by(chunk_len) {
+            assert_eq!(file.write_at(offset, &chunk(offset)), chunk_len);
+        }
+        let metadata = file.metadata();
+        assert_eq!(metadata.size as usize, len);
+        // 20480 data blocks, indirect1, indirect2 with 128 indirect1,
+        // and indirect3 with an indirect2 with 31 indirect1
+        assert_eq!(metadata.blocks, 20480 + 1 + (1 + 128) + (1 + 1 + 31));
+        let mut buffer = vec![0u8; chunk_len];
+        for offset in (0..len).step_by(chunk_len) {
+            assert_eq!(file.read_at(offset, &mut buffer), chunk_len);
+            assert!(buffer == chunk(offset), "round {} offset {}", round, offset);
+        }
+        // back to 8MiB frees indirect3
+        let indirect2_end = (28 + 128 + 128 * 128) * BLOCK_SZ;
+        assert!(file.truncate(indirect2_end));
+        assert_eq!(file.metadata().blocks, 16540 + 1 + (1 + 128));
+        assert_eq!(
+            file.read_at(indirect2_end - chunk_len, &mut buffer),
+            chunk_len
+        );
+        assert!(buffer == chunk(indirect2_end - chunk_len));
+        // the whole file is freed, or the next round runs out of space
+        assert!(file.truncate(0));
+        assert_eq!(file.metadata().blocks, 0);
+    }
+
+    // a sparse file far beyond the disk size
+    let offset = 900 * 1024 * 1024;
+    file.write_at(offset, b"far");
+    assert_eq!(file.metadata().blocks, 1 + 3);
+    let mut buffer = [1u8; 8];
+    assert_eq!(file.read_at(offset - 5, &mut buffer), 8);
+    assert_eq!(&buffer, b"\0\0\0\0\0far");
+    assert!(!file.truncate(2 * 1024 * 1024 * 1024));
+    assert!(file.truncate(0));
+    assert_eq!(file.metadata().blocks, 0);
+    Ok(())
+}
+
+#[test]
+fn efs_extent_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(32768)?;
+    let efs = EasyFileSystem::open(block_file.clone());
+    efs.lock().set_extents(true);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+
+    // a sequential write is a single extent in the inode, which needs no index block
+    let file = root_inode.create("sequential").unwrap();
+    let len = 10 * 1024 * 1024;
+    let chunk_len = 64 * 1024;
+    let chunk = |offset: usize| -> Vec<u8> {
+        (offset..offset + chunk_len)
+            .map(|i| (i / BLOCK_SZ + i) as u8)
+            .collect()
+    };
+    for offset in (0..len).step_by(chunk_len) {
+        assert_eq!(file.write_at(offset, &chunk(offset)), chunk_len);
+    }
+    assert_eq!(file.metadata().blocks, 20480);
+    let mut buffer = vec![0u8; chunk_len];
+    for offset in (0..len).step_by(chunk_len) {
+        assert_eq!(file.read_at(offset, &mut buffer), chunk_len);
+        assert!(buffer == chunk(offset), "offset {}", offset);
+    }
+    assert!(file.truncate(len / 2 + 100));
+    assert_eq!(file.metadata().blocks, 10241);
+    assert!(file.truncate(0));
+    assert_eq!(file.metadata().blocks, 0);
+
+    // two files written block by block in turn get an extent per block,
+    // which grows the trees to index blocks in two levels
+    let (forward, backward) = (
+        root_inode.create("forward").unwrap(),
+        root_inode.create("backward").unwrap(),
+    );
+    let blocks = 1000;
+    let block = |id: usize, tag: u8| -> Vec<u8> {
+        (0..BLOCK_SZ)
+            .map(|i| (id + i) as u8 ^ tag)
+            .collect::<Vec<_>>()
+    };
+    for i in 0..blocks {
+        forward.write_at(i * BLOCK_SZ, &block(i, 0));
+        let id = blocks - 1 - i;
+        backward.write_at(id * BLOCK_SZ, &block(id, 0xff));
+    }
+    let mut buffer = [0u8; BLOCK_SZ];
+    for (file, tag) in [(&forward, 0), (&backward, 0xff)] {
+        // one block per extent, and a leaf block holds 42 extents at most
+        let metadata = file.metadata();
+        assert_eq!(metadata.size as usize, blocks * BLOCK_SZ);
+        assert!(metadata.blocks as usize > blocks + blocks / 42 + 1);
+        for id in 0..blocks {
+            assert_eq!(file.read_at(id * BLOCK_SZ, &mut buffer), BLOCK_SZ);
+            assert!(buffer[..] == block(id, tag)[..], "block {}", id);
+        }
+        // a partial block is kept, and the holes after it read as zeros
+        let new_len = 300 * BLOCK_SZ + 100;
+        assert!(file.truncate(new_len));
+        assert!(file.metadata().blocks as usize >= 301);
+        assert!(file.truncate(new_len + BLOCK_SZ));
+        assert_eq!(file.read_at(300 * BLOCK_SZ, &mut buffer), BLOCK_SZ);
+        assert!(buffer[..100] == block(300, tag)[..100]);
+        assert!(buffer[100..].iter().all(|&b| b == 0));
+        assert!(file.truncate(0));
+        assert_eq!(file.metadata().blocks, 0);
+    }
+
+    // a directory maps its entries by extents too
+    let dir = root_inode.mkdir("dir").unwrap();
+    for i in 0..40 {
+        dir.create(&format!("file{}", i)).unwrap();
+    }
+    assert_eq!(dir.ls().len(), 40);
+    assert!(dir.find("file39").is_some());
+
+    // the freed blocks are allocated again as a single extent
+    let file = root_inode.create("again").unwrap();
+    for offset in (0..len).step_by(chunk_len) {
+        assert_eq!(file.write_at(offset, &chunk(offset)), chunk_len);
+    }
+    assert_eq!(file.metadata().blocks, 20480);
+    Ok(())
+}
+
+#[test]
+fn efs_journal_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    {
+        let efs = EasyFileSystem::open(block_file.clone());
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let old = root_inode.create("old").unwrap();
+        old.write_at(0, &[1u8; 3000]);
+    }
+    let mut image = Vec::new();
+    {
+        let mut file = block_file.0.lock().unwrap();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut image)?;
+    }
+    let new_data: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+    // every operation is done or not at all, however it is interrupted
+    let check = |root_inode: &easy_fs::Inode, writes: usize| {
+        let mut buffer = vec![0u8; 8192];
+        let old = root_inode.find("old");
+        let new = root_inode.find("new");
+        match &old {
+            Some(old) => {
+                assert_eq!(old.read_at(0, &mut buffer), 3000, "{} writes", writes);
+                assert!(buffer[..3000].iter().all(|&b| b == 1), "{} writes", writes);
+            }
+            // `old` is removed last
+            None => assert!(new.is_some(), "{} writes", writes),
+        }
+        if let Some(new) = &new {
+            let len = new.read_at(0, &mut buffer);
+            assert!(len == 0 || len == new_data.len(), "{} writes", writes);
+            assert!(old.is_some() || len == new_data.len(), "{} writes", writes);
+            assert!(buffer[..len] == new_data[..len], "{} writes", writes);
+        }
+    };
+    for writes in 0.. {
+        {
+            let mut file = block_file.0.lock().unwrap();
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&image)?;
+        }
+        let device = Arc::new(CrashingBlockFile {
+            block_file: block_file.clone(),
+            writes: Mutex::new(writes),
+            lost: Mutex::new(std::collections::BTreeMap::new()),
+        });
+        {
+            let efs = EasyFileSystem::open(device.clone());
+            let root_inode = EasyFileSystem::root_inode(&efs);
+            let new = root_inode.create("new").unwrap();
+            new.write_at(0, &new_data);
+            assert!(root_inode.unlink("old"));
+        }
+        let crashed = !device.lost.lock().unwrap().is_empty();
+        // mount again, which replays the journal
+        let efs = EasyFileSystem::open(block_file.clone());
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        check(&root_inode, writes);
+        // no block is used by two files: a new file overwrites none of the others
+        let filler = root_inode.create("filler").unwrap();
+        filler.write_at(0, &[2u8; 20000]);
+        check(&root_inode, writes);
+        if !crashed {
+            assert!(root_inode.find("old").is_none());
+            assert!(writes > 10);
+            break;
+        }
+    }
+
+    // a corrupt header of the journal(the last 128 blocks) is cleared instead of replayed
+    let mut header = [0u8; BLOCK_SZ];
+    header[..4].copy_from_slice(&1000u32.to_le_bytes());
+    block_file.write_block(4096 - 128, &header);
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    assert!(root_inode.find("new").is_some());
+    block_file.read_block(4096 - 128, &mut header);
+    assert!(header.iter().all(|&b| b == 0));
+
+    // new blocks are written in place, and an overwrite too large for the journal is split into transactions
+    let large = root_inode.create("large").unwrap();
+    let len = 200 * BLOCK_SZ;
+    assert_eq!(large.write_at(0, &vec![3u8; len]), len);
+    assert_eq!(large.write_at(0, &vec![4u8; len]), len);
+    let mut buffer = vec![0u8; len];
+    assert_eq!(large.read_at(0, &mut buffer), len);
+    assert!(buffer.iter().all(|&b| b == 4));
+    assert_eq!(large.write_at(len / 2, &vec![5u8; len]), len);
+    assert_eq!(large.read_at(0, &mut buffer), len);
+    assert!(buffer[..len / 2].iter().all(|&b| b == 4));
+    assert!(buffer[len / 2..].iter().all(|&b| b == 5));
+    Ok(())
+}
+
+#[test]
+fn efs_full_test() -> std::io::Result<()> {
+    let (_guard, block_file) = fresh_image(4096)?;
+    for extents in [false, true] {
+        if extents {
+            EasyFileSystem::create(block_file.clone(), 4096, 1);
+        }
+        let efs = EasyFileSystem::open(block_file.clone());
+        efs.lock().set_extents(extents);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let free = efs.lock().free_data_blocks();
+
+        // a write beyond the max file size allocates nothing
+        let file = root_inode.create("file").unwrap();
+        assert_eq!(file.write_at(easy_fs::Inode::max_size(), b"x"), 0);
+        assert_eq!(file.metadata().size, 0);
+        assert_eq!(efs.lock().free_data_blocks(), free);
+
+        // fill the disk, and the last write is short
+        let data = vec![0x5au8; 64 * BLOCK_SZ];
+        let mut size = 0;
+        loop {
+            let written = file.write_at(size, &data);
+            size += written;
+            if written < data.len() {
+                break;
+            }
+        }
+        while file.write_at(size, &data[..BLOCK_SZ]) == BLOCK_SZ {
+            size += BLOCK_SZ;
+        }
+        assert_eq!(file.write_at(size, b"x"), 0);
+        assert_eq!(file.metadata().size as usize, size);
+
+        // new directories fail without leaking their inodes or blocks
+        let dirs = (0..)
+            .take_while(|i| root_inode.mkdir(&format!("dir{}", i)).is_some())
+            .count();
+        assert_eq!(root_inode.ls().len(), 1 + dirs);
+        assert!(root_inode.symlink("link", &"x".repeat(200)).is_none());
+        assert!(root_inode.find("link").is_none());
+
+        // everything is freed again
+        drop(file);
+        assert!(root_inode.unlink("file"));
+        for i in 0..dirs {
+            assert!(root_inode.rmdir(&format!("dir{}", i)));
+        }
+        let left = efs.lock().free_data_blocks();
+        assert!(left <= free && left + 8 >= free);
+        let file = root_inode.create("file").unwrap();
+        assert_eq!(file.write_at(0, &data), data.len());
+
+        // the count agrees with the bitmap
+        let left = efs.lock().free_data_blocks();
+        drop((file, root_inode, efs));
+        assert_eq!(
+            EasyFileSystem::open(block_file.clone())
+                .lock()
+                .free_data_blocks(),
+            left
+        );
+    }
+
+    // the inodes run out, and a new file fails though the name is fine
+    EasyFileSystem::create(block_file.clone(), 4096, 1);
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let free = efs.lock().free_inodes();
+    let files: Vec<_> = (0..)
+        .map_while(|i| root_inode.create(&format!("file{}", i)))
+        .collect();
+    assert_eq!(files.len(), free);
+    assert_eq!(efs.lock().free_inodes(), 0);
+    assert!(root_inode.can_create("dir"));
+    assert!(root_inode.mkdir("dir").is_none());
+    assert!(root_inode.symlink("link", "file0").is_none());
+    drop(files);
+    assert!(root_inode.unlink("file0"));
+    assert!(root_inode.mkdir("dir").is_some());
+    drop((root_inode, efs));
+    assert_eq!(
+        EasyFileSystem::open(block_file.clone())
+            .lock()
+            .free_inodes(),
+        0
+    );
+    Ok(())
+}
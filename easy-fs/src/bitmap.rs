@@ -102,6 +102,50 @@ impl Bitmap {
         None
     }
 
+    /// Allocate a run of up to `max_len` contiguous blocks from a block device
+    ///
+    /// The run starts at `goal` if it is free, otherwise at the first free bit like `alloc`,
+    /// and ends at the first allocated bit.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - The position of the first allocated bit and the number of bits(at least 1)
+    /// - If all bits have already been assigned => `None`
+    pub fn alloc_run(
+        &self,
+        block_device: &Arc<dyn BlockDevice>,
+        goal: Option<usize>,
+        max_len: usize,
+    ) -> Option<(usize, usize)> {
+        let start = match goal {
+            Some(bit) if self.try_set(block_device, bit) => bit,
+            _ => self.alloc(block_device)?,
+        };
+        let mut len = 1;
+        while len < max_len && self.try_set(block_device, start + len) {
+            len += 1;
+        }
+        Some((start, len))
+    }
+
+    /// Set a bit if it is in the bitmap and is 0
+    ///
+    /// # Return
+    /// Whether the bit is set by this call
+    fn try_set(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) -> bool {
+        if bit >= self.maximum() {
+            return false;
+        }
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                let free = bitmap_block[bits64_pos] & (1u64 << inner_pos) == 0;
+                bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                free
+            })
+    }
+
     /// Deallocate a block
     pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
         let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
@@ -113,8 +157,29 @@ impl Bitmap {
             });
     }
 
+    /// Count the allocated bits in a block device
+    pub fn count_allocated(&self, block_device: &Arc<dyn BlockDevice>) -> usize {
+        (0..self.blocks)
+            .map(|block_id| {
+                get_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |bitmap_block: &BitmapBlock| {
+                        bitmap_block
+                            .iter()
+                            .map(|bits64| bits64.count_ones() as usize)
+                            .sum::<usize>()
+                    })
+            })
+            .sum()
+    }
+
     /// Get the max number of allocatable blocks
     pub fn maximum(&self) -> usize {
         self.blocks * BLOCK_BITS
     }
+
+    /// Number of blocks of the bitmap itself
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
 }
@@ -178,3 +178,14 @@ pub fn block_cache_sync_all() {
         cache.lock().sync();
     }
 }
+
+/// Sync and drop all block cache, so that the blocks are read from the block device again
+///
+/// A cache still referenced outside the manager stays valid for its holder.
+pub fn block_cache_drop_all() {
+    let mut manager = BLOCK_CACHE_MANAGER.lock();
+    for (_, cache) in manager.queue.iter() {
+        cache.lock().sync();
+    }
+    manager.queue.clear();
+}
@@ -1,13 +1,15 @@
 use super::{
-    block_cache_sync_all, get_block_cache, Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode,
-    SuperBlock,
+    block_cache_drop_all, block_cache_sync_all, get_block_cache, Bitmap, BlockDevice, DirEntry,
+    DiskInode, DiskInodeType, DiskTime, Inode, Journal, SuperBlock, DIRENT_SZ, JOURNAL_CAPACITY,
 };
 use crate::BLOCK_SZ;
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use spin::Mutex;
 ///An easy file system on block
 pub struct EasyFileSystem {
-    ///Real device
+    ///Real device, through the journal
     pub block_device: Arc<dyn BlockDevice>,
     ///Inode bitmap
     pub inode_bitmap: Bitmap,
@@ -17,12 +19,42 @@ pub struct EasyFileSystem {
     inode_area_start_block: u32,
     /// starting block number of data block area
     data_area_start_block: u32,
+    /// Number of `Inode`s in memory for each inode id
+    ///
+    /// An inode whose `nlink` is 0 is freed when the last one is dropped.
+    open_inodes: BTreeMap<u32, usize>,
+    /// Source of the timestamps of inodes
+    clock: fn() -> DiskTime,
+    /// Whether new files and directories map their blocks by extent trees
+    extents: bool,
+    /// The journal that `block_device` writes through
+    journal: Arc<Journal>,
+    /// Number of free data blocks
+    free_data_blocks: usize,
+    /// Number of free inodes
+    free_inodes: usize,
 }
 
+/// Number of blocks of the journal region of a new filesystem
+const JOURNAL_BLOCKS: u32 = 1 + JOURNAL_CAPACITY as u32;
+/// Number of blocks that an operation other than writing the content modifies at most besides the bitmaps,
+/// e.g. the inodes and the directory entries of `rename` and the index blocks of a growing directory
+const OPERATION_BLOCKS: usize = 16;
+/// A new filesystem has an inode for this many blocks of the image at most,
+/// so that the inode area(an eighth of the image) leaves a small image room for data
+const BLOCKS_PER_INODE: u32 = 4;
+
 type DataBlock = [u8; BLOCK_SZ];
 /// An easy fs over a block device
 impl EasyFileSystem {
     /// A data block of block size
+    ///
+    /// The last `JOURNAL_BLOCKS` blocks are the journal region.
+    /// The inodes are as many as the inode bitmap can hold, or one for `BLOCKS_PER_INODE` blocks
+    /// if it is fewer.
+    ///
+    /// # Panic
+    /// If the bitmaps are so large that an operation may not fit in the journal
     pub fn create(
         block_device: Arc<dyn BlockDevice>,
         total_blocks: u32,
@@ -30,25 +62,21 @@ impl EasyFileSystem {
     ) -> Arc<Mutex<Self>> {
         // calculate block size of areas & create bitmaps
         let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
-        let inode_num = inode_bitmap.maximum();
+        let inode_num = inode_bitmap
+            .maximum()
+            .min((total_blocks / BLOCKS_PER_INODE) as usize);
         let inode_area_blocks =
             ((inode_num * core::mem::size_of::<DiskInode>() + BLOCK_SZ - 1) / BLOCK_SZ) as u32;
         let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
-        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks - JOURNAL_BLOCKS;
         let data_bitmap_blocks = (data_total_blocks + 4096) / 4097;
         let data_area_blocks = data_total_blocks - data_bitmap_blocks;
         let data_bitmap = Bitmap::new(
             (1 + inode_bitmap_blocks + inode_area_blocks) as usize,
             data_bitmap_blocks as usize,
         );
-        let mut efs = Self {
-            block_device: Arc::clone(&block_device),
-            inode_bitmap,
-            data_bitmap,
-            inode_area_start_block: 1 + inode_bitmap_blocks,
-            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
-        };
-        // clear all blocks
+        // clear all blocks, and no cached block of another filesystem is left
+        block_cache_drop_all();
         for i in 0..total_blocks {
             get_block_cache(i as usize, Arc::clone(&block_device))
                 .lock()
@@ -68,34 +96,85 @@ impl EasyFileSystem {
                     inode_area_blocks,
                     data_bitmap_blocks,
                     data_area_blocks,
+                    JOURNAL_BLOCKS,
                 );
             },
         );
-        // write back immediately
+        // write back immediately, and the rest goes through the journal
+        block_cache_drop_all();
+        let journal = Arc::new(Journal::new(
+            block_device,
+            (total_blocks - JOURNAL_BLOCKS) as usize,
+            JOURNAL_BLOCKS as usize,
+        ));
+        let mut efs = Self {
+            block_device: journal.clone(),
+            inode_bitmap,
+            data_bitmap,
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            open_inodes: BTreeMap::new(),
+            clock: DiskTime::default,
+            extents: false,
+            journal,
+            free_data_blocks: 0,
+            free_inodes: 0,
+        };
+        // the bits past the data area would be the journal region and beyond the device
+        let data_area_end = data_area_blocks as usize;
+        if data_area_end < efs.data_bitmap.maximum() {
+            efs.data_bitmap
+                .alloc_run(&efs.block_device, Some(data_area_end), usize::MAX);
+        }
+        efs.free_data_blocks = data_area_blocks as usize;
+        // the bits past the inode area would be data blocks
+        if inode_num < efs.inode_bitmap.maximum() {
+            efs.inode_bitmap
+                .alloc_run(&efs.block_device, Some(inode_num), usize::MAX);
+        }
+        efs.free_inodes = inode_num;
+        assert!(
+            efs.fits_transaction(OPERATION_BLOCKS),
+            "the bitmaps are too large for the journal"
+        );
         // create a inode for root node "/"
         // Since this is the first time it has been secured, its number is fixed at 0
-        assert_eq!(efs.alloc_inode(), 0);
+        assert_eq!(efs.alloc_inode(), Some(0));
         let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
-        get_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
+        get_block_cache(root_inode_block_id as usize, Arc::clone(&efs.block_device))
             .lock()
             .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
-                disk_inode.initialize(DiskInodeType::Directory);
+                disk_inode.initialize(DiskInodeType::Directory, efs.now());
             });
-        block_cache_sync_all();
-        Arc::new(Mutex::new(efs))
+        let efs = Arc::new(Mutex::new(efs));
+        // "." and ".." of the root directory are the root itself
+        let root_inode = Self::root_inode(&efs);
+        root_inode.write_at(0, DirEntry::new(".", 0).as_bytes());
+        root_inode.write_at(DIRENT_SZ, DirEntry::new("..", 0).as_bytes());
+        efs
     }
 
     /// Open a block device as a filesystem
+    ///
+    /// A transaction left committed in the journal by a crash is replayed first.
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
+        // the cached blocks may be stale, e.g. when the image is mounted again after a crash
+        block_cache_drop_all();
         // read SuperBlock
-        get_block_cache(0, Arc::clone(&block_device))
-            .lock()
-            .read(0, |super_block: &SuperBlock| {
+        let mut efs = get_block_cache(0, Arc::clone(&block_device)).lock().read(
+            0,
+            |super_block: &SuperBlock| {
                 assert!(super_block.is_valid(), "Error loading EFS!");
+                let journal = Arc::new(Journal::new(
+                    block_device,
+                    super_block.journal_start as usize,
+                    super_block.journal_blocks as usize,
+                ));
+                journal.replay();
                 let inode_total_blocks =
                     super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
-                let efs = Self {
-                    block_device,
+                Self {
+                    block_device: journal.clone(),
                     inode_bitmap: Bitmap::new(1, super_block.inode_bitmap_blocks as usize),
                     data_bitmap: Bitmap::new(
                         (1 + inode_total_blocks) as usize,
@@ -103,19 +182,71 @@ impl EasyFileSystem {
                     ),
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
-                };
-                Arc::new(Mutex::new(efs))
-            })
+                    open_inodes: BTreeMap::new(),
+                    clock: DiskTime::default,
+                    extents: false,
+                    journal,
+                    free_data_blocks: 0,
+                    free_inodes: 0,
+                }
+            },
+        );
+        efs.free_data_blocks =
+            efs.data_bitmap.maximum() - efs.data_bitmap.count_allocated(&efs.block_device);
+        efs.free_inodes =
+            efs.inode_bitmap.maximum() - efs.inode_bitmap.count_allocated(&efs.block_device);
+        Arc::new(Mutex::new(efs))
+    }
+
+    /// Set the source of the timestamps of inodes
+    ///
+    /// All the timestamps are 0(the Unix epoch) until it is set.
+    pub fn set_clock(&mut self, clock: fn() -> DiskTime) {
+        self.clock = clock;
+    }
+
+    /// Make new files and directories map their blocks by extent trees(or not)
+    ///
+    /// The existing inodes keep their format, so an image may have both.
+    pub fn set_extents(&mut self, enabled: bool) {
+        self.extents = enabled;
+    }
+
+    /// Whether new files and directories map their blocks by extent trees
+    pub fn extents(&self) -> bool {
+        self.extents
+    }
+
+    /// Number of free data blocks
+    pub fn free_data_blocks(&self) -> usize {
+        self.free_data_blocks
+    }
+
+    /// Number of free inodes
+    pub fn free_inodes(&self) -> usize {
+        self.free_inodes
+    }
+
+    /// Whether an operation that modifies `blocks` blocks besides the bitmaps fits in a transaction
+    ///
+    /// Every operation but writing the content modifies `OPERATION_BLOCKS` blocks at most, which always fit,
+    /// and `Inode::write_at` splits a large write into transactions that fit.
+    pub fn fits_transaction(&self, blocks: usize) -> bool {
+        self.journal
+            .fits(blocks + self.inode_bitmap.blocks() + self.data_bitmap.blocks())
+    }
+
+    /// Current time for the timestamps of inodes
+    pub fn now(&self) -> DiskTime {
+        (self.clock)()
     }
 
     /// Get the root inode of the filesystem
     pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
-        let block_device = Arc::clone(&efs.lock().block_device);
-        // acquire efs lock temporarily
         // `inode_id 0` is `root inode`
-        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
+        let mut fs = efs.lock();
+        Inode::new(0, &mut fs, Arc::clone(efs))
         // release efs lock
-        Inode::new(block_id, block_offset, Arc::clone(efs), block_device)
     }
 
     /// Get inode by id
@@ -133,6 +264,14 @@ impl EasyFileSystem {
         )
     }
 
+    /// Get the inode id from the position of a disk inode(inverse of `get_disk_inode_pos`)
+    pub fn get_inode_id(&self, block_id: u32, block_offset: usize) -> u32 {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        (block_id - self.inode_area_start_block) * inodes_per_block
+            + (block_offset / inode_size) as u32
+    }
+
     /// Get data block by id
     pub fn get_data_block_id(&self, data_block_id: u32) -> u32 {
         self.data_area_start_block + data_block_id
@@ -141,31 +280,136 @@ impl EasyFileSystem {
     /// Allocate a new inode
     ///
     /// # Return
-    /// index node number
-    pub fn alloc_inode(&mut self) -> u32 {
-        self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
+    /// Conditional branching.
+    /// - index node number
+    /// - If all the inodes are in use => `None`
+    pub fn alloc_inode(&mut self) -> Option<u32> {
+        let inode_id = self.inode_bitmap.alloc(&self.block_device)? as u32;
+        self.free_inodes -= 1;
+        Some(inode_id)
+    }
+
+    /// Deallocate an inode
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.free_inodes += 1;
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize)
+    }
+
+    /// Record that an `Inode` of `inode_id` is created
+    pub fn open_inode(&mut self, inode_id: u32) {
+        *self.open_inodes.entry(inode_id).or_insert(0) += 1;
+    }
+
+    /// Record that an `Inode` of `inode_id` is dropped
+    ///
+    /// # Return
+    /// Whether any `Inode` of `inode_id` is still left
+    pub fn close_inode(&mut self, inode_id: u32) -> bool {
+        let count = self.open_inodes.get_mut(&inode_id).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.open_inodes.remove(&inode_id);
+            return false;
+        }
+        true
+    }
+
+    /// Whether any `Inode` of `inode_id` exists
+    pub fn is_inode_open(&self, inode_id: u32) -> bool {
+        self.open_inodes.contains_key(&inode_id)
+    }
+
+    /// Deallocate the data blocks and the inode of `inode_id`
+    pub fn free_inode(&mut self, inode_id: u32) {
+        let (block_id, block_offset) = self.get_disk_inode_pos(inode_id);
+        let data_blocks_dealloc =
+            get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                .lock()
+                .modify(block_offset, |disk_inode: &mut DiskInode| {
+                    let blocks = disk_inode.allocated_blocks();
+                    let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+                    assert!(data_blocks_dealloc.len() == blocks as usize);
+                    data_blocks_dealloc
+                });
+        self.dealloc_data_blocks(data_blocks_dealloc);
+        self.dealloc_inode(inode_id);
     }
 
     /// Allocate a data block
     ///
     /// # Return
-    /// block number
-    pub fn alloc_data(&mut self) -> u32 {
-        self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
+    /// Conditional branching.
+    /// - block number
+    /// - If the data area is full => `None`
+    pub fn alloc_data(&mut self) -> Option<u32> {
+        Some(self.alloc_data_run(0, 1)?.0)
+    }
+
+    /// Allocate up to `max_len` contiguous data blocks, from the block `goal` if it is free
+    ///
+    /// The blocks are cleared to zero.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - (the first block number, number of blocks(at least 1))
+    /// - If the data area is full => `None`
+    pub fn alloc_data_run(&mut self, goal: u32, max_len: usize) -> Option<(u32, usize)> {
+        let goal = goal
+            .checked_sub(self.data_area_start_block)
+            .map(|bit| bit as usize);
+        let (bit, len) = self
+            .data_bitmap
+            .alloc_run(&self.block_device, goal, max_len)?;
+        self.free_data_blocks -= len;
+        let start = bit as u32 + self.data_area_start_block;
+        for block_id in start as usize..start as usize + len {
+            self.journal.alloc_block(block_id);
+            get_block_cache(block_id, Arc::clone(&self.block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| data_block.fill(0));
+        }
+        Some((start, len))
     }
 
     /// Deallocate a data block
+    ///
+    /// The content is left as it is until the block is allocated again.
     pub fn dealloc_data(&mut self, block_id: u32) {
-        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
-            .lock()
-            .modify(0, |data_block: &mut DataBlock| {
-                data_block.iter_mut().for_each(|p| {
-                    *p = 0;
-                })
-            });
+        self.journal.dealloc_block(block_id as usize);
+        self.free_data_blocks += 1;
         self.data_bitmap.dealloc(
             &self.block_device,
             (block_id - self.data_area_start_block) as usize,
         )
     }
+
+    /// Deallocate data blocks that no inode refers to any more
+    ///
+    /// Only the data bitmap blocks are written for them, so the transaction of the operation
+    /// grows by the data bitmap blocks at most however many blocks are freed.
+    pub fn dealloc_data_blocks(&mut self, blocks: Vec<u32>) {
+        for block_id in blocks {
+            self.dealloc_data(block_id);
+        }
+    }
+
+    /// Write back the block caches and commit the blocks written since the last commit as a transaction
+    ///
+    /// Every operation that modifies the filesystem commits at last, so that a crash leaves it as
+    /// before or after the operation.
+    ///
+    /// # Note
+    /// No block cache may be locked by the caller, since all of them are locked to be written back.
+    pub fn commit(&self) {
+        block_cache_sync_all();
+        self.journal.commit();
+    }
+}
+
+impl Drop for EasyFileSystem {
+    /// Commit what is left, if any
+    fn drop(&mut self) {
+        self.commit();
+    }
 }
@@ -0,0 +1,177 @@
+use super::{BlockDevice, JournalHeader, BLOCK_SZ, JOURNAL_CAPACITY};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use spin::Mutex;
+
+/// A data block
+type DataBlock = [u8; BLOCK_SZ];
+
+/// A write-ahead journal over a block device
+///
+/// It is a block device itself, through which the block caches of the filesystem read and write.
+/// The blocks written since the last commit are held in memory as the current transaction,
+/// except the data blocks allocated in it, which nothing committed refers to, so they are written in place.
+///
+/// # Commit
+/// 1. The blocks are written to the log blocks after the header block.
+/// 2. The header with their home block ids is written, which commits the transaction at once.
+/// 3. The blocks are written to their homes(checkpointing), and the header is cleared.
+///
+/// A crash before 2 loses the whole transaction, and a crash after it is recovered by `replay`.
+///
+/// A transaction must fit in the log blocks, so an operation checks the blocks it may modify
+/// with [`Journal::fits`] beforehand, and fails if they are too many.
+pub struct Journal {
+    /// The block device of the image
+    device: Arc<dyn BlockDevice>,
+    /// The header block, followed by the log blocks
+    start: usize,
+    /// Number of log blocks
+    capacity: usize,
+    /// The current transaction
+    inner: Mutex<JournalInner>,
+}
+
+/// The current transaction of a journal
+#[derive(Default)]
+struct JournalInner {
+    /// Blocks written in the transaction: home block id -> content
+    blocks: BTreeMap<usize, Box<DataBlock>>,
+    /// Data blocks allocated in the transaction
+    fresh: BTreeSet<usize>,
+    /// Data blocks deallocated in the transaction
+    freed: BTreeSet<usize>,
+}
+
+impl Journal {
+    /// A journal in the `blocks` blocks from `start` of `device`
+    pub fn new(device: Arc<dyn BlockDevice>, start: usize, blocks: usize) -> Self {
+        Self {
+            device,
+            start,
+            capacity: blocks.saturating_sub(1).min(JOURNAL_CAPACITY),
+            inner: Mutex::new(JournalInner::default()),
+        }
+    }
+
+    /// Checkpoint the transaction committed to the log blocks, if a crash interrupted it
+    ///
+    /// A header that records more blocks than the log blocks or a home in the journal region is corrupt,
+    /// and it is cleared without replaying anything.
+    ///
+    /// # Return
+    /// Number of blocks written to their homes
+    pub fn replay(&self) -> usize {
+        let mut header = JournalHeader::empty();
+        self.device.read_block(self.start, header.as_bytes_mut());
+        let count = header.count as usize;
+        if count > self.capacity
+            || header.block_ids[..count]
+                .iter()
+                .any(|&block_id| block_id as usize >= self.start)
+        {
+            self.device
+                .write_block(self.start, JournalHeader::empty().as_bytes());
+            return 0;
+        }
+        let mut data = [0u8; BLOCK_SZ];
+        for (i, &block_id) in header.block_ids[..count].iter().enumerate() {
+            self.device.read_block(self.start + 1 + i, &mut data);
+            self.device.write_block(block_id as usize, &data);
+        }
+        if count > 0 {
+            self.device
+                .write_block(self.start, JournalHeader::empty().as_bytes());
+        }
+        count
+    }
+
+    /// Commit the blocks written since the last commit as a transaction and checkpoint it
+    ///
+    /// The block caches must be written back beforehand, or their changes are left to the next transaction.
+    pub fn commit(&self) {
+        let mut inner = self.inner.lock();
+        let blocks = core::mem::take(&mut inner.blocks);
+        inner.fresh.clear();
+        inner.freed.clear();
+        self.write_transaction(blocks);
+    }
+
+    /// Write the blocks to the log blocks, commit them and checkpoint them
+    fn write_transaction(&self, blocks: BTreeMap<usize, Box<DataBlock>>) {
+        if blocks.is_empty() {
+            return;
+        }
+        let mut header = JournalHeader::empty();
+        header.count = blocks.len() as u32;
+        for (i, (&block_id, data)) in blocks.iter().enumerate() {
+            self.device.write_block(self.start + 1 + i, data.as_slice());
+            header.block_ids[i] = block_id as u32;
+        }
+        // the commit point
+        self.device.write_block(self.start, header.as_bytes());
+        for (&block_id, data) in blocks.iter() {
+            self.device.write_block(block_id, data.as_slice());
+        }
+        self.device
+            .write_block(self.start, JournalHeader::empty().as_bytes());
+    }
+
+    /// Whether a transaction of `blocks` blocks fits in the log blocks
+    pub fn fits(&self, blocks: usize) -> bool {
+        blocks <= self.capacity
+    }
+
+    /// Record that a data block is allocated in the transaction
+    ///
+    /// It is written in place unless it is deallocated in the transaction too,
+    /// because the committed inodes may still refer to such a block.
+    pub fn alloc_block(&self, block_id: usize) {
+        let mut inner = self.inner.lock();
+        if !inner.freed.contains(&block_id) {
+            inner.fresh.insert(block_id);
+        }
+    }
+
+    /// Record that a data block is deallocated in the transaction
+    pub fn dealloc_block(&self, block_id: usize) {
+        let mut inner = self.inner.lock();
+        inner.fresh.remove(&block_id);
+        inner.freed.insert(block_id);
+    }
+}
+
+impl BlockDevice for Journal {
+    /// Read a block, which may be written in the transaction
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        match self.inner.lock().blocks.get(&block_id) {
+            Some(data) => buf.copy_from_slice(data.as_slice()),
+            None => self.device.read_block(block_id, buf),
+        }
+    }
+
+    /// Write a block into the transaction, or in place if it is allocated in the transaction
+    ///
+    /// # Panic
+    /// If the transaction outgrows the log blocks, which the operation should have checked by `fits`
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut inner = self.inner.lock();
+        if inner.fresh.contains(&block_id) {
+            drop(inner);
+            self.device.write_block(block_id, buf);
+            return;
+        }
+        if let Some(data) = inner.blocks.get_mut(&block_id) {
+            data.copy_from_slice(buf);
+            return;
+        }
+        assert!(
+            inner.blocks.len() < self.capacity,
+            "the transaction outgrows the journal"
+        );
+        let mut data = Box::new([0u8; BLOCK_SZ]);
+        data.copy_from_slice(buf);
+        inner.blocks.insert(block_id, data);
+    }
+}
@@ -1,25 +1,41 @@
 use super::{get_block_cache, BlockDevice, BLOCK_SZ};
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::{Debug, Formatter, Result};
 
 /// Magic number for sanity check
-const EFS_MAGIC: u32 = 0x3b800001;
+///
+/// The low byte is the version of the on-disk layout.
+/// Version 2 has `nlink` in `DiskInode`, which leaves 27 direct blocks.
+/// Version 3 has the 256-byte `DiskInode` with metadata, which has room for 28 direct blocks again.
+const EFS_MAGIC: u32 = 0x3b800003;
 /// The max number of direct inodes
 const INODE_DIRECT_COUNT: usize = 28;
 /// The max length of inode name
-const NAME_LENGTH_LIMIT: usize = 27;
+pub const NAME_LENGTH_LIMIT: usize = 27;
+/// The max length of a symbolic link target stored in `direct` instead of data blocks
+const INLINE_SYMLINK_LIMIT: usize = INODE_DIRECT_COUNT * 4;
 /// The max number of indirect1 inodes
 const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
 /// The max number of indirect2 inodes
 const INODE_INDIRECT2_COUNT: usize = INODE_INDIRECT1_COUNT * INODE_INDIRECT1_COUNT;
+/// The max number of indirect3 inodes
+const INODE_INDIRECT3_COUNT: usize = INODE_INDIRECT2_COUNT * INODE_INDIRECT1_COUNT;
 /// The upper bound of direct inode index
 const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
 /// The upper bound of indirect1 inode index
 const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT1_COUNT;
 /// The upper bound of indirect2 inode indexes
-#[allow(unused)]
 const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
+/// The upper bound of indirect3 inode indexes
+const INDIRECT3_BOUND: usize = INDIRECT2_BOUND + INODE_INDIRECT3_COUNT;
+/// The inode maps its blocks by an extent tree rooted in `direct` instead of block pointers
+const INODE_FLAG_EXTENTS: u32 = 1;
+/// Number of entries of the extent node in `direct`(after a header word)
+const EXTENT_ROOT_COUNT: usize = (INODE_DIRECT_COUNT - 1) / 3;
+/// Number of entries of an extent node in a block(after a header word)
+const EXTENT_BLOCK_COUNT: usize = (BLOCK_SZ / 4 - 1) / 3;
 
 /// Super block of a filesystem
 ///
@@ -48,6 +64,10 @@ pub struct SuperBlock {
     /// In the data block area, as the name implies,
     /// each allocated block holds the specific data contents of a file or directory.
     pub data_area_blocks: u32,
+    /// The first block of the journal region, which follows the data block area
+    pub journal_start: u32,
+    /// The journal region is a header block followed by log blocks(see `Journal`).
+    pub journal_blocks: u32,
 }
 
 impl Debug for SuperBlock {
@@ -58,6 +78,8 @@ impl Debug for SuperBlock {
             .field("inode_area_blocks", &self.inode_area_blocks)
             .field("data_bitmap_blocks", &self.data_bitmap_blocks)
             .field("data_area_blocks", &self.data_area_blocks)
+            .field("journal_start", &self.journal_start)
+            .field("journal_blocks", &self.journal_blocks)
             .finish()
     }
 }
@@ -75,6 +97,7 @@ impl SuperBlock {
         inode_area_blocks: u32,
         data_bitmap_blocks: u32,
         data_area_blocks: u32,
+        journal_blocks: u32,
     ) {
         *self = Self {
             magic: EFS_MAGIC,
@@ -83,6 +106,8 @@ impl SuperBlock {
             inode_area_blocks,
             data_bitmap_blocks,
             data_area_blocks,
+            journal_start: total_blocks - journal_blocks,
+            journal_blocks,
         }
     }
 
@@ -97,6 +122,38 @@ impl SuperBlock {
 pub enum DiskInodeType {
     File,
     Directory,
+    /// The target path is its content
+    SymLink,
+}
+
+impl DiskInodeType {
+    /// File type bits of `st_mode` of Linux
+    pub fn mode_bits(&self) -> u32 {
+        match self {
+            Self::File => 0o100000,
+            Self::Directory => 0o040000,
+            Self::SymLink => 0o120000,
+        }
+    }
+
+    /// Permission bits given to a new inode
+    fn default_permission(&self) -> u16 {
+        match self {
+            Self::File => 0o644,
+            Self::Directory => 0o755,
+            Self::SymLink => 0o777,
+        }
+    }
+}
+
+/// A point in time stored on the disk
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DiskTime {
+    /// Seconds since the Unix epoch
+    pub sec: u64,
+    /// Nanoseconds in the second
+    pub nsec: u64,
 }
 
 /// A indirect block
@@ -104,17 +161,138 @@ type IndirectBlock = [u32; BLOCK_SZ / 4];
 /// A data block
 type DataBlock = [u8; BLOCK_SZ];
 
+/// A run of blocks of an extent inode, or an entry of an index node of the extent tree
+///
+/// An extent node is a header word(`depth << 16 | count`) followed by `count` entries
+/// of 3 words sorted by `logical`.
+/// The entries of a leaf node(depth 0) are extents, and the entries of an index node
+/// point to the child nodes with `logical` as the first logical block under them.
+///
+/// |    name   |  type    |  size |
+/// |-----------|----------|-------|
+/// |  logical  |  u32     |  4byte|
+/// |  physical |  u32     |  4byte|
+/// |    len    |  u32     |  4byte|
+#[derive(Debug, Clone, Copy)]
+struct Extent {
+    /// The first inner block id
+    logical: u32,
+    /// The first block id, or the child node block of an index entry
+    physical: u32,
+    /// Number of blocks(0 for an index entry)
+    len: u32,
+}
+
+impl Extent {
+    /// An entry stored as 3 words
+    fn from_words(words: &[u32]) -> Self {
+        Self {
+            logical: words[0],
+            physical: words[1],
+            len: words[2],
+        }
+    }
+
+    /// An index entry pointing to the node `block` whose first entry is `first`
+    fn index(first: &Extent, block: u32) -> Self {
+        Self {
+            logical: first.logical,
+            physical: block,
+            len: 0,
+        }
+    }
+
+    /// The block id of `inner_id` if this extent covers it
+    fn block_id(&self, inner_id: u32) -> Option<u32> {
+        if (self.logical..self.logical + self.len).contains(&inner_id) {
+            Some(self.physical + inner_id - self.logical)
+        } else {
+            None
+        }
+    }
+}
+
+/// Depth and the entries of an extent node
+fn decode_node(words: &[u32]) -> (usize, Vec<Extent>) {
+    let count = (words[0] & 0xffff) as usize;
+    let entries = words[1..1 + count * 3]
+        .chunks(3)
+        .map(Extent::from_words)
+        .collect();
+    ((words[0] >> 16) as usize, entries)
+}
+
+/// Store an extent node into `words`, clearing the unused entries
+fn encode_node(words: &mut [u32], depth: usize, entries: &[Extent]) {
+    assert!(entries.len() <= (words.len() - 1) / 3);
+    words.fill(0);
+    words[0] = (depth << 16 | entries.len()) as u32;
+    for (e, w) in entries.iter().zip(words[1..].chunks_mut(3)) {
+        w.copy_from_slice(&[e.logical, e.physical, e.len]);
+    }
+}
+
+/// Depth of an extent node and its entry that may cover `inner_id`:
+/// the last one starting at or before it
+fn search_node(words: &[u32], inner_id: u32) -> (usize, Option<Extent>) {
+    let count = (words[0] & 0xffff) as usize;
+    let entry = words[1..1 + count * 3]
+        .chunks(3)
+        .take_while(|e| e[0] <= inner_id)
+        .last()
+        .map(Extent::from_words);
+    ((words[0] >> 16) as usize, entry)
+}
+
+/// Depth and the entries of the extent node in `block_id`
+fn read_node(block_id: u32, block_device: &Arc<dyn BlockDevice>) -> (usize, Vec<Extent>) {
+    get_block_cache(block_id as usize, Arc::clone(block_device))
+        .lock()
+        .read(0, |node: &IndirectBlock| decode_node(node))
+}
+
+/// Store an extent node into `block_id`
+fn write_node(
+    block_id: u32,
+    depth: usize,
+    entries: &[Extent],
+    block_device: &Arc<dyn BlockDevice>,
+) {
+    get_block_cache(block_id as usize, Arc::clone(block_device))
+        .lock()
+        .modify(0, |node: &mut IndirectBlock| {
+            encode_node(node, depth, entries)
+        });
+}
+
 /// A disk inode
 ///
-/// - 128 bytes
+/// - 256 bytes(2 inodes per block)
+///
+/// The 128-byte inode of version 1 is full with `size`, 28 direct blocks, 2 index blocks and `type_`,
+/// so `nlink` took a direct block in version 2. The owner, the mode and the timestamps take 58 bytes more,
+/// and an inode must divide a block, so the next size is 256 bytes, which gives the direct block back.
+/// The inode area is twice as large for the same number of inodes,
+/// so `EasyFileSystem::create` gives a small image fewer inodes.
 ///
-/// |    name   |  type   |  size |
-/// |-----------|---------|-------|
-/// |    size   |  u32    |  4byte|
-/// |   direct  |  u32*28 |112byte|
-/// | indirect1 |  u32    |  4byte|
-/// | indirect2 |  u32    |  4byte|
-/// |    type_  |  i32    |  4byte|
+/// |    name   |  type    |  size |
+/// |-----------|----------|-------|
+/// |    size   |  u32     |  4byte|
+/// |   direct  |  u32*28  |112byte|
+/// | indirect1 |  u32     |  4byte|
+/// | indirect2 |  u32     |  4byte|
+/// |   nlink   |  u32     |  4byte|
+/// |    uid    |  u32     |  4byte|
+/// |    gid    |  u32     |  4byte|
+/// |    mode   |  u16     |  2byte|
+/// |    type_  |  u8      |  6byte(with padding)|
+/// |   atime   | DiskTime | 16byte|
+/// |   mtime   | DiskTime | 16byte|
+/// |   ctime   | DiskTime | 16byte|
+/// | indirect3 |  u32     |  4byte|
+/// |   flags   |  u32     |  4byte|
+/// |   blocks  |  u32     |  4byte|
+/// |  reserved |  u8*52   | 52byte|
 ///
 /// #\[repr(C)\] enum
 /// - sw: store word(32bit)
@@ -131,6 +309,8 @@ pub struct DiskInode {
     /// BLOCK_SZ(512byte) * INODE_DIRECT_COUNT(28) =14,336 = 14KiB
     ///
     /// - BLOCK_SZ(512byte): 512 * 8 = 4096 bit
+    ///
+    /// An extent inode stores the root node of its extent tree here instead, and has no indirect block.
     pub direct: [u32; INODE_DIRECT_COUNT],
     /// The first level index block in the data block area of the disk layout.
     ///
@@ -141,7 +321,7 @@ pub struct DiskInode {
     ///
     /// 512byte(1block) / 4 = 128byte, 128 * BLOCK_SZ(512byte) = 64KiB of content.
     ///
-    ///  - Divide 4: To make efficient use of space, the DiskInode size is set to 128 bytes, so that each block can hold exactly four DiskInodes.
+    ///  - Divide 4: To make efficient use of space, the DiskInode size is set to 256 bytes, so that each block can hold exactly two DiskInodes.
     pub indirect1: u32,
     /// Each u32 in the secondary index block refers to a different primary index block in the data block area.
     /// Therefore, up to
@@ -150,19 +330,92 @@ pub struct DiskInode {
     ///
     /// can be indexed in the secondary indirect index.
     pub indirect2: u32,
-    /// File/Directory
+    /// Number of directory entries that refer to this inode
+    ///
+    /// A directory is also referred to by its own `.` and the `..` of its subdirectories.
+    pub nlink: u32,
+    /// User id of the owner
+    pub uid: u32,
+    /// Group id of the owner
+    pub gid: u32,
+    /// Permission bits(`0o7777`) of `st_mode`. The file type bits come from `type_`.
+    pub mode: u16,
+    /// File/Directory/SymLink
     type_: DiskInodeType,
+    /// Time of the last access of the content
+    pub atime: DiskTime,
+    /// Time of the last modification of the content
+    pub mtime: DiskTime,
+    /// Time of the last change of the content or the metadata
+    pub ctime: DiskTime,
+    /// Each u32 in the tertiary index block refers to a different secondary index block.
+    /// Therefore, up to
+    ///
+    /// 128 x 8MiB(max size that can be specified by indirect2) = 1GiB
+    ///
+    /// can be indexed in the tertiary indirect index.
+    pub indirect3: u32,
+    /// `INODE_FLAG_*`
+    flags: u32,
+    /// Number of blocks held, including the index blocks or the extent nodes
+    ///
+    /// It is counted when the blocks are allocated and freed, so it is known without walking the trees.
+    blocks: u32,
+    /// Space for future metadata
+    reserved: [u8; 52],
 }
 
+/// `DiskInode`s must fill a block exactly
+const _: () = assert!(BLOCK_SZ % core::mem::size_of::<DiskInode>() == 0);
+const _: () = assert!(core::mem::size_of::<DiskInode>() == 256);
+
 impl DiskInode {
     /// Initialize a disk inode, as well as all direct inodes under it
-    /// indirect1 and indirect2 block are allocated only when they are needed
-    pub fn initialize(&mut self, type_: DiskInodeType) {
+    /// indirect1, indirect2 and indirect3 block are allocated only when they are needed
+    ///
+    /// `nlink` counts the entry in the parent directory(and `.` of a directory).
+    /// The owner is root(0) and all the timestamps are `now`.
+    pub fn initialize(&mut self, type_: DiskInodeType, now: DiskTime) {
         self.size = 0;
         self.direct.iter_mut().for_each(|v| *v = 0);
         self.indirect1 = 0;
         self.indirect2 = 0;
+        self.indirect3 = 0;
+        self.flags = 0;
+        self.blocks = 0;
+        self.nlink = match type_ {
+            DiskInodeType::File | DiskInodeType::SymLink => 1,
+            DiskInodeType::Directory => 2,
+        };
+        self.uid = 0;
+        self.gid = 0;
+        self.mode = type_.default_permission();
         self.type_ = type_;
+        self.atime = now;
+        self.mtime = now;
+        self.ctime = now;
+        self.reserved.fill(0);
+    }
+
+    /// `st_mode` of Linux(the file type and permission bits)
+    pub fn st_mode(&self) -> u32 {
+        self.type_.mode_bits() | self.mode as u32
+    }
+
+    /// File type bits of `st_mode` of Linux
+    pub fn file_type(&self) -> u32 {
+        self.type_.mode_bits()
+    }
+
+    /// Record that the content is modified at `now`
+    pub fn touch_modified(&mut self, now: DiskTime) {
+        self.mtime = now;
+        self.ctime = now;
+    }
+
+    /// Record that the metadata(e.g. `nlink`) is changed at `now`
+    pub fn touch_changed(&mut self, now: DiskTime) {
+        self.ctime = now;
     }
 
     /// Whether this inode is a directory
@@ -171,14 +424,75 @@ impl DiskInode {
     }
 
     /// Whether this inode is a file
-    #[allow(unused)]
     pub fn is_file(&self) -> bool {
         self.type_ == DiskInodeType::File
     }
 
-    /// Return block number correspond to size.
-    pub fn data_blocks(&self) -> u32 {
-        Self::_data_blocks(self.size)
+    /// Whether this inode is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.type_ == DiskInodeType::SymLink
+    }
+
+    /// Whether the content is stored in `direct` instead of data blocks
+    ///
+    /// Only a short symbolic link(up to 112 bytes) is.
+    pub fn is_inline(&self) -> bool {
+        self.is_symlink() && self.size as usize <= INLINE_SYMLINK_LIMIT
+    }
+
+    /// Whether `len` bytes of content of a symbolic link is stored in `direct`
+    pub fn fits_inline(len: usize) -> bool {
+        len <= INLINE_SYMLINK_LIMIT
+    }
+
+    /// `direct` as the bytes of inline content
+    fn inline_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(self.direct.as_ptr() as *const u8, INLINE_SYMLINK_LIMIT)
+        }
+    }
+
+    /// `direct` as the mutable bytes of inline content
+    fn inline_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.direct.as_mut_ptr() as *mut u8,
+                INLINE_SYMLINK_LIMIT,
+            )
+        }
+    }
+
+    /// Set the content of a symbolic link stored in `direct`
+    ///
+    /// # Panic
+    /// If `data` does not fit in `direct`, or the inode is not an empty symbolic link
+    pub fn write_inline(&mut self, data: &[u8]) {
+        assert!(self.is_symlink() && self.size == 0 && Self::fits_inline(data.len()));
+        self.size = data.len() as u32;
+        self.inline_bytes_mut()[..data.len()].copy_from_slice(data);
+    }
+
+    /// Whether the blocks are mapped by an extent tree
+    pub fn uses_extents(&self) -> bool {
+        self.flags & INODE_FLAG_EXTENTS != 0
+    }
+
+    /// Map the blocks by an extent tree instead of block pointers
+    ///
+    /// An empty tree is a leaf node without entry, that is `direct` filled with zeros.
+    ///
+    /// # Panic
+    /// If the inode has content, or it is a symbolic link(which may store it in `direct`)
+    pub fn use_extents(&mut self) {
+        assert!(self.size == 0 && !self.is_symlink());
+        self.flags |= INODE_FLAG_EXTENTS;
+    }
+
+    /// Return number of blocks that this inode holds include indirect1/2/3.
+    ///
+    /// A hole(a block pointer of 0) holds no block.
+    pub fn allocated_blocks(&self) -> u32 {
+        self.blocks
     }
 
     /// Round up the size given as argument to multiples of Block size.
@@ -186,211 +500,626 @@ impl DiskInode {
         (size + BLOCK_SZ as u32 - 1) / BLOCK_SZ as u32
     }
 
-    /// Return number of blocks needed include indirect1/2.
-    pub fn total_blocks(size: u32) -> u32 {
-        let data_blocks = Self::_data_blocks(size) as usize;
-        let mut total = data_blocks as usize;
-        // indirect1
-        if data_blocks > INODE_DIRECT_COUNT {
-            total += 1;
-        }
-        // indirect2
-        if data_blocks > INDIRECT1_BOUND {
-            total += 1;
-            // sub indirect1
-            total +=
-                (data_blocks - INDIRECT1_BOUND + INODE_INDIRECT1_COUNT - 1) / INODE_INDIRECT1_COUNT;
+    /// The max size of the content
+    pub fn max_size() -> usize {
+        (INDIRECT3_BOUND * BLOCK_SZ).min(u32::MAX as usize)
+    }
+
+    /// The index trees: (root index block, levels of index blocks, the first inner id)
+    fn index_trees(&self) -> [(u32, usize, usize); 3] {
+        [
+            (self.indirect1, 1, DIRECT_BOUND),
+            (self.indirect2, 2, INDIRECT1_BOUND),
+            (self.indirect3, 3, INDIRECT2_BOUND),
+        ]
+    }
+
+    /// The root index block of the tree of `level`
+    fn index_root_mut(&mut self, level: usize) -> &mut u32 {
+        match level {
+            1 => &mut self.indirect1,
+            2 => &mut self.indirect2,
+            3 => &mut self.indirect3,
+            _ => unreachable!(),
         }
-        total as u32
     }
 
-    /// Get the number of data blocks that have to be allocated given the new size of data
-    pub fn blocks_num_needed(&self, new_size: u32) -> u32 {
-        assert!(new_size >= self.size);
-        Self::total_blocks(new_size) - Self::total_blocks(self.size)
+    /// Number of data blocks that an index tree of `level` points to
+    fn tree_capacity(level: usize) -> usize {
+        INODE_INDIRECT1_COUNT.pow(level as u32)
     }
 
     /// Get id of block given inner id
+    ///
+    /// # Return
+    /// 0 if the block is a hole(not allocated yet)
     pub fn get_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        if self.uses_extents() {
+            return self.extent_block_id(inner_id, block_device);
+        }
         let inner_id = inner_id as usize;
         if inner_id < INODE_DIRECT_COUNT {
-            self.direct[inner_id]
-        } else if inner_id < INDIRECT1_BOUND {
-            get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
-                .lock()
-                .read(0, |indirect_block: &IndirectBlock| {
-                    indirect_block[inner_id - INODE_DIRECT_COUNT]
-                })
+            return self.direct[inner_id];
+        }
+        let (root, level, first) = self
+            .index_trees()
+            .into_iter()
+            .find(|&(_, level, first)| inner_id < first + Self::tree_capacity(level))
+            .expect("file too large");
+        Self::read_tree(root, level, inner_id - first, block_device)
+    }
+
+    /// Get the `idx`-th data block of an index tree(0 if it or an index block on the way is a hole)
+    fn read_tree(root: u32, level: usize, idx: usize, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        if root == 0 {
+            return 0;
+        }
+        let child_capacity = Self::tree_capacity(level - 1);
+        let child = get_block_cache(root as usize, Arc::clone(block_device))
+            .lock()
+            .read(0, |index: &IndirectBlock| index[idx / child_capacity]);
+        if level == 1 {
+            child
         } else {
-            let last = inner_id - INDIRECT1_BOUND;
-            let indirect1 = get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
-                .lock()
-                .read(0, |indirect2: &IndirectBlock| {
-                    indirect2[last / INODE_INDIRECT1_COUNT]
-                });
-            get_block_cache(indirect1 as usize, Arc::clone(block_device))
-                .lock()
-                .read(0, |indirect1: &IndirectBlock| {
-                    indirect1[last % INODE_INDIRECT1_COUNT]
-                })
+            Self::read_tree(child, level - 1, idx % child_capacity, block_device)
         }
     }
 
-    /// Increase the size of current disk inode
-    pub fn increase_size(
+    /// Get id of block given inner id, allocating it(and the index blocks on the way) if it is a hole
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `inner_id` is beyond the index trees, or `alloc` fails => `None`
+    /// - Otherwise => The block id
+    fn alloc_block_id(
         &mut self,
-        new_size: u32,
-        new_blocks: Vec<u32>,
+        inner_id: usize,
+        alloc: &mut impl FnMut(u32, usize) -> Option<(u32, usize)>,
         block_device: &Arc<dyn BlockDevice>,
-    ) {
-        let mut current_blocks = self.data_blocks();
-        self.size = new_size;
-        let mut total_blocks = self.data_blocks();
-        let mut new_blocks = new_blocks.into_iter();
-        // fill direct
-        while current_blocks < total_blocks.min(INODE_DIRECT_COUNT as u32) {
-            self.direct[current_blocks as usize] = new_blocks.next().unwrap();
-            current_blocks += 1;
-        }
-        // alloc indirect1
-        if total_blocks > INODE_DIRECT_COUNT as u32 {
-            if current_blocks == INODE_DIRECT_COUNT as u32 {
-                self.indirect1 = new_blocks.next().unwrap();
+    ) -> Option<u32> {
+        if inner_id < INODE_DIRECT_COUNT {
+            if self.direct[inner_id] == 0 {
+                self.direct[inner_id] = alloc(0, 1)?.0;
             }
-            current_blocks -= INODE_DIRECT_COUNT as u32;
-            total_blocks -= INODE_DIRECT_COUNT as u32;
-        } else {
-            return;
+            return Some(self.direct[inner_id]);
+        }
+        let (_, level, first) = self
+            .index_trees()
+            .into_iter()
+            .find(|&(_, level, first)| inner_id < first + Self::tree_capacity(level))?;
+        let root = self.index_root_mut(level);
+        if *root == 0 {
+            *root = alloc(0, 1)?.0;
         }
-        // fill indirect1
-        get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+        Self::alloc_tree(*root, level, inner_id - first, alloc, block_device)
+    }
+
+    /// Get the `idx`-th data block of an index tree, allocating the blocks on the way
+    ///
+    /// An allocated block is filled with zeros, so a new index block has no entry.
+    /// If `alloc` fails, the blocks allocated on the way are left in the tree as empty index blocks.
+    fn alloc_tree(
+        root: u32,
+        level: usize,
+        idx: usize,
+        alloc: &mut impl FnMut(u32, usize) -> Option<(u32, usize)>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Option<u32> {
+        let child_capacity = Self::tree_capacity(level - 1);
+        let child = get_block_cache(root as usize, Arc::clone(block_device))
             .lock()
-            .modify(0, |indirect1: &mut IndirectBlock| {
-                while current_blocks < total_blocks.min(INODE_INDIRECT1_COUNT as u32) {
-                    indirect1[current_blocks as usize] = new_blocks.next().unwrap();
-                    current_blocks += 1;
+            .modify(0, |index: &mut IndirectBlock| {
+                let entry = &mut index[idx / child_capacity];
+                if *entry == 0 {
+                    *entry = alloc(0, 1)?.0;
                 }
-            });
-        // alloc indirect2
-        if total_blocks > INODE_INDIRECT1_COUNT as u32 {
-            if current_blocks == INODE_INDIRECT1_COUNT as u32 {
-                self.indirect2 = new_blocks.next().unwrap();
+                Some(*entry)
+            })?;
+        if level == 1 {
+            Some(child)
+        } else {
+            Self::alloc_tree(child, level - 1, idx % child_capacity, alloc, block_device)
+        }
+    }
+
+    /// Collect the data blocks of an index tree from the `from`-th one,
+    /// and the index blocks left empty under the root, and clear them
+    ///
+    /// An index block left empty is not written, so that freeing a large file writes few blocks.
+    ///
+    /// # Return
+    /// Whether the root has no entry left(and should be deallocated too)
+    fn drop_tree(
+        root: u32,
+        level: usize,
+        from: usize,
+        v: &mut Vec<u32>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> bool {
+        let child_capacity = Self::tree_capacity(level - 1);
+        let mut index = get_block_cache(root as usize, Arc::clone(block_device))
+            .lock()
+            .read(0, |index: &IndirectBlock| *index);
+        let mut changed = false;
+        for (i, child) in index.iter_mut().enumerate() {
+            let from_in_child = from.saturating_sub(i * child_capacity);
+            if *child == 0 || from_in_child >= child_capacity {
+                continue;
             }
-            current_blocks -= INODE_INDIRECT1_COUNT as u32;
-            total_blocks -= INODE_INDIRECT1_COUNT as u32;
+            if level == 1 || Self::drop_tree(*child, level - 1, from_in_child, v, block_device) {
+                v.push(*child);
+                *child = 0;
+                changed = true;
+            }
+        }
+        let empty = index.iter().all(|&block_id| block_id == 0);
+        if changed && !empty {
+            get_block_cache(root as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |block: &mut IndirectBlock| *block = index);
+        }
+        empty
+    }
+
+    /// Allocate the data blocks for `[start, end)` bytes of content
+    /// and increase the size to `end` if it is larger
+    ///
+    /// The blocks before `start` beyond the current size stay holes.
+    ///
+    /// # Parameters
+    /// - `alloc`: Allocate up to `max_len` contiguous data blocks(filled with zeros),
+    ///   from the block `goal` if it is not 0 and free, and return the first id and the number of them,
+    ///   or `None` if there is no free block.
+    ///   An extent inode asks for a whole hole at once to make large extents.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `end` exceeds the max size => `None`, and nothing is allocated
+    /// - If `alloc` fails => `None`, and the size is left as it is.
+    ///   The blocks allocated so far stay in the inode(and are counted) and are freed with it,
+    ///   but the caller should make sure that [`DiskInode::blocks_needed`] blocks are free,
+    ///   because an extent node that fails to split loses the entries to be moved.
+    /// - Otherwise => `Some(())`
+    pub fn allocate_range(
+        &mut self,
+        start: usize,
+        end: usize,
+        mut alloc: impl FnMut(u32, usize) -> Option<(u32, usize)>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Option<()> {
+        if start > end || end > Self::max_size() {
+            return None;
+        }
+        let (start_block, end_block) = (start / BLOCK_SZ, (end + BLOCK_SZ - 1) / BLOCK_SZ);
+        let mut allocated = 0;
+        let mut alloc = |goal, max_len| {
+            let run = alloc(goal, max_len)?;
+            allocated += run.1;
+            Some(run)
+        };
+        let result = if self.uses_extents() {
+            self.allocate_extents(
+                start_block as u32,
+                end_block as u32,
+                &mut alloc,
+                block_device,
+            )
         } else {
-            return;
+            (start_block..end_block).try_for_each(|inner_id| {
+                self.alloc_block_id(inner_id, &mut alloc, block_device)
+                    .map(|_| ())
+            })
+        };
+        self.blocks += allocated as u32;
+        result?;
+        self.size = self.size.max(end as u32);
+        Some(())
+    }
+
+    /// Number of blocks that `allocate_range` may allocate for `[start, end)` bytes at most,
+    /// including the index blocks or the extent nodes
+    pub fn blocks_needed(
+        &self,
+        start: usize,
+        end: usize,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> usize {
+        let holes = self.holes(start, end, block_device);
+        if holes == 0 {
+            return 0;
+        }
+        if self.uses_extents() {
+            // each extent may split a node on every level and grow the tree by a level
+            let depth = decode_node(&self.direct).0;
+            holes * (depth + 3)
+        } else {
+            // the index blocks of each level of the three trees
+            holes + 2 * (holes / INODE_INDIRECT1_COUNT) + 6
+        }
+    }
+
+    /// Number of blocks that already exist and a write of `[start, end)` bytes may modify at most,
+    /// that is the data blocks overwritten and the index blocks or the extent nodes that get new blocks
+    ///
+    /// The inode itself and the blocks allocated by the write are not counted.
+    pub fn blocks_modified(
+        &self,
+        start: usize,
+        end: usize,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> usize {
+        let (start_block, end_block) = (start / BLOCK_SZ, (end + BLOCK_SZ - 1) / BLOCK_SZ);
+        let holes = self.holes(start, end, block_device);
+        let overwritten = end_block - start_block - holes;
+        if holes == 0 {
+            return overwritten;
+        }
+        if self.uses_extents() {
+            // on each level, a node that gets an extent starts with a block overwritten,
+            // or it is the one before them
+            let depth = decode_node(&self.direct).0;
+            overwritten + depth * (overwritten + 1)
+        } else {
+            // the index blocks of each level of the three trees
+            overwritten + 2 * ((end_block - start_block) / INODE_INDIRECT1_COUNT) + 6
+        }
+    }
+
+    /// Number of holes in the blocks of `[start, end)` bytes
+    ///
+    /// The extents or the index blocks are read once for the range, not for each block.
+    fn holes(&self, start: usize, end: usize, block_device: &Arc<dyn BlockDevice>) -> usize {
+        let (start_block, end_block) = (start / BLOCK_SZ, (end + BLOCK_SZ - 1) / BLOCK_SZ);
+        if start_block >= end_block {
+            return 0;
+        }
+        if self.uses_extents() {
+            return self.extent_holes(start_block as u32, end_block as u32, block_device);
+        }
+        let direct =
+            &self.direct[start_block.min(INODE_DIRECT_COUNT)..end_block.min(INODE_DIRECT_COUNT)];
+        let mut mapped = direct.iter().filter(|&&block_id| block_id != 0).count();
+        for (root, level, first) in self.index_trees() {
+            let (from, to) = (
+                start_block.max(first),
+                end_block.min(first + Self::tree_capacity(level)),
+            );
+            if from < to {
+                mapped += Self::count_tree(root, level, from - first, to - first, block_device);
+            }
+        }
+        end_block - start_block - mapped
+    }
+
+    /// Number of holes in `[start_block, end_block)` of the extent tree, looking up each extent once
+    fn extent_holes(
+        &self,
+        start_block: u32,
+        end_block: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> usize {
+        let mut holes = 0;
+        let mut inner_id = start_block;
+        while inner_id < end_block {
+            match self.next_extent(inner_id, block_device) {
+                Some(e) if e.logical <= inner_id => inner_id = e.logical + e.len,
+                Some(e) => {
+                    let hole_end = e.logical.min(end_block);
+                    holes += (hole_end - inner_id) as usize;
+                    inner_id = hole_end;
+                }
+                None => {
+                    holes += (end_block - inner_id) as usize;
+                    break;
+                }
+            }
+        }
+        holes
+    }
+
+    /// Number of the data blocks in `[from, to)` of an index tree that are not holes
+    fn count_tree(
+        root: u32,
+        level: usize,
+        from: usize,
+        to: usize,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> usize {
+        if root == 0 {
+            return 0;
         }
-        // fill indirect2 from (a0, b0) -> (a1, b1)
-
-        // old indirect1 inode group
-        let mut a0 = current_blocks as usize / INODE_INDIRECT1_COUNT;
-        // old indirect1 inode position
-        let mut b0 = current_blocks as usize % INODE_INDIRECT1_COUNT;
-        // new indirect1 inode group
-        let a1 = total_blocks as usize / INODE_INDIRECT1_COUNT;
-        // new indirect1 inode position
-        let b1 = total_blocks as usize % INODE_INDIRECT1_COUNT;
-        // alloc low-level indirect1
-        get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+        let child_capacity = Self::tree_capacity(level - 1);
+        let index = get_block_cache(root as usize, Arc::clone(block_device))
             .lock()
-            .modify(0, |indirect2: &mut IndirectBlock| {
-                while (a0 < a1) || (a0 == a1 && b0 < b1) {
-                    if b0 == 0 {
-                        indirect2[a0] = new_blocks.next().unwrap();
-                    }
-                    // fill current
-                    get_block_cache(indirect2[a0] as usize, Arc::clone(block_device))
-                        .lock()
-                        .modify(0, |indirect1: &mut IndirectBlock| {
-                            indirect1[b0] = new_blocks.next().unwrap();
-                        });
-                    // move to next
-                    b0 += 1;
-                    // When the maximum position in the inode group is reached,
-                    // the index is shifted to the next group.
-                    if b0 == INODE_INDIRECT1_COUNT {
-                        b0 = 0;
-                        a0 += 1;
-                    }
+            .read(0, |index: &IndirectBlock| *index);
+        let children = index[from / child_capacity..(to + child_capacity - 1) / child_capacity]
+            .iter()
+            .zip(from / child_capacity..);
+        if level == 1 {
+            return children.filter(|&(&child, _)| child != 0).count();
+        }
+        children
+            .map(|(&child, i)| {
+                let first = i * child_capacity;
+                Self::count_tree(
+                    child,
+                    level - 1,
+                    from.max(first) - first,
+                    to.min(first + child_capacity) - first,
+                    block_device,
+                )
+            })
+            .sum()
+    }
+
+    /// Block id of `inner_id` in the extent tree(0 if it is a hole)
+    fn extent_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        let (mut depth, mut entry) = search_node(&self.direct, inner_id);
+        while depth > 0 {
+            match entry {
+                Some(index) => {
+                    (depth, entry) =
+                        get_block_cache(index.physical as usize, Arc::clone(block_device))
+                            .lock()
+                            .read(0, |node: &IndirectBlock| search_node(node, inner_id));
                 }
+                None => return 0,
+            }
+        }
+        entry.and_then(|e| e.block_id(inner_id)).unwrap_or(0)
+    }
+
+    /// The extent covering `inner_id`, or the first one after it
+    fn next_extent(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> Option<Extent> {
+        let (depth, entries) = decode_node(&self.direct);
+        Self::next_extent_in(depth, &entries, inner_id, block_device)
+    }
+
+    /// The extent covering `inner_id`, or the first one after it, under an extent node of `depth` with `entries`
+    fn next_extent_in(
+        depth: usize,
+        entries: &[Extent],
+        inner_id: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Option<Extent> {
+        if depth == 0 {
+            return entries
+                .iter()
+                .find(|e| e.logical + e.len > inner_id)
+                .copied();
+        }
+        // the child that may cover `inner_id`, then the next one if all of its extents are before it
+        let i = entries
+            .partition_point(|e| e.logical <= inner_id)
+            .saturating_sub(1);
+        entries[i..].iter().find_map(|index| {
+            let (child_depth, children) = read_node(index.physical, block_device);
+            Self::next_extent_in(child_depth, &children, inner_id, block_device)
+        })
+    }
+
+    /// Allocate the holes in `[start_block, end_block)` of the extent tree, each hole as few extents as possible
+    ///
+    /// The tree is searched once for each extent, not for each block.
+    ///
+    /// # Return
+    /// `None` if `alloc` fails
+    fn allocate_extents(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+        alloc: &mut impl FnMut(u32, usize) -> Option<(u32, usize)>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Option<()> {
+        let mut inner_id = start_block;
+        // continue the previous block on the disk if possible, so that the extents merge
+        let mut goal = match inner_id {
+            0 => 0,
+            _ => match self.extent_block_id(inner_id - 1, block_device) {
+                0 => 0,
+                prev => prev + 1,
+            },
+        };
+        while inner_id < end_block {
+            let hole_end = match self.next_extent(inner_id, block_device) {
+                Some(e) if e.logical <= inner_id => {
+                    // skip the blocks already mapped
+                    inner_id = e.logical + e.len;
+                    goal = e.physical + e.len;
+                    continue;
+                }
+                Some(e) => e.logical.min(end_block),
+                None => end_block,
+            };
+            while inner_id < hole_end {
+                let (physical, len) = alloc(goal, (hole_end - inner_id) as usize)?;
+                let extent = Extent {
+                    logical: inner_id,
+                    physical,
+                    len: len as u32,
+                };
+                self.insert_extent(extent, alloc, block_device)?;
+                inner_id += len as u32;
+                goal = physical + len as u32;
+            }
+        }
+        Some(())
+    }
+
+    /// Insert an extent into the extent tree, growing the tree by a level when the root overflows
+    ///
+    /// # Return
+    /// `None` if `alloc` fails, and the root is left as it is
+    fn insert_extent(
+        &mut self,
+        extent: Extent,
+        alloc: &mut impl FnMut(u32, usize) -> Option<(u32, usize)>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Option<()> {
+        let (mut depth, mut entries) = decode_node(&self.direct);
+        Self::insert_into_node(depth, &mut entries, extent, alloc, block_device)?;
+        if entries.len() > EXTENT_ROOT_COUNT {
+            // move the root down to a new block
+            let child = alloc(0, 1)?.0;
+            write_node(child, depth, &entries, block_device);
+            entries = vec![Extent::index(&entries[0], child)];
+            depth += 1;
+        }
+        encode_node(&mut self.direct, depth, &entries);
+        Some(())
+    }
+
+    /// Insert an extent into an extent node of `depth` with `entries`
+    ///
+    /// A leaf extends the previous extent instead if the new one continues it.
+    /// A child node that overflows is split in half, and the new half is added to `entries`,
+    /// which may overflow in turn.
+    ///
+    /// # Return
+    /// `None` if `alloc` fails, and the child node is left as it is
+    fn insert_into_node(
+        depth: usize,
+        entries: &mut Vec<Extent>,
+        extent: Extent,
+        alloc: &mut impl FnMut(u32, usize) -> Option<(u32, usize)>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Option<()> {
+        let pos = entries.partition_point(|e| e.logical <= extent.logical);
+        if depth == 0 {
+            if let Some(prev) = pos.checked_sub(1).map(|i| &mut entries[i]) {
+                if prev.logical + prev.len == extent.logical
+                    && prev.physical + prev.len == extent.physical
+                {
+                    prev.len += extent.len;
+                    return Some(());
+                }
+            }
+            entries.insert(pos, extent);
+            return Some(());
+        }
+        // the child covering the extent, or the first one if the extent is before all of them
+        let i = pos.saturating_sub(1);
+        let child = entries[i].physical;
+        let (child_depth, mut children) = read_node(child, block_device);
+        Self::insert_into_node(child_depth, &mut children, extent, alloc, block_device)?;
+        if children.len() > EXTENT_BLOCK_COUNT {
+            let sibling = alloc(0, 1)?.0;
+            let right = children.split_off(children.len() / 2);
+            write_node(sibling, child_depth, &right, block_device);
+            entries.insert(i + 1, Extent::index(&right[0], sibling));
+        }
+        write_node(child, child_depth, &children, block_device);
+        entries[i] = Extent::index(&children[0], child);
+        Some(())
+    }
+
+    /// Collect the blocks from the `keep`-th inner block under an extent node,
+    /// and the node blocks left empty below it, and remove them from `entries`
+    fn drop_extents(
+        depth: usize,
+        entries: &mut Vec<Extent>,
+        keep: u32,
+        v: &mut Vec<u32>,
+        block_device: &Arc<dyn BlockDevice>,
+    ) {
+        if depth == 0 {
+            entries.retain_mut(|e| {
+                let kept = keep.saturating_sub(e.logical).min(e.len);
+                v.extend(e.physical + kept..e.physical + e.len);
+                e.len = kept;
+                kept > 0
             });
+            return;
+        }
+        // the children before the one covering `keep` lose nothing
+        let from = entries
+            .partition_point(|e| e.logical < keep)
+            .saturating_sub(1);
+        let mut tail = entries.split_off(from);
+        tail.retain(|e| {
+            let (child_depth, mut children) = read_node(e.physical, block_device);
+            Self::drop_extents(child_depth, &mut children, keep, v, block_device);
+            if children.is_empty() {
+                v.push(e.physical);
+                false
+            } else {
+                write_node(e.physical, child_depth, &children, block_device);
+                true
+            }
+        });
+        entries.append(&mut tail);
     }
 
-    /// Clear size to zero and return blocks that should be deallocated.
-    /// We will clear the block contents to zero later.
-    pub fn clear_size(&mut self, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
+    /// Change the size and return blocks that should be deallocated.
+    ///
+    /// Shrinking drops the data blocks beyond `new_size` and the index blocks left empty,
+    /// and zeroes the rest of the last block so that growing again reads zeros.
+    /// Growing allocates nothing: the new content is a hole.
+    /// The blocks are cleared to zero when they are allocated again.
+    pub fn truncate(&mut self, new_size: u32, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
         let mut v: Vec<u32> = Vec::new();
-        let mut data_blocks = self.data_blocks() as usize;
-        self.size = 0;
-        let mut current_blocks = 0usize;
-        // direct
-        while current_blocks < data_blocks.min(INODE_DIRECT_COUNT) {
-            v.push(self.direct[current_blocks]);
-            self.direct[current_blocks] = 0;
-            current_blocks += 1;
-        }
-        // indirect1 block
-        if data_blocks > INODE_DIRECT_COUNT {
-            v.push(self.indirect1);
-            data_blocks -= INODE_DIRECT_COUNT;
-            current_blocks = 0;
-        } else {
+        if new_size >= self.size {
+            assert!(!self.is_inline() || Self::fits_inline(new_size as usize));
+            self.size = new_size;
             return v;
         }
-        // indirect1
-        get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
-            .lock()
-            .modify(0, |indirect1: &mut IndirectBlock| {
-                while current_blocks < data_blocks.min(INODE_INDIRECT1_COUNT) {
-                    v.push(indirect1[current_blocks]);
-                    //indirect1[current_blocks] = 0;
-                    current_blocks += 1;
-                }
-            });
-        self.indirect1 = 0;
-        // indirect2 block
-        if data_blocks > INODE_INDIRECT1_COUNT {
-            v.push(self.indirect2);
-            data_blocks -= INODE_INDIRECT1_COUNT;
-        } else {
+        if self.is_inline() {
+            self.inline_bytes_mut()[new_size as usize..].fill(0);
+            self.size = new_size;
             return v;
         }
-        // indirect2
-        assert!(data_blocks <= INODE_INDIRECT2_COUNT);
-        let a1 = data_blocks / INODE_INDIRECT1_COUNT;
-        let b1 = data_blocks % INODE_INDIRECT1_COUNT;
-        get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
-            .lock()
-            .modify(0, |indirect2: &mut IndirectBlock| {
-                // full indirect1 blocks
-                for entry in indirect2.iter_mut().take(a1) {
-                    v.push(*entry);
-                    get_block_cache(*entry as usize, Arc::clone(block_device))
-                        .lock()
-                        .modify(0, |indirect1: &mut IndirectBlock| {
-                            for entry in indirect1.iter() {
-                                v.push(*entry);
-                            }
-                        });
+        let tail = new_size as usize % BLOCK_SZ;
+        if tail != 0 {
+            let block_id = self.get_block_id(new_size / BLOCK_SZ as u32, block_device);
+            if block_id != 0 {
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .modify(0, |data_block: &mut DataBlock| data_block[tail..].fill(0));
+            }
+        }
+        self.size = new_size;
+        // the data blocks to keep
+        let keep = Self::_data_blocks(new_size) as usize;
+        if self.uses_extents() {
+            let (depth, mut entries) = decode_node(&self.direct);
+            Self::drop_extents(depth, &mut entries, keep as u32, &mut v, block_device);
+            // an empty tree is a leaf again
+            let depth = if entries.is_empty() { 0 } else { depth };
+            encode_node(&mut self.direct, depth, &entries);
+        } else {
+            // direct
+            for block_id in self.direct.iter_mut().skip(keep) {
+                if *block_id != 0 {
+                    v.push(*block_id);
+                    *block_id = 0;
                 }
-                // last indirect1 block
-                if b1 > 0 {
-                    v.push(indirect2[a1]);
-                    get_block_cache(indirect2[a1] as usize, Arc::clone(block_device))
-                        .lock()
-                        .modify(0, |indirect1: &mut IndirectBlock| {
-                            for entry in indirect1.iter().take(b1) {
-                                v.push(*entry);
-                            }
-                        });
-                    //indirect2[a1] = 0;
+            }
+            // indirect1/2/3
+            for (root, level, first) in self.index_trees() {
+                if root != 0
+                    && Self::drop_tree(
+                        root,
+                        level,
+                        keep.saturating_sub(first),
+                        &mut v,
+                        block_device,
+                    )
+                {
+                    v.push(root);
+                    *self.index_root_mut(level) = 0;
                 }
-            });
-        self.indirect2 = 0;
+            }
+        }
+        self.blocks -= v.len() as u32;
         v
     }
 
+    /// Clear size to zero and return blocks that should be deallocated.
+    /// The blocks are cleared to zero when they are allocated again.
+    pub fn clear_size(&mut self, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
+        self.truncate(0, block_device)
+    }
+
     /// Read data from current disk inode
     ///
     /// # parameters
@@ -415,6 +1144,10 @@ impl DiskInode {
         if start >= end {
             return 0;
         }
+        if self.is_inline() {
+            buf[..end - start].copy_from_slice(&self.inline_bytes()[start..end]);
+            return end - start;
+        }
         let mut start_block = start / BLOCK_SZ;
         let mut read_size = 0usize;
         loop {
@@ -429,17 +1162,20 @@ impl DiskInode {
             // read and update read size
             let block_read_size = end_current_block - start;
             let dst = &mut buf[read_size..read_size + block_read_size];
-            get_block_cache(
-                self.get_block_id(start_block as u32, block_device) as usize,
-                Arc::clone(block_device),
-            )
-            .lock()
-            .read(0, |data_block: &DataBlock| {
-                // data_block is 1Block.
-                // `start % BLOCK_SZ` index of 1Block
-                let src = &data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_read_size];
-                dst.copy_from_slice(src);
-            });
+            let block_id = self.get_block_id(start_block as u32, block_device);
+            if block_id == 0 {
+                // a hole
+                dst.fill(0);
+            } else {
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |data_block: &DataBlock| {
+                        // data_block is 1Block.
+                        // `start % BLOCK_SZ` index of 1Block
+                        let src = &data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_read_size];
+                        dst.copy_from_slice(src);
+                    });
+            }
             read_size += block_read_size;
             // move to next block
             if end_current_block == end {
@@ -452,7 +1188,7 @@ impl DiskInode {
     }
 
     /// Write data into current disk inode
-    /// size must be adjusted properly beforehand
+    /// the blocks must be allocated by `allocate_range` beforehand
     ///
     /// # parameters
     /// - `offset`: The starting point of the block to be read.
@@ -460,7 +1196,8 @@ impl DiskInode {
     /// - `block_device`: The structure in which the methods of the `File` trait are implemented.
     ///                   The `read` method defined in the `FIle` trait is read inside the function.
     /// # Panic
-    /// 1st argument `offset` is greater than `offset + buf length` or  `self.size(file/dir size)`
+    /// - 1st argument `offset` is greater than `offset + buf length` or  `self.size(file/dir size)`
+    /// - A block to be written is a hole
     ///
     /// # Return
     /// Length of data that has been written
@@ -473,6 +1210,9 @@ impl DiskInode {
         let mut start = offset;
         let end = (offset + buf.len()).min(self.size as usize);
         assert!(start <= end);
+        if start == end {
+            return 0;
+        }
         let mut start_block = start / BLOCK_SZ;
         let mut write_size = 0usize;
         loop {
@@ -481,16 +1221,16 @@ impl DiskInode {
             end_current_block = end_current_block.min(end);
             // write and update write size
             let block_write_size = end_current_block - start;
-            get_block_cache(
-                self.get_block_id(start_block as u32, block_device) as usize,
-                Arc::clone(block_device),
-            )
-            .lock()
-            .modify(0, |data_block: &mut DataBlock| {
-                let src = &buf[write_size..write_size + block_write_size];
-                let dst = &mut data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_write_size];
-                dst.copy_from_slice(src);
-            });
+            let block_id = self.get_block_id(start_block as u32, block_device);
+            assert_ne!(block_id, 0, "writing to a hole");
+            get_block_cache(block_id as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    let src = &buf[write_size..write_size + block_write_size];
+                    let dst =
+                        &mut data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_write_size];
+                    dst.copy_from_slice(src);
+                });
             write_size += block_write_size;
             // move to next block
             if end_current_block == end {
@@ -555,3 +1295,41 @@ impl DirEntry {
         self.inode_number
     }
 }
+
+/// Number of log blocks that a journal header can record
+pub const JOURNAL_CAPACITY: usize = BLOCK_SZ / 4 - 1;
+
+/// The first block of the journal region
+///
+/// `count` is 0 unless a transaction is committed to the log blocks and not checkpointed yet.
+/// It is written in a single block write, which commits the transaction at once.
+#[repr(C)]
+pub struct JournalHeader {
+    /// Number of logged blocks
+    pub count: u32,
+    /// Home block id of each log block
+    pub block_ids: [u32; JOURNAL_CAPACITY],
+}
+
+/// A journal header must fill a block exactly
+const _: () = assert!(core::mem::size_of::<JournalHeader>() == BLOCK_SZ);
+
+impl JournalHeader {
+    /// Create a header without logged block
+    pub fn empty() -> Self {
+        Self {
+            count: 0,
+            block_ids: [0; JOURNAL_CAPACITY],
+        }
+    }
+
+    /// Serialize into bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const _ as usize as *const u8, BLOCK_SZ) }
+    }
+
+    /// Serialize into mutable bytes
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self as *mut _ as usize as *mut u8, BLOCK_SZ) }
+    }
+}
@@ -6,6 +6,7 @@ mod bitmap;
 mod block_cache;
 mod block_dev;
 mod efs;
+mod journal;
 mod layout;
 mod vfs;
 /// 1 sector == 512byte
@@ -15,8 +16,10 @@ mod vfs;
 /// `easy-fs`'s implementation equates blocks and sectors to 512 bytes.
 pub const BLOCK_SZ: usize = 512;
 use bitmap::Bitmap;
-use block_cache::{block_cache_sync_all, get_block_cache};
+use block_cache::{block_cache_drop_all, block_cache_sync_all, get_block_cache};
 pub use block_dev::BlockDevice;
 pub use efs::EasyFileSystem;
+use journal::Journal;
+pub use layout::DiskTime;
 use layout::*;
-pub use vfs::Inode;
+pub use vfs::{DirEntryInfo, Inode, Metadata};
@@ -1,15 +1,59 @@
 //! Index node layer providing file-related system calls
 use super::{
-    block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType, DiskTime, EasyFileSystem,
+    BLOCK_SZ, DIRENT_SZ, NAME_LENGTH_LIMIT,
 };
 use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
 
+/// Seconds after which a read updates `atime` anyway
+const ATIME_UPDATE_INTERVAL: u64 = 24 * 60 * 60;
+
+/// Metadata of an inode, which `stat` of Linux reports
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    /// Inode number
+    pub inode_id: u32,
+    /// File type and permission bits(`st_mode` of Linux)
+    pub mode: u32,
+    /// Number of directory entries that refer to the inode
+    pub nlink: u32,
+    /// User id of the owner
+    pub uid: u32,
+    /// Group id of the owner
+    pub gid: u32,
+    /// Bytes of the content
+    pub size: u64,
+    /// Number of blocks(`BLOCK_SZ`) allocated to the content, including indirect blocks
+    pub blocks: u64,
+    /// Time of the last access
+    pub atime: DiskTime,
+    /// Time of the last modification of the content
+    pub mtime: DiskTime,
+    /// Time of the last change of the content or the metadata
+    pub ctime: DiskTime,
+}
+
+/// A directory entry reported by [`Inode::read_dir`]
+#[derive(Debug, Clone)]
+pub struct DirEntryInfo {
+    /// Inode number of the entry
+    pub inode_id: u32,
+    /// File type bits of `st_mode` of Linux(e.g. `0o040000` for a directory)
+    pub file_type: u32,
+    /// Name of the entry
+    pub name: String,
+}
+
 /// Virtual filesystem layer over easy-fs
+///
+/// The inode is freed when it is removed from every directory(`nlink` is 0)
+/// and the last `Inode` of it is dropped.
 pub struct Inode {
+    inode_id: u32,
     block_id: usize,
     block_offset: usize,
     fs: Arc<Mutex<EasyFileSystem>>,
@@ -17,18 +61,19 @@ pub struct Inode {
 }
 
 impl Inode {
-    /// Create a vfs inode
-    pub fn new(
-        block_id: u32,
-        block_offset: usize,
-        fs: Arc<Mutex<EasyFileSystem>>,
-        block_device: Arc<dyn BlockDevice>,
-    ) -> Self {
+    /// Create a vfs inode of `inode_id`
+    ///
+    /// # Parameters
+    /// - `fs`: The locked `efs`
+    pub fn new(inode_id: u32, fs: &mut EasyFileSystem, efs: Arc<Mutex<EasyFileSystem>>) -> Self {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        fs.open_inode(inode_id);
         Self {
+            inode_id,
             block_id: block_id as usize,
             block_offset,
-            fs,
-            block_device,
+            fs: efs,
+            block_device: Arc::clone(&fs.block_device),
         }
     }
 
@@ -45,127 +90,635 @@ impl Inode {
             .modify(self.block_offset, f)
     }
 
-    /// Find inode under a disk inode by name
-    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+    /// Call a function over the disk inode of `inode_id` to read it
+    fn read_disk_inode_of<V>(
+        &self,
+        fs: &EasyFileSystem,
+        inode_id: u32,
+        f: impl FnOnce(&DiskInode) -> V,
+    ) -> V {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .read(block_offset, f)
+    }
+    /// Call a function over the disk inode of `inode_id` to modify it
+    fn modify_disk_inode_of<V>(
+        &self,
+        fs: &EasyFileSystem,
+        inode_id: u32,
+        f: impl FnOnce(&mut DiskInode) -> V,
+    ) -> V {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(block_offset, f)
+    }
+
+    /// Get the directory entries in use(an empty name means a removed entry) of a disk inode
+    ///
+    /// # Return
+    /// (index of the entry, entry)
+    fn dirents(&self, disk_inode: &DiskInode) -> Vec<(usize, DirEntry)> {
+        self.dirents_from(disk_inode, 0).collect()
+    }
+
+    /// Iterate over the directory entries in use of a disk inode from the `from`-th entry
+    ///
+    /// # Return
+    /// (index of the entry, entry)
+    fn dirents_from<'a>(
+        &'a self,
+        disk_inode: &'a DiskInode,
+        from: usize,
+    ) -> impl Iterator<Item = (usize, DirEntry)> + 'a {
         // assert it is a directory
         assert!(disk_inode.is_dir());
         let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-        let mut dirent = DirEntry::empty();
-        for i in 0..file_count {
+        (from..file_count).filter_map(move |i| {
+            let mut dirent = DirEntry::empty();
             assert_eq!(
                 disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device,),
                 DIRENT_SZ,
             );
-            if dirent.name() == name {
-                return Some(dirent.inode_number() as u32);
-            }
-        }
-        None
+            (!dirent.name().is_empty()).then_some((i, dirent))
+        })
+    }
+
+    /// Find the directory entry under a disk inode by name
+    ///
+    /// # Return
+    /// (index of the entry, inode id)
+    fn find_dirent(&self, name: &str, disk_inode: &DiskInode) -> Option<(usize, u32)> {
+        self.dirents(disk_inode)
+            .into_iter()
+            .find(|(_, dirent)| dirent.name() == name)
+            .map(|(idx, dirent)| (idx, dirent.inode_number()))
+    }
+
+    /// Find inode under a disk inode by name
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        self.find_dirent(name, disk_inode)
+            .map(|(_, inode_id)| inode_id)
+    }
+
+    /// Overwrite the `idx`-th directory entry of the directory `disk_inode`
+    fn write_dirent(
+        &self,
+        disk_inode: &mut DiskInode,
+        idx: usize,
+        dirent: &DirEntry,
+        fs: &EasyFileSystem,
+    ) {
+        disk_inode.write_at(idx * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+        disk_inode.touch_modified(fs.now());
+    }
+
+    /// Whether this inode is a directory
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+
+    /// Whether this inode is a regular file
+    pub fn is_file(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_file())
+    }
+
+    /// Number of directory entries that refer to this inode
+    pub fn nlink(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.nlink)
+    }
+
+    /// Bytes of the content
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
     }
 
     /// Find inode under current inode by name
+    ///
+    /// `None` if the current inode is not a directory.
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
-        let fs = self.fs.lock();
+        let mut fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
-            self.find_inode_id(name, disk_inode).map(|inode_id| {
-                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
-                Arc::new(Self::new(
-                    block_id,
-                    block_offset,
-                    self.fs.clone(),
-                    self.block_device.clone(),
-                ))
-            })
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_inode_id(name, disk_inode)
+                .map(|inode_id| Arc::new(Self::new(inode_id, &mut fs, self.fs.clone())))
         })
     }
 
-    /// Increase the size of a disk inode
-    fn increase_size(
+    /// Allocate the data blocks of a disk inode for `[start, end)` bytes to be written,
+    /// increasing the size to `end` if it is larger
+    ///
+    /// The free blocks are checked beforehand, so nothing is allocated if they are not enough.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `end` exceeds the max file size, or the disk is full => `None`
+    /// - Otherwise => `Some(())`
+    fn allocate_range(
         &self,
-        new_size: u32,
+        start: usize,
+        end: usize,
         disk_inode: &mut DiskInode,
         fs: &mut MutexGuard<EasyFileSystem>,
-    ) {
-        if new_size < disk_inode.size {
-            return;
-        }
-        let blocks_needed = disk_inode.blocks_num_needed(new_size);
-        let mut v: Vec<u32> = Vec::new();
-        for _ in 0..blocks_needed {
-            v.push(fs.alloc_data());
+    ) -> Option<()> {
+        if end > DiskInode::max_size()
+            || disk_inode.blocks_needed(start, end, &self.block_device) > fs.free_data_blocks()
+        {
+            return None;
         }
-        disk_inode.increase_size(new_size, v, &self.block_device);
+        disk_inode.allocate_range(
+            start,
+            end,
+            |goal, max_len| fs.alloc_data_run(goal, max_len),
+            &self.block_device,
+        )
     }
 
-    /// Create inode under current inode by name
-    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
-        let mut fs = self.fs.lock();
-        let op = |root_inode: &DiskInode| {
-            // assert it is a directory
-            assert!(root_inode.is_dir());
-            // has the file been created?
-            self.find_inode_id(name, root_inode)
+    /// Add a directory entry to the directory `disk_inode`
+    ///
+    /// The first entry removed by `unlink`/`rmdir` is reused, otherwise it is appended.
+    ///
+    /// # Return
+    /// `None` if the disk is full, and the directory is left as it is
+    fn add_dirent(
+        &self,
+        disk_inode: &mut DiskInode,
+        dirent: &DirEntry,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Option<()> {
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let mut slot = DirEntry::empty();
+        let free_slot = (0..file_count).find(|&i| {
+            disk_inode.read_at(DIRENT_SZ * i, slot.as_bytes_mut(), &self.block_device);
+            slot.name().is_empty()
+        });
+        let idx = match free_slot {
+            Some(idx) => idx,
+            None => {
+                // increase size
+                self.allocate_range(
+                    file_count * DIRENT_SZ,
+                    (file_count + 1) * DIRENT_SZ,
+                    disk_inode,
+                    fs,
+                )?;
+                file_count
+            }
         };
-        if self.read_disk_inode(op).is_some() {
+        // write dirent
+        self.write_dirent(disk_inode, idx, dirent, fs);
+        Some(())
+    }
+
+    /// Whether a new entry `name` can be added to the directory `disk_inode`
+    ///
+    /// A removed directory(`nlink` is 0) can not have new entries.
+    fn can_add_dirent(&self, name: &str, disk_inode: &DiskInode) -> bool {
+        !name.is_empty()
+            && name.len() <= NAME_LENGTH_LIMIT
+            && disk_inode.is_dir()
+            && disk_inode.nlink > 0
+            && self.find_inode_id(name, disk_inode).is_none()
+    }
+
+    /// Create a file, a directory or a symbolic link under current inode by name
+    ///
+    /// A new directory gets `.`(itself) and `..`(current inode).
+    /// A new symbolic link gets `content` as its target.
+    /// A new file or directory maps its blocks by an extent tree if the filesystem is set so.
+    /// The new inode is freed again if the disk is full.
+    ///
+    /// # Return
+    /// `None` if `name` can not be added(see [`Inode::can_create`]), or all the inodes are in use
+    /// or the disk is full
+    fn create_inode(&self, name: &str, type_: DiskInodeType, content: &[u8]) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        if !self.read_disk_inode(|root_inode| self.can_add_dirent(name, root_inode)) {
             return None;
         }
         // create a new file
         // alloc a inode with an indirect block
-        let new_inode_id = fs.alloc_inode();
+        let new_inode_id = fs.alloc_inode()?;
         // initialize inode
         let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
-        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
-            .lock()
-            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(DiskInodeType::File);
-            });
-        self.modify_disk_inode(|root_inode| {
-            // append file in the dirent
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
-            let dirent = DirEntry::new(name, new_inode_id);
-            root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
+        let (is_dir, initialized) =
+            get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+                .lock()
+                .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                    new_inode.initialize(type_, fs.now());
+                    if fs.extents() && !new_inode.is_symlink() {
+                        new_inode.use_extents();
+                    }
+                    let initialized = if new_inode.is_dir() {
+                        let dot = DirEntry::new(".", new_inode_id);
+                        let dot_dot = DirEntry::new("..", self.inode_id);
+                        self.add_dirent(new_inode, &dot, &mut fs)
+                            .and_then(|()| self.add_dirent(new_inode, &dot_dot, &mut fs))
+                    } else if new_inode.is_symlink() {
+                        if DiskInode::fits_inline(content.len()) {
+                            new_inode.write_inline(content);
+                            Some(())
+                        } else {
+                            self.allocate_range(0, content.len(), new_inode, &mut fs)
+                                .map(|()| {
+                                    new_inode.write_at(0, content, &self.block_device);
+                                })
+                        }
+                    } else {
+                        Some(())
+                    };
+                    (new_inode.is_dir(), initialized)
+                });
+        let added = initialized.and_then(|()| {
+            self.modify_disk_inode(|root_inode| {
+                // append file in the dirent
+                let dirent = DirEntry::new(name, new_inode_id);
+                self.add_dirent(root_inode, &dirent, &mut fs)?;
+                // `..` of the new directory
+                if is_dir {
+                    root_inode.nlink += 1;
+                }
+                Some(())
+            })
         });
+        if added.is_none() {
+            // no entry refers to the new inode
+            fs.free_inode(new_inode_id);
+            fs.commit();
+            return None;
+        }
 
-        let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
-        block_cache_sync_all();
+        fs.commit();
         // return inode
-        Some(Arc::new(Self::new(
-            block_id,
-            block_offset,
-            self.fs.clone(),
-            self.block_device.clone(),
-        )))
+        Some(Arc::new(Self::new(new_inode_id, &mut fs, self.fs.clone())))
         // release efs lock automatically by compiler
     }
 
-    /// List inodes under current inode
+    /// Whether `name` can be created under current inode if the disk has space for it
+    ///
+    /// Current inode must be a directory that is not removed, and `name` must be a new valid name.
+    pub fn can_create(&self, name: &str) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| self.can_add_dirent(name, disk_inode))
+    }
+
+    /// Create a file under current inode by name
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If current inode is not a directory, or `name` already exists => `None`
+    /// - If `name` is empty or longer than 27 bytes => `None`
+    /// - If all the inodes are in use or the disk is full => `None`
+    /// - Otherwise => The new file
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File, &[])
+    }
+
+    /// Create a directory under current inode by name
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If current inode is not a directory, or `name` already exists => `None`
+    /// - If `name` is empty or longer than 27 bytes => `None`
+    /// - If all the inodes are in use or the disk is full => `None`
+    /// - Otherwise => The new directory
+    pub fn mkdir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory, &[])
+    }
+
+    /// Create a symbolic link to `target` under current inode by name
+    ///
+    /// `target` is not looked up, so it may not exist.
+    /// A short `target`(up to 112 bytes) is stored in the inode without data blocks.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If current inode is not a directory, or `name` already exists => `None`
+    /// - If `name` is empty or longer than 27 bytes, or `target` is empty => `None`
+    /// - If all the inodes are in use or the disk is full => `None`
+    /// - Otherwise => The new symbolic link
+    pub fn symlink(&self, name: &str, target: &str) -> Option<Arc<Inode>> {
+        if target.is_empty() {
+            return None;
+        }
+        self.create_inode(name, DiskInodeType::SymLink, target.as_bytes())
+    }
+
+    /// Whether this inode is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_symlink())
+    }
+
+    /// Get the target of this symbolic link
+    ///
+    /// `None` if this inode is not a symbolic link.
+    pub fn read_link(&self) -> Option<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_symlink() {
+                return None;
+            }
+            let mut buf = vec![0u8; disk_inode.size as usize];
+            disk_inode.read_at(0, &mut buf, &self.block_device);
+            String::from_utf8(buf).ok()
+        })
+    }
+
+    /// Add a directory entry `name` under current inode that refers to `target`(not a directory)
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If current inode is not a directory, or `name` already exists => false
+    /// - If `name` is empty or longer than 27 bytes => false
+    /// - If `target` is a directory, already removed or in another filesystem => false
+    /// - If the disk is full => false
+    /// - Otherwise => true
+    pub fn link(&self, name: &str, target: &Inode) -> bool {
+        if !Arc::ptr_eq(&self.fs, &target.fs) {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        if !self.read_disk_inode(|root_inode| self.can_add_dirent(name, root_inode))
+            || target.read_disk_inode(|disk_inode| disk_inode.is_dir() || disk_inode.nlink == 0)
+        {
+            return false;
+        }
+        let added = self.modify_disk_inode(|root_inode| {
+            let dirent = DirEntry::new(name, target.inode_id);
+            self.add_dirent(root_inode, &dirent, &mut fs)
+        });
+        if added.is_none() {
+            return false;
+        }
+        target.modify_disk_inode(|disk_inode| {
+            disk_inode.nlink += 1;
+            disk_inode.touch_changed(fs.now());
+        });
+        fs.commit();
+        true
+    }
+
+    /// Drop a reference to `inode_id` from a directory entry
+    ///
+    /// A directory loses its entry and `.` at once, and its parent `parent_id` loses `..` of it.
+    /// The inode is freed if `nlink` becomes 0 and no `Inode` of it is left.
+    fn drop_link(&self, fs: &mut EasyFileSystem, inode_id: u32, parent_id: u32) {
+        let now = fs.now();
+        let (is_dir, nlink) = self.modify_disk_inode_of(fs, inode_id, |disk_inode| {
+            if disk_inode.is_dir() {
+                disk_inode.nlink = 0;
+            } else {
+                disk_inode.nlink -= 1;
+            }
+            disk_inode.touch_changed(now);
+            (disk_inode.is_dir(), disk_inode.nlink)
+        });
+        if is_dir {
+            self.modify_disk_inode_of(fs, parent_id, |disk_inode| {
+                disk_inode.nlink -= 1;
+                disk_inode.touch_changed(now);
+            });
+        }
+        if nlink == 0 && !fs.is_inode_open(inode_id) {
+            fs.free_inode(inode_id);
+        }
+    }
+
+    /// Whether the directory `disk_inode` has only `.` and `..`
+    fn is_empty_dir(&self, disk_inode: &DiskInode) -> bool {
+        self.dirents(disk_inode).len() <= 2
+    }
+
+    /// Remove a file or an empty directory under current inode by name
+    ///
+    /// The directory entry is left empty to be reused.
+    fn remove_inode(&self, name: &str, type_: DiskInodeType) -> bool {
+        if name == "." || name == ".." {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let found = self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_dirent(name, disk_inode)
+        });
+        let (idx, inode_id) = match found {
+            Some(found) => found,
+            None => return false,
+        };
+        let removable = self.read_disk_inode_of(&fs, inode_id, |disk_inode| {
+            disk_inode.is_dir() == (type_ == DiskInodeType::Directory)
+                // only `.` and `..` are left?
+                && (!disk_inode.is_dir() || self.is_empty_dir(disk_inode))
+        });
+        if !removable {
+            return false;
+        }
+        // remove the entry
+        self.modify_disk_inode(|disk_inode| {
+            self.write_dirent(disk_inode, idx, &DirEntry::empty(), &fs)
+        });
+        self.drop_link(&mut fs, inode_id, self.inode_id);
+        fs.commit();
+        true
+    }
+
+    /// Remove a file(or a symbolic link, not its target) under current inode by name
+    ///
+    /// Its data blocks and inode are deallocated when no other entry refers to it
+    /// and no `Inode` of it is left.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `name` is not found or a directory => false
+    /// - Otherwise => true
+    pub fn unlink(&self, name: &str) -> bool {
+        self.remove_inode(name, DiskInodeType::File)
+    }
+
+    /// Remove an empty directory under current inode by name
+    ///
+    /// Its data blocks and inode are deallocated when no `Inode` of it is left.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `name` is `.` or `..`, or not found => false
+    /// - If `name` is not a directory, or not empty => false
+    /// - Otherwise => true
+    pub fn rmdir(&self, name: &str) -> bool {
+        self.remove_inode(name, DiskInodeType::Directory)
+    }
+
+    /// Whether the directory `inode_id` is `ancestor_id` or under it
+    fn is_under(&self, fs: &EasyFileSystem, mut inode_id: u32, ancestor_id: u32) -> bool {
+        loop {
+            if inode_id == ancestor_id {
+                return true;
+            }
+            // the root
+            if inode_id == 0 {
+                return false;
+            }
+            inode_id = self
+                .read_disk_inode_of(fs, inode_id, |disk_inode| {
+                    self.find_inode_id("..", disk_inode)
+                })
+                .unwrap();
+        }
+    }
+
+    /// Move the entry `old_name` under current inode to `new_name` under `new_dir`
+    ///
+    /// It is done under the lock of the filesystem, so no one sees the halfway state.
+    /// An existing `new_name` is replaced if it is the same type(a directory must be empty).
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `old_name` is not found, or either name is `.` or `..` => false
+    /// - If `new_name` is empty or longer than 27 bytes => false
+    /// - If `new_dir` is not a directory, already removed or in another filesystem => false
+    /// - If a directory is moved under itself => false
+    /// - If `new_name` exists and is a different type or a directory that is not empty => false
+    /// - If `new_name` does not exist and the disk is full => false
+    /// - Otherwise => true
+    pub fn rename(&self, old_name: &str, new_dir: &Inode, new_name: &str) -> bool {
+        if [old_name, new_name]
+            .iter()
+            .any(|&name| name == "." || name == "..")
+            || new_name.is_empty()
+            || new_name.len() > NAME_LENGTH_LIMIT
+            || !Arc::ptr_eq(&self.fs, &new_dir.fs)
+        {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let found = self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_dirent(old_name, disk_inode)
+        });
+        let (old_idx, inode_id) = match found {
+            Some(found) => found,
+            None => return false,
+        };
+        let is_dir = self.read_disk_inode_of(&fs, inode_id, |disk_inode| disk_inode.is_dir());
+        let existing = new_dir.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() || disk_inode.nlink == 0 {
+                return Err(());
+            }
+            Ok(self.find_dirent(new_name, disk_inode))
+        });
+        let existing = match existing {
+            Ok(existing) => existing,
+            Err(()) => return false,
+        };
+        if is_dir && self.is_under(&fs, new_dir.inode_id, inode_id) {
+            return false;
+        }
+        let dirent = DirEntry::new(new_name, inode_id);
+        let replaced = match existing {
+            // the same inode
+            Some((_, existing_id)) if existing_id == inode_id => return true,
+            Some((new_idx, existing_id)) => {
+                let replaceable = self.read_disk_inode_of(&fs, existing_id, |disk_inode| {
+                    disk_inode.is_dir() == is_dir && (!is_dir || self.is_empty_dir(disk_inode))
+                });
+                if !replaceable {
+                    return false;
+                }
+                new_dir.modify_disk_inode(|disk_inode| {
+                    self.write_dirent(disk_inode, new_idx, &dirent, &fs)
+                });
+                Some(existing_id)
+            }
+            None => {
+                let added = new_dir
+                    .modify_disk_inode(|disk_inode| self.add_dirent(disk_inode, &dirent, &mut fs));
+                if added.is_none() {
+                    return false;
+                }
+                None
+            }
+        };
+        self.modify_disk_inode(|disk_inode| {
+            self.write_dirent(disk_inode, old_idx, &DirEntry::empty(), &fs)
+        });
+        self.modify_disk_inode_of(&fs, inode_id, |disk_inode| {
+            // `..` of the directory moves to the new parent
+            if is_dir && self.inode_id != new_dir.inode_id {
+                let (idx, _) = self.find_dirent("..", disk_inode).unwrap();
+                let dot_dot = DirEntry::new("..", new_dir.inode_id);
+                self.write_dirent(disk_inode, idx, &dot_dot, &fs);
+            }
+            disk_inode.touch_changed(fs.now());
+        });
+        if is_dir && self.inode_id != new_dir.inode_id {
+            self.modify_disk_inode(|disk_inode| disk_inode.nlink -= 1);
+            new_dir.modify_disk_inode(|disk_inode| disk_inode.nlink += 1);
+        }
+        // the entry of the replaced inode refers to the moved one now
+        if let Some(existing_id) = replaced {
+            self.drop_link(&mut fs, existing_id, new_dir.inode_id);
+        }
+        fs.commit();
+        true
+    }
+
+    /// List inodes under current inode except `.` and `..`
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
-            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-            let mut v: Vec<String> = Vec::new();
-            for i in 0..file_count {
-                let mut dirent = DirEntry::empty();
-                assert_eq!(
-                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
-                    DIRENT_SZ,
-                );
-                v.push(String::from(dirent.name()));
-            }
-            v
+            self.dirents(disk_inode)
+                .iter()
+                .map(|(_, dirent)| dirent.name())
+                .filter(|&name| name != "." && name != "..")
+                .map(String::from)
+                .collect()
         })
     }
 
+    /// Read the first directory entry in use from the `offset`-th entry, including `.` and `..`
+    ///
+    /// The entries can be read one by one by passing the returned index plus 1 as `offset`.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If current inode is not a directory or there is no entry from `offset` => `None`
+    /// - Otherwise => (index of the entry, entry)
+    pub fn read_dir(&self, offset: usize) -> Option<(usize, DirEntryInfo)> {
+        let fs = self.fs.lock();
+        let (idx, dirent) = self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.dirents_from(disk_inode, offset).next()
+        })?;
+        let inode_id = dirent.inode_number();
+        Some((
+            idx,
+            DirEntryInfo {
+                inode_id,
+                file_type: self.read_disk_inode_of(&fs, inode_id, DiskInode::file_type),
+                name: String::from(dirent.name()),
+            },
+        ))
+    }
+
     /// Read data from current inode
     ///
+    /// `atime` is updated only if it is earlier than `mtime` or `ctime`, or a day old(like `relatime` of Linux),
+    /// and the clock has moved past it, so that most reads write nothing.
+    ///
     /// # parameters
     ///
     /// - `offset`: The starting point of the block to be read.
@@ -176,42 +729,166 @@ impl Inode {
     /// - If offset is greater than `offset + buf length` or  `self.size(file/dir size)` => 0
     /// - otherwise => Length of data finished reading (`buf` same as length of copied data)
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
-        let _fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+        let fs = self.fs.lock();
+        let now = fs.now();
+        let (size, stale) = self.read_disk_inode(|disk_inode| {
+            let stale = now > disk_inode.atime
+                && (disk_inode.atime < disk_inode.mtime
+                    || disk_inode.atime < disk_inode.ctime
+                    || disk_inode.atime.sec + ATIME_UPDATE_INTERVAL <= now.sec);
+            (disk_inode.read_at(offset, buf, &self.block_device), stale)
+        });
+        if stale {
+            self.modify_disk_inode(|disk_inode| disk_inode.atime = now);
+            fs.commit();
+        }
+        size
     }
 
     /// Write data to current inode
     ///
+    /// The write is split into transactions that fit in the journal(see [`EasyFileSystem::fits_transaction`]),
+    /// each of them done as a whole or not at all, so a crash may leave only the first part written.
+    ///
     /// # parameters
     /// - `offset`: The starting point of the block to be read.
     /// - `buf`: Data to be written.
     ///
-    /// # Panic
-    /// 1st argument `offset` is greater than `offset + buf length` or  `self.size(file/dir size)`
-    ///
     /// # Return
-    /// Length of data that has been written
+    /// Conditional branching.
+    /// - If the file would exceed `Inode::max_size` => 0
+    /// - If the disk becomes full => Length of data written by the transactions before(0 if none)
+    /// - Otherwise => Length of data that has been written
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
         let mut fs = self.fs.lock();
-        let size = self.modify_disk_inode(|disk_inode| {
-            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
-            disk_inode.write_at(offset, buf, &self.block_device)
-        });
-        block_cache_sync_all();
-        size
+        let end = offset + buf.len();
+        if end > DiskInode::max_size() {
+            return 0;
+        }
+        let mut written = 0;
+        while written < buf.len() {
+            let start = offset + written;
+            let chunk_end = match self.transaction_end(start, end, &fs) {
+                Some(chunk_end) => chunk_end,
+                None => break,
+            };
+            let size = self.modify_disk_inode(|disk_inode| {
+                self.allocate_range(start, chunk_end, disk_inode, &mut fs)?;
+                disk_inode.touch_modified(fs.now());
+                Some(disk_inode.write_at(
+                    start,
+                    &buf[written..chunk_end - offset],
+                    &self.block_device,
+                ))
+            });
+            fs.commit();
+            match size {
+                Some(size) => written += size,
+                None => break,
+            }
+        }
+        written
+    }
+
+    /// The end of the first transaction of a write of `[start, end)` bytes:
+    /// the whole range if it fits in the journal, or fewer blocks from `start` otherwise
+    ///
+    /// # Return
+    /// `None` if even the block of `start` does not fit
+    fn transaction_end(&self, start: usize, end: usize, fs: &EasyFileSystem) -> Option<usize> {
+        let first_block = start / BLOCK_SZ;
+        let mut blocks = (end + BLOCK_SZ - 1) / BLOCK_SZ - first_block;
+        loop {
+            let chunk_end = end.min((first_block + blocks) * BLOCK_SZ);
+            // the inode block and the blocks that exist already
+            let modified = self.read_disk_inode(|disk_inode| {
+                1 + disk_inode.blocks_modified(start, chunk_end, &self.block_device)
+            });
+            if fs.fits_transaction(modified) {
+                return Some(chunk_end);
+            }
+            if blocks == 1 {
+                return None;
+            }
+            blocks /= 2;
+        }
+    }
+
+    /// The max size of a file in bytes
+    pub fn max_size() -> usize {
+        DiskInode::max_size()
     }
 
     /// Clear the data in current inode
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
-        self.modify_disk_inode(|disk_inode| {
-            let size = disk_inode.size;
+        let data_blocks_dealloc = self.modify_disk_inode(|disk_inode| {
+            let blocks = disk_inode.allocated_blocks();
             let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
-            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
-            for data_block in data_blocks_dealloc.into_iter() {
-                fs.dealloc_data(data_block);
+            assert!(data_blocks_dealloc.len() == blocks as usize);
+            disk_inode.touch_modified(fs.now());
+            data_blocks_dealloc
+        });
+        fs.dealloc_data_blocks(data_blocks_dealloc);
+        fs.commit();
+    }
+
+    /// Change the size of current inode(a regular file) to `new_size` bytes
+    ///
+    /// Shrinking frees the data blocks beyond `new_size`.
+    /// Growing allocates no data block: the new content is a hole that reads as zeros.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If current inode is not a regular file, or `new_size` exceeds the max file size => false
+    /// - Otherwise => true
+    pub fn truncate(&self, new_size: usize) -> bool {
+        if new_size > DiskInode::max_size() {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let data_blocks_dealloc = self.modify_disk_inode(|disk_inode| {
+            if !disk_inode.is_file() {
+                return None;
             }
+            disk_inode.touch_modified(fs.now());
+            Some(disk_inode.truncate(new_size as u32, &self.block_device))
         });
-        block_cache_sync_all();
+        let truncated = data_blocks_dealloc.is_some();
+        if let Some(data_blocks_dealloc) = data_blocks_dealloc {
+            fs.dealloc_data_blocks(data_blocks_dealloc);
+        }
+        fs.commit();
+        truncated
+    }
+
+    /// Get the metadata of current inode
+    pub fn metadata(&self) -> Metadata {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| Metadata {
+            inode_id: self.inode_id,
+            mode: disk_inode.st_mode(),
+            nlink: disk_inode.nlink,
+            uid: disk_inode.uid,
+            gid: disk_inode.gid,
+            size: disk_inode.size as u64,
+            blocks: disk_inode.allocated_blocks() as u64,
+            atime: disk_inode.atime,
+            mtime: disk_inode.mtime,
+            ctime: disk_inode.ctime,
+        })
+    }
+}
+
+impl Drop for Inode {
+    /// Free the inode if it has been removed from every directory and this is the last `Inode` of it
+    fn drop(&mut self) {
+        let mut fs = self.fs.lock();
+        if !fs.close_inode(self.inode_id)
+            && self.read_disk_inode(|disk_inode| disk_inode.nlink == 0)
+        {
+            fs.free_inode(self.inode_id);
+            fs.commit();
+        }
     }
 }
@@ -8,11 +8,65 @@ pub const MEMORY_END: usize = 0x81000000;
 /// Memory mapped I/O Address range (starting address, length)
 //- https://lists.gnu.org/archive/html/qemu-riscv/2019-11/msg00023.html
 pub const MMIO: &[(usize, usize)] = &[
-    (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC  in virt machine
+    (0x0010_0000, 0x00_2000), // VIRT_TEST(0x0010_0000)/RTC(0x0010_1000) in virt machine
+    (0x0C00_0000, 0x21_0000), // VIRT_PLIC in virt machine(contexts of up to 8 harts)
+    (0x1000_0000, 0x00_1000), // VIRT_UART0 in virt machine
     (0x1000_1000, 0x00_1000), // Virtio Block in virt machine
 ];
 
 pub type BlockDeviceImpl = crate::drivers::block::VirtIOBlock;
+pub type CharDeviceImpl = crate::drivers::chardev::NS16550a;
+pub type RtcImpl = crate::drivers::rtc::GoldfishRtc;
+
+/// Starting address of the PLIC registers
+pub const VIRT_PLIC: usize = 0x0C00_0000;
+/// Starting address of the UART registers
+pub const VIRT_UART: usize = 0x1000_0000;
+/// Interrupt source ID of the UART
+const UART_IRQ: u32 = 10;
+
+use crate::drivers::plic::{IntrTargetPriority, Plic};
+use crate::drivers::{CharDevice, UART};
+use crate::fs::TTY;
+use crate::hart::hart_id;
+
+/// Set up the devices to raise interrupts, and route them to the current hart.
+///
+/// Call it once on the boot hart after the kernel address space is activated.
+pub fn device_init() {
+    let mut plic = unsafe { Plic::new(VIRT_PLIC) };
+    plic.set_priority(UART_IRQ as usize, 1);
+    UART.init();
+    irq_init_hart();
+}
+
+/// Route the device interrupts to S mode of the current hart.
+///
+/// Whichever hart claims an interrupt first handles it(see [`irq_handler`]).
+pub fn irq_init_hart() {
+    let mut plic = unsafe { Plic::new(VIRT_PLIC) };
+    let hart_id = hart_id();
+    plic.set_threshold(hart_id, IntrTargetPriority::Supervisor, 0);
+    plic.set_threshold(hart_id, IntrTargetPriority::Machine, 1);
+    plic.enable(hart_id, IntrTargetPriority::Supervisor, UART_IRQ as usize);
+}
+
+/// Handle an external interrupt routed by the PLIC.
+pub fn irq_handler() {
+    let mut plic = unsafe { Plic::new(VIRT_PLIC) };
+    let hart_id = hart_id();
+    let intr_src_id = plic.claim(hart_id, IntrTargetPriority::Supervisor);
+    match intr_src_id {
+        // another hart has claimed it
+        0 => return,
+        UART_IRQ => {
+            UART.handle_irq();
+            TTY.handle_input();
+        }
+        _ => panic!("unsupported IRQ {}", intr_src_id),
+    }
+    plic.complete(hart_id, IntrTargetPriority::Supervisor, intr_src_id);
+}
 
 //ref:: https://github.com/andre-richter/qemu-exit
 use core::arch::asm;
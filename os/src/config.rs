@@ -4,9 +4,20 @@
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 /// 4096 * 2 = 8KiB
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
+/// Kernel stacks are placed at every 16KiB below the trampoline.
+/// The lower half of each slot is left unmapped as guard pages.
+///
+/// It must be `KERNEL_STACK_SIZE * 2`, since `__kerneltrap` in `trap.S` tests a bit of `sp`
+/// to detect kernel stack overflow.
+pub const KERNEL_STACK_SLOT_SIZE: usize = KERNEL_STACK_SIZE * 2;
 /// 0x200000 byte == 2MiB
 pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
 
+/// Maximum number of harts(hardware threads). Harts whose id is not less than this are not used.
+///
+/// `entry.asm` reserves a boot stack for each of them.
+pub const MAX_HARTS: usize = 8;
+
 /// 4096byte == 4KiB
 pub const PAGE_SIZE: usize = 0x1000;
 /// Bit width of intra-page offset
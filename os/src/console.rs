@@ -1,21 +1,38 @@
-//! SBI console driver, for text output
+//! Console output through the UART driver
 
-use crate::sbi::console_putchar;
+use crate::drivers::{CharDevice, UART};
+use crate::sync::SpinLock;
 use core::fmt::{self, Write};
 
 struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            console_putchar(c as usize);
+        for c in s.bytes() {
+            UART.write(c);
         }
         Ok(())
     }
 }
 
+/// Serializes the output of harts, so that their messages are not interleaved
+static STDOUT: SpinLock<Stdout> = SpinLock::new(Stdout);
+
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
+}
+
+/// Print raw bytes, which may not be valid UTF-8.
+pub fn print_bytes(bytes: &[u8]) {
+    let _stdout = STDOUT.lock();
+    for &c in bytes {
+        UART.write(c);
+    }
+}
+
+/// Write out the output buffered in the UART driver, e.g. before shutdown.
+pub fn flush() {
+    UART.flush();
 }
 
 /// Prints to the standard output.
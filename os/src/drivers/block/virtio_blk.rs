@@ -2,7 +2,7 @@ use crate::mm::{
     frame_alloc, frame_dealloc, kernel_token, FrameTracker, PageTable, PhysAddr, PhysPageNum,
     StepByOne, VirtAddr,
 };
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use alloc::vec::Vec;
 use easy_fs::BlockDevice;
 use lazy_static::*;
@@ -11,12 +11,12 @@ use virtio_drivers::{Hal, VirtIOBlk, VirtIOHeader};
 /// Starting virtual address of Memory Mapped Input/Output(MMIO)
 const VIRTIO0: usize = 0x10001000;
 
-pub struct VirtIOBlock(UPSafeCell<VirtIOBlk<'static, VirtioHal>>);
+pub struct VirtIOBlock(SpinLock<VirtIOBlk<'static, VirtioHal>>);
 
 impl VirtIOBlock {
     pub fn new() -> Self {
         unsafe {
-            Self(UPSafeCell::new(
+            Self(SpinLock::new(
                 VirtIOBlk::<VirtioHal>::new(&mut *(VIRTIO0 as *mut VirtIOHeader)).unwrap(),
             ))
         }
@@ -26,21 +26,21 @@ impl VirtIOBlock {
 impl BlockDevice for VirtIOBlock {
     fn read_block(&self, block_id: usize, buf: &mut [u8]) {
         self.0
-            .exclusive_access()
+            .lock()
             .read_block(block_id, buf)
             .expect("Error when reading VirtIOBlk")
     }
 
     fn write_block(&self, block_id: usize, buf: &[u8]) {
         self.0
-            .exclusive_access()
+            .lock()
             .write_block(block_id, buf)
             .expect("Error when writing VirtIOBlk")
     }
 }
 
 lazy_static! {
-    static ref QUEUE_FRAMES: UPSafeCell<Vec<FrameTracker>> = unsafe { UPSafeCell::new(Vec::new()) };
+    static ref QUEUE_FRAMES: SpinLock<Vec<FrameTracker>> = SpinLock::new(Vec::new());
 }
 
 pub struct VirtioHal;
@@ -54,7 +54,7 @@ impl Hal for VirtioHal {
                 ppn_base = frame.ppn;
             }
             assert_eq!(frame.ppn.0, ppn_base.0 + i);
-            QUEUE_FRAMES.exclusive_access().push(frame);
+            QUEUE_FRAMES.lock().push(frame);
         }
         let pa: PhysAddr = ppn_base.into();
         pa.0
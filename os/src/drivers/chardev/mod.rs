@@ -0,0 +1,24 @@
+mod ns16550a;
+
+pub use ns16550a::NS16550a;
+
+use crate::board::{CharDeviceImpl, VIRT_UART};
+
+/// Character device(e.g. serial port)
+pub trait CharDevice: Send + Sync {
+    /// Set up the device to raise interrupts
+    fn init(&self);
+    /// Take a received byte, or `None` if nothing has been received.
+    fn read(&self) -> Option<u8>;
+    /// Write a byte. It may be buffered until the device is ready.
+    fn write(&self, ch: u8);
+    /// Write all the buffered bytes out without waiting for interrupts.
+    fn flush(&self);
+    /// Handle an interrupt raised by the device
+    fn handle_irq(&self);
+}
+
+/// Serial port of the console.
+///
+/// It is not behind `lazy_static`, since the kernel prints before the heap is initialized.
+pub static UART: CharDeviceImpl = CharDeviceImpl::new(VIRT_UART);
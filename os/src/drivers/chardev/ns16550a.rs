@@ -0,0 +1,217 @@
+use super::CharDevice;
+use crate::sync::SpinLock;
+use core::hint::spin_loop;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Receiver Buffer Register(read) / Transmitter Holding Register(write)
+const RBR_THR: usize = 0;
+/// Interrupt Enable Register
+const IER: usize = 1;
+/// FIFO Control Register(write)
+const FCR: usize = 2;
+/// Line Control Register
+const LCR: usize = 3;
+/// Modem Control Register
+const MCR: usize = 4;
+/// Line Status Register
+const LSR: usize = 5;
+
+/// IER: Received data available
+const IER_RX_AVAILABLE: u8 = 1 << 0;
+/// IER: Transmitter holding register empty
+const IER_TX_EMPTY: u8 = 1 << 1;
+/// FCR: Enable FIFOs and clear them
+const FCR_ENABLE_AND_CLEAR: u8 = 0b111;
+/// LCR: 8 data bits, no parity, 1 stop bit
+const LCR_8N1: u8 = 0b11;
+/// MCR: Data Terminal Ready | Request To Send | Auxiliary Output 2(routes the interrupt)
+const MCR_DTR_RTS_OUT2: u8 = 0b1011;
+/// LSR: Data ready
+const LSR_DATA_READY: u8 = 1 << 0;
+/// LSR: Transmitter holding register empty
+const LSR_TX_EMPTY: u8 = 1 << 5;
+
+/// Size of the buffer of received bytes
+const RX_BUFFER_SIZE: usize = 256;
+/// Size of the buffer of bytes to be transmitted
+const TX_BUFFER_SIZE: usize = 1024;
+
+/// Fixed size FIFO of bytes
+struct RingBuffer<const N: usize> {
+    arr: [u8; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    const fn new() -> Self {
+        Self {
+            arr: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// # Return
+    /// Conditional branching.
+    /// - If the buffer is full => false(`ch` is dropped)
+    /// - Otherwise => true
+    fn push(&mut self, ch: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.arr[(self.head + self.len) % N] = ch;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        let ch = self.arr[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(ch)
+    }
+}
+
+struct NS16550aInner {
+    /// Received bytes not read yet
+    rx_buffer: RingBuffer<RX_BUFFER_SIZE>,
+    /// Bytes waiting for the transmitter
+    tx_buffer: RingBuffer<TX_BUFFER_SIZE>,
+    /// Whether the receiver interrupt is masked because `rx_buffer` is full
+    ///
+    /// The received bytes are left in the FIFO of the UART until a reader frees space.
+    rx_paused: bool,
+}
+
+/// NS16550A UART of qemu virt machine
+///
+/// - http://caro.su/msx/ocm_de1/16550.pdf
+pub struct NS16550a {
+    /// Starting address of the registers(Memory Mapped Input/Output)
+    base: usize,
+    inner: SpinLock<NS16550aInner>,
+}
+
+impl NS16550a {
+    pub const fn new(base: usize) -> Self {
+        Self {
+            base,
+            inner: SpinLock::new(NS16550aInner {
+                rx_buffer: RingBuffer::new(),
+                tx_buffer: RingBuffer::new(),
+                rx_paused: false,
+            }),
+        }
+    }
+
+    fn read_reg(&self, offset: usize) -> u8 {
+        unsafe { read_volatile((self.base + offset) as *const u8) }
+    }
+
+    fn write_reg(&self, offset: usize, value: u8) {
+        unsafe { write_volatile((self.base + offset) as *mut u8, value) }
+    }
+
+    fn tx_ready(&self) -> bool {
+        self.read_reg(LSR) & LSR_TX_EMPTY != 0
+    }
+
+    /// Move the buffered bytes to the transmitter while it accepts them.
+    ///
+    /// If some bytes are left, the transmitter raises an interrupt when it becomes ready.
+    fn start_tx(&self, inner: &mut NS16550aInner) {
+        while !inner.tx_buffer.is_empty() && self.tx_ready() {
+            let ch = inner.tx_buffer.pop().unwrap();
+            self.write_reg(RBR_THR, ch);
+        }
+        self.update_ier(inner);
+    }
+
+    /// Enable the receiver interrupt unless it is paused,
+    /// and the transmitter interrupt if some bytes are waiting for it.
+    fn update_ier(&self, inner: &NS16550aInner) {
+        let mut ier = 0;
+        if !inner.rx_paused {
+            ier |= IER_RX_AVAILABLE;
+        }
+        if !inner.tx_buffer.is_empty() {
+            ier |= IER_TX_EMPTY;
+        }
+        self.write_reg(IER, ier);
+    }
+
+    /// Transmit all the buffered bytes by busy waiting.
+    fn drain_tx(&self, inner: &mut NS16550aInner) {
+        while let Some(ch) = inner.tx_buffer.pop() {
+            while !self.tx_ready() {
+                spin_loop();
+            }
+            self.write_reg(RBR_THR, ch);
+        }
+    }
+}
+
+impl CharDevice for NS16550a {
+    fn init(&self) {
+        let _inner = self.inner.lock();
+        // The baud rate(divisor latch) has been set by the firmware.
+        self.write_reg(LCR, LCR_8N1);
+        self.write_reg(FCR, FCR_ENABLE_AND_CLEAR);
+        self.write_reg(MCR, MCR_DTR_RTS_OUT2);
+        self.write_reg(IER, IER_RX_AVAILABLE);
+    }
+
+    /// Resume the receiver interrupt once a byte is taken from the full buffer,
+    /// which raises an interrupt again for the bytes left in the FIFO.
+    fn read(&self) -> Option<u8> {
+        let mut inner = self.inner.lock();
+        let ch = inner.rx_buffer.pop();
+        if ch.is_some() && inner.rx_paused {
+            inner.rx_paused = false;
+            self.update_ier(&inner);
+        }
+        ch
+    }
+
+    fn write(&self, ch: u8) {
+        let mut inner = self.inner.lock();
+        if inner.tx_buffer.is_full() {
+            // The transmitter is slower than the writers. Wait for it here,
+            // since the writer may be the kernel itself which cannot block.
+            self.drain_tx(&mut inner);
+        }
+        inner.tx_buffer.push(ch);
+        self.start_tx(&mut inner);
+    }
+
+    fn flush(&self) {
+        let mut inner = self.inner.lock();
+        self.drain_tx(&mut inner);
+    }
+
+    fn handle_irq(&self) {
+        let mut inner = self.inner.lock();
+        while self.read_reg(LSR) & LSR_DATA_READY != 0 {
+            if inner.rx_buffer.is_full() {
+                // nobody reads the buffer, so leave the rest in the FIFO until a reader frees space
+                inner.rx_paused = true;
+                break;
+            }
+            let ch = self.read_reg(RBR_THR);
+            inner.rx_buffer.push(ch);
+        }
+        self.start_tx(&mut inner);
+    }
+}
@@ -1,3 +1,8 @@
 pub mod block;
+pub mod chardev;
+pub mod plic;
+pub mod rtc;
 
 pub use block::BLOCK_DEVICE;
+pub use chardev::{CharDevice, UART};
+pub use rtc::RTC_DEVICE;
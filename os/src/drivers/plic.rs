@@ -0,0 +1,120 @@
+//! Driver of PLIC(Platform-Level Interrupt Controller)
+//!
+//! - https://github.com/riscv/riscv-plic-spec/blob/master/riscv-plic.adoc
+
+use core::ptr::{read_volatile, write_volatile};
+
+/// Privilege level of the interrupt target of a hart
+#[derive(Copy, Clone)]
+pub enum IntrTargetPriority {
+    /// M mode
+    Machine = 0,
+    /// S mode
+    Supervisor = 1,
+}
+
+/// PLIC registers at `base_addr`(Memory Mapped Input/Output)
+pub struct Plic {
+    base_addr: usize,
+}
+
+impl Plic {
+    /// # Safety
+    /// `base_addr` must be the address of the PLIC registers mapped in the kernel address space.
+    pub unsafe fn new(base_addr: usize) -> Self {
+        Self { base_addr }
+    }
+
+    /// Id of the context that delivers interrupts to `hart_id` in `target_priority` mode.
+    ///
+    /// Each hart has a context for M mode and one for S mode in qemu virt machine.
+    fn context_id(hart_id: usize, target_priority: IntrTargetPriority) -> usize {
+        hart_id * 2 + target_priority as usize
+    }
+
+    fn priority_ptr(&self, intr_source_id: usize) -> *mut u32 {
+        assert!(intr_source_id > 0 && intr_source_id <= 1023);
+        (self.base_addr + intr_source_id * 4) as *mut u32
+    }
+
+    fn enable_ptr(
+        &self,
+        hart_id: usize,
+        target_priority: IntrTargetPriority,
+        intr_source_id: usize,
+    ) -> (*mut u32, usize) {
+        let id = Self::context_id(hart_id, target_priority);
+        let reg_id = intr_source_id / 32;
+        let reg_shift = intr_source_id % 32;
+        (
+            (self.base_addr + 0x2000 + 0x80 * id + 0x4 * reg_id) as *mut u32,
+            reg_shift,
+        )
+    }
+
+    fn threshold_ptr(&self, hart_id: usize, target_priority: IntrTargetPriority) -> *mut u32 {
+        let id = Self::context_id(hart_id, target_priority);
+        (self.base_addr + 0x20_0000 + 0x1000 * id) as *mut u32
+    }
+
+    fn claim_comp_ptr(&self, hart_id: usize, target_priority: IntrTargetPriority) -> *mut u32 {
+        let id = Self::context_id(hart_id, target_priority);
+        (self.base_addr + 0x20_0004 + 0x1000 * id) as *mut u32
+    }
+
+    /// Set the priority of an interrupt source. 0 never interrupts.
+    pub fn set_priority(&mut self, intr_source_id: usize, priority: u32) {
+        assert!(priority < 8);
+        unsafe {
+            write_volatile(self.priority_ptr(intr_source_id), priority);
+        }
+    }
+
+    /// Let an interrupt source interrupt the context.
+    pub fn enable(
+        &mut self,
+        hart_id: usize,
+        target_priority: IntrTargetPriority,
+        intr_source_id: usize,
+    ) {
+        let (reg_ptr, shift) = self.enable_ptr(hart_id, target_priority, intr_source_id);
+        unsafe {
+            write_volatile(reg_ptr, read_volatile(reg_ptr) | 1 << shift);
+        }
+    }
+
+    /// Only the interrupts whose priority is higher than `threshold` interrupt the context.
+    pub fn set_threshold(
+        &mut self,
+        hart_id: usize,
+        target_priority: IntrTargetPriority,
+        threshold: u32,
+    ) {
+        assert!(threshold < 8);
+        unsafe {
+            write_volatile(self.threshold_ptr(hart_id, target_priority), threshold);
+        }
+    }
+
+    /// Claim the pending interrupt of the highest priority.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If there is a pending interrupt => ID of the interrupt source
+    /// - Otherwise(e.g. another hart has claimed it) => 0
+    pub fn claim(&mut self, hart_id: usize, target_priority: IntrTargetPriority) -> u32 {
+        unsafe { read_volatile(self.claim_comp_ptr(hart_id, target_priority)) }
+    }
+
+    /// Tell that the interrupt claimed by [`Plic::claim`] has been handled.
+    pub fn complete(
+        &mut self,
+        hart_id: usize,
+        target_priority: IntrTargetPriority,
+        completion: u32,
+    ) {
+        unsafe {
+            write_volatile(self.claim_comp_ptr(hart_id, target_priority), completion);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use super::RtcDevice;
+use core::ptr::read_volatile;
+
+/// Starting address of the Goldfish RTC registers(Memory Mapped Input/Output)
+const VIRT_RTC: usize = 0x0010_1000;
+/// Lower 32 bits of the time in nanoseconds.
+/// Reading this register latches the upper 32 bits into `TIME_HIGH`.
+const TIME_LOW: usize = 0x00;
+/// Upper 32 bits of the time in nanoseconds
+const TIME_HIGH: usize = 0x04;
+
+/// Goldfish RTC of qemu virt machine
+///
+/// - https://android.googlesource.com/platform/external/qemu/+/master/docs/GOLDFISH-VIRTUAL-HARDWARE.TXT
+pub struct GoldfishRtc {
+    base: usize,
+}
+
+impl GoldfishRtc {
+    pub fn new() -> Self {
+        Self { base: VIRT_RTC }
+    }
+}
+
+impl RtcDevice for GoldfishRtc {
+    fn get_time_ns(&self) -> u64 {
+        // `TIME_LOW` must be read first
+        let low = unsafe { read_volatile((self.base + TIME_LOW) as *const u32) } as u64;
+        let high = unsafe { read_volatile((self.base + TIME_HIGH) as *const u32) } as u64;
+        (high << 32) | low
+    }
+}
@@ -0,0 +1,17 @@
+mod goldfish;
+
+pub use goldfish::GoldfishRtc;
+
+use crate::board::RtcImpl;
+use alloc::sync::Arc;
+use lazy_static::*;
+
+/// Real time clock device
+pub trait RtcDevice: Send + Sync {
+    /// Wall-clock time in nanoseconds since the UNIX epoch(1970-01-01 00:00:00 UTC)
+    fn get_time_ns(&self) -> u64;
+}
+
+lazy_static! {
+    pub static ref RTC_DEVICE: Arc<dyn RtcDevice> = Arc::new(RtcImpl::new());
+}
@@ -3,12 +3,28 @@
     .section .text.entry
     .globl _start
 _start:
-    la sp, boot_stack_top # load address dist, src_symbol: sp <- boot_stack_top
+    # a0 = id of the boot hart
+    mv tp, a0             # tp keeps the hart id while running in the kernel
+    call set_boot_stack
     call rust_main        # This function is defined in main.rs
 
+    .globl _start_secondary
+_start_secondary:
+    # Other harts are started here by the SBI HSM extension(see hart.rs). a0 = hart id
+    mv tp, a0
+    call set_boot_stack
+    call rust_main_secondary
+
+# sp <- boot_stack_top - hart id * 64KiB
+set_boot_stack:
+    la sp, boot_stack_top # load address dist, src_symbol: sp <- boot_stack_top
+    slli t0, tp, 16
+    sub sp, sp, t0
+    ret
+
     .section .bss.stack
     .globl boot_stack     # become global symbol. base stack
 boot_stack:
-    .space 4096 * 16      # 64KiB
+    .space 4096 * 16 * 8  # 64KiB for each hart(MAX_HARTS = 8)
     .globl boot_stack_top # become global symbol to label
 boot_stack_top:
@@ -0,0 +1,27 @@
+//! `struct linux_dirent64` of Linux reported by `getdents64`
+use alloc::vec::Vec;
+use easy_fs::DirEntryInfo;
+
+/// Size of the fields before the name(`d_ino`, `d_off`, `d_reclen` and `d_type`)
+const DIRENT64_HEADER_SZ: usize = 19;
+
+/// Encode a directory entry as a `struct linux_dirent64` record.
+///
+/// # Parameters
+/// - `next_offset`: Offset of the next entry(`d_off`), used to resume the listing.
+///
+/// # Return
+/// The record: `d_ino: u64`, `d_off: i64`, `d_reclen: u16`, `d_type: u8` and the null-terminated
+/// name, padded to a multiple of 8 bytes
+pub fn dirent64_record(entry: &DirEntryInfo, next_offset: usize) -> Vec<u8> {
+    let reclen = (DIRENT64_HEADER_SZ + entry.name.len() + 1 + 7) & !7;
+    let mut record = Vec::with_capacity(reclen);
+    record.extend_from_slice(&(entry.inode_id as u64).to_ne_bytes());
+    record.extend_from_slice(&(next_offset as i64).to_ne_bytes());
+    record.extend_from_slice(&(reclen as u16).to_ne_bytes());
+    // `DT_*` is the file type bits of `st_mode` shifted(e.g. `DT_DIR` is 4)
+    record.push((entry.file_type >> 12) as u8);
+    record.extend_from_slice(entry.name.as_bytes());
+    record.resize(reclen, 0);
+    record
+}
@@ -2,13 +2,19 @@
 //! we need to wrap `Inode` into `Arc`,but `Mutex` in `Inode` prevents
 //! file systems from being accessed simultaneously
 //!
-//! `UPSafeCell<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
-//! need to wrap `OSInodeInner` into `UPSafeCell`
-use super::File;
-use crate::{drivers::BLOCK_DEVICE, sync::UPSafeCell};
+//! `SpinLock<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
+//! need to wrap `OSInodeInner` into `SpinLock`
+use super::dirent::dirent64_record;
+use super::{File, Stat, EFBIG, ENOSPC, SEEK_CUR, SEEK_END, SEEK_SET};
+use crate::{
+    drivers::{BLOCK_DEVICE, RTC_DEVICE},
+    mm::UserBuffer,
+    sync::SpinLock,
+};
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use easy_fs::{EasyFileSystem, Inode};
+use easy_fs::{DiskTime, EasyFileSystem, Inode};
 use lazy_static::*;
 
 /// A wrapper around a filesystem inode
@@ -19,10 +25,10 @@ pub struct OSInode {
     /// Whether the file is allowed to be write by `sys_write` or not.
     writable: bool,
 
-    inner: UPSafeCell<OSInodeInner>,
+    inner: SpinLock<OSInodeInner>,
 }
 
-/// The OS inode inner in 'UPSafeCell'
+/// The OS inode inner in 'SpinLock'
 pub struct OSInodeInner {
     ///
     ///
@@ -37,13 +43,18 @@ impl OSInode {
         Self {
             readable,
             writable,
-            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+            inner: SpinLock::new(OSInodeInner { offset: 0, inode }),
         }
     }
 
+    /// Whether the inode is a regular file
+    pub fn is_file(&self) -> bool {
+        self.inner.lock().inode.is_file()
+    }
+
     /// Read all data inside a inode into vector
     pub fn read_all(&self) -> Vec<u8> {
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         let mut buffer = [0u8; 512];
         let mut v: Vec<u8> = Vec::new();
         loop {
@@ -59,12 +70,24 @@ impl OSInode {
 }
 
 lazy_static! {
+    /// Root directory(`/`) of the file system
     pub static ref ROOT_INODE: Arc<Inode> = {
         let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
+        efs.lock().set_clock(fs_clock);
+        efs.lock().set_extents(true);
         Arc::new(EasyFileSystem::root_inode(&efs))
     };
 }
 
+/// Wall-clock time for the timestamps of the file system
+fn fs_clock() -> DiskTime {
+    let ns = RTC_DEVICE.get_time_ns();
+    DiskTime {
+        sec: ns / 1_000_000_000,
+        nsec: ns % 1_000_000_000,
+    }
+}
+
 /// Receive a list of files from `ROOT_INODE` and output them in order to standard output.
 pub fn list_apps() {
     let apps = ROOT_INODE.ls();
@@ -88,46 +111,366 @@ bitflags! {
         const CREATE = 1 << 9;
         /// clear and the size set back to zero
         const TRUNC = 1 << 10;
+        /// Fail if the path is not a directory
+        const DIRECTORY = 1 << 16;
+        /// Fail if the last component of the path is a symbolic link
+        const NOFOLLOW = 1 << 17;
     }
 }
 
 impl OpenFlags {
     /// Do not check validity for simplicity
+    ///
+    /// Only the access mode(`WRONLY`/`RDWR`) is looked at, so that `CREATE`, `DIRECTORY`, etc.
+    /// alone mean read-only as Linux does.
     /// # Return
     /// (readable, writable)
     pub fn read_write(&self) -> (bool, bool) {
-        if self.is_empty() {
-            (true, false)
-        } else if self.contains(Self::WRONLY) {
+        if self.contains(Self::WRONLY) {
             (false, true)
-        } else {
+        } else if self.contains(Self::RDWR) {
             (true, true)
+        } else {
+            (true, false)
         }
     }
 }
 
-/// When it is desired to create a file with the same name as an existing file,
-/// the contents of the file are cleared.
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
-    let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            // clear size
-            inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
+/// Maximum number of symbolic links followed in one path resolution
+const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+/// Walk `path` from `dir`.
+///
+/// An absolute path starts from `ROOT_INODE`. `.` and `..` are resolved by the entries
+/// of each directory(`..` of the root is the root itself).
+///
+/// A symbolic link is followed(relative to the directory containing it),
+/// except the last component when `follow` is false.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is empty => `None`
+/// - If a component is not found, or a component except the last is not a directory => `None`
+/// - If more than `MAX_SYMLINK_FOLLOWS` symbolic links are followed => `None`
+/// - Otherwise => Inode of `path`
+fn find_path(dir: &Arc<Inode>, path: &str, follow: bool) -> Option<Arc<Inode>> {
+    if path.is_empty() {
+        return None;
+    }
+    let mut inode = if path.starts_with('/') {
+        ROOT_INODE.clone()
+    } else {
+        dir.clone()
+    };
+    // Components left to walk, the next one at the end.
+    // The target of a symbolic link is pushed here instead of being walked by recursion,
+    // which could overflow the kernel stack.
+    let mut names = Vec::new();
+    push_components(&mut names, path);
+    let mut follows = 0;
+    while let Some(name) = names.pop() {
+        // `find` fails if `inode` is not a directory
+        let next = inode.find(name.as_str())?;
+        let is_last = names.is_empty();
+        if next.is_symlink() && (follow || !is_last) {
+            follows += 1;
+            if follows > MAX_SYMLINK_FOLLOWS {
+                return None;
+            }
+            let target = next.read_link()?;
+            if target.starts_with('/') {
+                inode = ROOT_INODE.clone();
+            }
+            push_components(&mut names, target.as_str());
         } else {
-            // create file
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
+            inode = next;
         }
+    }
+    Some(inode)
+}
+
+/// Push the components of `path` except empty ones and `.` to `names`, the first one at the end.
+fn push_components(names: &mut Vec<String>, path: &str) {
+    names.extend(
+        path.split('/')
+            .rev()
+            .filter(|name| !name.is_empty() && *name != ".")
+            .map(String::from),
+    );
+}
+
+/// Split `path` into the directory containing the last component and its name.
+///
+/// # Return
+/// Conditional branching.
+/// - If the directory is not found or not a directory => `None`
+/// - If the last component is empty, `.` or `..`(e.g. `a/b/`, `/`) => `None`
+/// - Otherwise => (directory, name)
+fn find_parent<'a>(dir: &Arc<Inode>, path: &'a str) -> Option<(Arc<Inode>, &'a str)> {
+    let (dir_path, name) = match path.rfind('/') {
+        Some(idx) => (&path[..=idx], &path[idx + 1..]),
+        None => ("", path),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    // a bare name is in `dir` itself
+    let parent = if dir_path.is_empty() {
+        dir.clone()
     } else {
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
-                inode.clear();
-            }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+        find_path(dir, dir_path, true)?
+    };
+    if !parent.is_dir() {
+        return None;
+    }
+    Some((parent, name))
+}
+
+/// Find where `CREATE` creates the file of `path`: [`find_parent`] of `path`,
+/// or of the target if the last component is a dangling symbolic link(as POSIX does).
+///
+/// # Return
+/// Conditional branching.
+/// - If the directory is not found or not a directory => `None`
+/// - If more than `MAX_SYMLINK_FOLLOWS` symbolic links are followed => `None`
+/// - If the name already exists and is not a symbolic link => `None`
+/// - Otherwise => (directory, name)
+fn find_create_parent(dir: &Arc<Inode>, path: &str) -> Option<(Arc<Inode>, String)> {
+    let (mut parent, name) = find_parent(dir, path)?;
+    let mut name = String::from(name);
+    let mut follows = 0;
+    while let Some(link) = parent.find(name.as_str()) {
+        if !link.is_symlink() {
+            return None;
+        }
+        follows += 1;
+        if follows > MAX_SYMLINK_FOLLOWS {
+            return None;
+        }
+        // the target is relative to the directory containing the link
+        let target = link.read_link()?;
+        let (target_parent, target_name) = find_parent(&parent, target.as_str())?;
+        name = String::from(target_name);
+        parent = target_parent;
+    }
+    Some((parent, name))
+}
+
+/// Open the file of `path` from `ROOT_INODE`.
+///
+/// See [`open_file_at`], whose error is `None`.
+pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+    open_file_at(&ROOT_INODE, path, flags).ok()
+}
+
+/// The error of `create`/`mkdir`/`symlink` of `name` under `parent` that failed
+///
+/// # Return
+/// Conditional branching.
+/// - If `name` could be created if the disk had space => -`ENOSPC`
+/// - Otherwise => -1
+fn create_error(parent: &Inode, name: &str) -> isize {
+    if parent.can_create(name) {
+        -ENOSPC
+    } else {
+        -1
+    }
+}
+
+/// Open the file of `path` relative to the directory `dir`.
+///
+/// An existing file is cleared only with `TRUNC`, after every check below has passed,
+/// so a rejected open leaves the contents as they are.
+///
+/// Symbolic links in `path` are followed, except the last one with `NOFOLLOW`.
+/// `CREATE` on a dangling symbolic link creates its target.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found(and not created with `CREATE`) => `Err(-1)`
+/// - If `path` is created with `CREATE` and the disk is full => `Err(-ENOSPC)`
+/// - If `path` is a directory and opened for writing or with `TRUNC` => `Err(-1)`
+/// - If `path` is not a directory with `DIRECTORY` => `Err(-1)`
+/// - If `TRUNC` is given without write access => `Err(-1)`
+/// - If `path` is a symbolic link with `NOFOLLOW`, or too many symbolic links are followed => `Err(-1)`
+/// - Otherwise => The opened file
+pub fn open_file_at(dir: &Arc<Inode>, path: &str, flags: OpenFlags) -> Result<Arc<OSInode>, isize> {
+    let (readable, writable) = flags.read_write();
+    let follow = !flags.contains(OpenFlags::NOFOLLOW);
+    let inode = match find_path(dir, path, follow) {
+        // only with `NOFOLLOW`
+        Some(inode) if inode.is_symlink() => return Err(-1),
+        Some(inode) => inode,
+        None if flags.contains(OpenFlags::CREATE) => {
+            // create file
+            let (parent, name) = match find_create_parent(dir, path) {
+                Some(found) => found,
+                None => return Err(-1),
+            };
+            parent
+                .create(name.as_str())
+                .ok_or_else(|| create_error(&parent, name.as_str()))?
+        }
+        None => return Err(-1),
+    };
+    let is_dir = inode.is_dir();
+    let trunc = flags.contains(OpenFlags::TRUNC);
+    if (is_dir && (writable || trunc))
+        || (!is_dir && flags.contains(OpenFlags::DIRECTORY))
+        || (trunc && !writable)
+    {
+        return Err(-1);
+    }
+    if trunc {
+        // clear size
+        inode.clear();
+    }
+    Ok(Arc::new(OSInode::new(readable, writable, inode)))
+}
+
+/// Create a directory of `path` relative to the directory `dir`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` already exists, or its parent directory is not found => -1
+/// - If the disk is full => -`ENOSPC`
+/// - Otherwise => 0
+pub fn mkdir_at(dir: &Arc<Inode>, path: &str) -> isize {
+    match find_parent(dir, path) {
+        Some((parent, name)) if parent.mkdir(name).is_none() => create_error(&parent, name),
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// Remove the file(or the empty directory) of `path` relative to the directory `dir`.
+///
+/// # Parameters
+/// - `remove_dir`: Remove a directory instead of a file
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found, or its type does not match `remove_dir` => false
+/// - If the directory is not empty => false
+/// - Otherwise => true
+pub fn unlink_at(dir: &Arc<Inode>, path: &str, remove_dir: bool) -> bool {
+    match find_parent(dir, path) {
+        Some((parent, name)) if remove_dir => parent.rmdir(name),
+        Some((parent, name)) => parent.unlink(name),
+        None => false,
+    }
+}
+
+/// Add the entry `new_path` relative to `new_dir` that refers to the file `old_path` relative to `old_dir`.
+///
+/// # Parameters
+/// - `follow`: Link the target of `old_path` if it is a symbolic link, instead of the link itself
+///
+/// # Return
+/// Conditional branching.
+/// - If `old_path` is not found or a directory => false
+/// - If `new_path` already exists, or its parent directory is not found => false
+/// - Otherwise => true
+pub fn link_at(
+    old_dir: &Arc<Inode>,
+    old_path: &str,
+    new_dir: &Arc<Inode>,
+    new_path: &str,
+    follow: bool,
+) -> bool {
+    let target = match find_path(old_dir, old_path, follow) {
+        Some(target) => target,
+        None => return false,
+    };
+    match find_parent(new_dir, new_path) {
+        Some((parent, name)) => parent.link(name, &target),
+        None => false,
+    }
+}
+
+/// Create a symbolic link of `path` relative to the directory `dir` that refers to `target`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` already exists, or its parent directory is not found => -1
+/// - If `target` is empty => -1
+/// - If the disk is full => -`ENOSPC`
+/// - Otherwise => 0
+pub fn symlink_at(target: &str, dir: &Arc<Inode>, path: &str) -> isize {
+    if target.is_empty() {
+        return -1;
+    }
+    match find_parent(dir, path) {
+        Some((parent, name)) if parent.symlink(name, target).is_none() => {
+            create_error(&parent, name)
+        }
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// Get the target of the symbolic link of `path` relative to the directory `dir`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found or not a symbolic link => `None`
+/// - Otherwise => The target
+pub fn read_link_at(dir: &Arc<Inode>, path: &str) -> Option<String> {
+    find_path(dir, path, false)?.read_link()
+}
+
+/// Get the status of `path` relative to the directory `dir`.
+///
+/// # Parameters
+/// - `follow`: Whether to report the target instead if `path` itself is a symbolic link
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found => `None`
+/// - Otherwise => The status
+pub fn stat_at(dir: &Arc<Inode>, path: &str, follow: bool) -> Option<Stat> {
+    Some(find_path(dir, path, follow)?.metadata().into())
+}
+
+/// Change the size of the file of `path` relative to the directory `dir` to `len` bytes.
+///
+/// A symbolic link is followed.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found => false
+/// - If [`Inode::truncate`] fails => false
+/// - Otherwise => true
+pub fn truncate_at(dir: &Arc<Inode>, path: &str, len: usize) -> bool {
+    match find_path(dir, path, true) {
+        Some(inode) => inode.truncate(len),
+        None => false,
+    }
+}
+
+/// Move `old_path` relative to `old_dir` to `new_path` relative to `new_dir`.
+///
+/// An existing `new_path` is replaced as [`Inode::rename`] does.
+///
+/// # Return
+/// Conditional branching.
+/// - If either parent directory is not found, or either last component is `.` or `..` => false
+/// - If [`Inode::rename`] fails => false
+/// - Otherwise => true
+pub fn rename_at(
+    old_dir: &Arc<Inode>,
+    old_path: &str,
+    new_dir: &Arc<Inode>,
+    new_path: &str,
+) -> bool {
+    match (
+        find_parent(old_dir, old_path),
+        find_parent(new_dir, new_path),
+    ) {
+        (Some((old_parent, old_name)), Some((new_parent, new_name))) => {
+            old_parent.rename(old_name, &new_parent, new_name)
+        }
+        _ => false,
     }
 }
 
@@ -140,29 +483,138 @@ impl File for OSInode {
         self.writable
     }
 
-    fn read(&self, mut buf: crate::mm::UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
-        let mut total_read_size = 0usize;
-        for slice in buf.buffers.iter_mut() {
-            let read_size = inner.inode.read_at(inner.offset, slice);
-            if read_size == 0 {
+    fn read(&self, buf: UserBuffer) -> isize {
+        let mut inner = self.inner.lock();
+        let read_size = read_inode(&inner.inode, inner.offset, buf);
+        inner.offset += read_size;
+        read_size as isize
+    }
+
+    fn write(&self, buf: UserBuffer) -> isize {
+        let mut inner = self.inner.lock();
+        let write_size = write_inode(&inner.inode, inner.offset, buf);
+        if write_size > 0 {
+            inner.offset += write_size as usize;
+        }
+        write_size
+    }
+
+    fn inode(&self) -> Option<Arc<Inode>> {
+        Some(self.inner.lock().inode.clone())
+    }
+
+    fn stat(&self) -> Stat {
+        self.inner.lock().inode.metadata().into()
+    }
+
+    fn seek(&self, offset: isize, whence: usize) -> isize {
+        let mut inner = self.inner.lock();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => inner.offset as isize,
+            SEEK_END => inner.inode.size() as isize,
+            _ => return -1,
+        };
+        // the offset may go beyond the end of the file
+        match base.checked_add(offset) {
+            Some(new_offset) if new_offset >= 0 => {
+                inner.offset = new_offset as usize;
+                new_offset
+            }
+            _ => -1,
+        }
+    }
+
+    fn pread(&self, buf: UserBuffer, offset: usize) -> isize {
+        let inode = self.inner.lock().inode.clone();
+        read_inode(&inode, offset, buf) as isize
+    }
+
+    fn pwrite(&self, buf: UserBuffer, offset: usize) -> isize {
+        let inode = self.inner.lock().inode.clone();
+        write_inode(&inode, offset, buf)
+    }
+
+    /// The offset of a directory is the index of the next directory entry
+    fn getdents(&self, buf: UserBuffer) -> isize {
+        let mut inner = self.inner.lock();
+        if !inner.inode.is_dir() {
+            return -1;
+        }
+        let len = buf.len();
+        let mut records = Vec::new();
+        while let Some((idx, entry)) = inner.inode.read_dir(inner.offset) {
+            let record = dirent64_record(&entry, idx + 1);
+            if records.len() + record.len() > len {
+                if records.is_empty() {
+                    // the buffer is too small for even one entry
+                    return -1;
+                }
                 break;
             }
-            inner.offset += read_size;
-            total_read_size += read_size;
+            records.extend_from_slice(&record);
+            inner.offset = idx + 1;
         }
-        total_read_size
+        for (dst, &byte) in buf.into_iter().zip(records.iter()) {
+            unsafe {
+                *dst = byte;
+            }
+        }
+        records.len() as isize
+    }
+
+    fn truncate(&self, len: usize) -> bool {
+        self.writable && self.inner.lock().inode.truncate(len)
+    }
+}
+
+/// Read `inode` from `offset` to `buf`.
+///
+/// # Return
+/// Size of buffer read(0 for a directory, whose entries are not read as data)
+fn read_inode(inode: &Inode, mut offset: usize, mut buf: UserBuffer) -> usize {
+    if inode.is_dir() {
+        return 0;
     }
+    let mut total_read_size = 0usize;
+    for slice in buf.buffers.iter_mut() {
+        let read_size = inode.read_at(offset, slice);
+        if read_size == 0 {
+            break;
+        }
+        offset += read_size;
+        total_read_size += read_size;
+    }
+    total_read_size
+}
 
-    fn write(&self, buf: crate::mm::UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
-        let mut total_write_size = 0usize;
-        for slice in buf.buffers.iter() {
-            let write_size = inner.inode.write_at(inner.offset, slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
-            total_write_size += write_size;
+/// Write `buf` to `inode` from `offset`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `inode` is a directory, whose entries are not written as data => -1
+/// - If the file would exceed the max file size => -`EFBIG`
+/// - If the disk is full before anything is written => -`ENOSPC`
+/// - Otherwise => Size of written buffer, which is short if the disk becomes full
+fn write_inode(inode: &Inode, mut offset: usize, buf: UserBuffer) -> isize {
+    if inode.is_dir() {
+        return -1;
+    }
+    match offset.checked_add(buf.len()) {
+        Some(end) if end <= Inode::max_size() => {}
+        _ => return -EFBIG,
+    }
+    let mut total_write_size = 0usize;
+    for slice in buf.buffers.iter() {
+        let write_size = inode.write_at(offset, slice);
+        offset += write_size;
+        total_write_size += write_size;
+        if write_size < slice.len() {
+            break;
         }
-        total_write_size
     }
+    if total_write_size == 0 && buf.len() > 0 {
+        return -ENOSPC;
+    }
+    total_write_size as isize
 }
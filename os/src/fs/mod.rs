@@ -1,9 +1,29 @@
 //! File system in os
+mod dirent;
 mod inode;
 mod pipe;
+mod stat;
 mod stdio;
+mod tty;
 
 use crate::mm::UserBuffer;
+use alloc::sync::Arc;
+use easy_fs::Inode;
+
+/// `whence` of `lseek`: The offset is set to `offset` bytes
+pub const SEEK_SET: usize = 0;
+/// `whence` of `lseek`: The offset is set to the current offset plus `offset` bytes
+pub const SEEK_CUR: usize = 1;
+/// `whence` of `lseek`: The offset is set to the size of the file plus `offset` bytes
+pub const SEEK_END: usize = 2;
+/// Error number of an I/O error(e.g. a background process reading the console with `SIGTTIN` blocked)
+pub const EIO: isize = 5;
+/// Error number of a write beyond the max file size
+pub const EFBIG: isize = 27;
+/// Error number of a write to a full disk
+pub const ENOSPC: isize = 28;
+/// Error number of the file that has no offset(e.g. a pipe or the console)
+pub const ESPIPE: isize = 29;
 
 /// File trait
 pub trait File: Send + Sync {
@@ -14,15 +34,87 @@ pub trait File: Send + Sync {
     /// Read file to `UserBuffer`
     ///
     /// # Return
-    /// Size of buffer read
-    fn read(&self, buf: UserBuffer) -> usize;
+    /// Size of buffer read, or a negative error number
+    fn read(&self, buf: UserBuffer) -> isize;
     /// Write `UserBuffer` to file
     ///
     /// # Return
-    /// Size of written buffer
-    fn write(&self, buf: UserBuffer) -> usize;
+    /// Size of written buffer, or a negative error number
+    fn write(&self, buf: UserBuffer) -> isize;
+    /// Control the device
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If the file is not a device that accepts `cmd` => -1
+    /// - Otherwise => Depends on `cmd`
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> isize {
+        -1
+    }
+    /// Inode of the file if it is on the disk(a regular file or a directory)
+    fn inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
+    /// Status of the file reported by `fstat`
+    fn stat(&self) -> Stat;
+    /// Move the offset used by `read`/`write`
+    ///
+    /// # Parameters
+    /// - `whence`: `SEEK_SET`, `SEEK_CUR` or `SEEK_END` that `offset` is relative to
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If the file has no offset => -`ESPIPE`
+    /// - If `whence` is unknown or the new offset is negative => -1
+    /// - Otherwise => The new offset
+    fn seek(&self, _offset: isize, _whence: usize) -> isize {
+        -ESPIPE
+    }
+    /// Read file to `UserBuffer` from `offset` without moving the offset
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If the file has no offset => -`ESPIPE`
+    /// - Otherwise => Size of buffer read
+    fn pread(&self, _buf: UserBuffer, _offset: usize) -> isize {
+        -ESPIPE
+    }
+    /// Write `UserBuffer` to file from `offset` without moving the offset
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If the file has no offset => -`ESPIPE`
+    /// - Otherwise => Size of written buffer
+    fn pwrite(&self, _buf: UserBuffer, _offset: usize) -> isize {
+        -ESPIPE
+    }
+    /// Read the entries of a directory to `UserBuffer` as `struct linux_dirent64` records
+    ///
+    /// The listing resumes from the entry after the last one read.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If the file is not a directory, or the next record does not fit in the buffer => -1
+    /// - If all entries have been read => 0
+    /// - Otherwise => Size of the records read
+    fn getdents(&self, _buf: UserBuffer) -> isize {
+        -1
+    }
+    /// Change the size of the file to `len` bytes
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If the file is not a regular file opened for writing => false
+    /// - Otherwise => true
+    fn truncate(&self, _len: usize) -> bool {
+        false
+    }
 }
 
-pub use inode::{list_apps, open_file, OSInode, OpenFlags};
+pub use inode::{
+    link_at, list_apps, mkdir_at, open_file, open_file_at, read_link_at, rename_at, stat_at,
+    symlink_at, truncate_at, unlink_at, OSInode, OpenFlags, ROOT_INODE,
+};
 pub use pipe::make_pipe;
+pub use stat::Stat;
 pub use stdio::{Stdin, Stdout};
+pub use tty::TTY;
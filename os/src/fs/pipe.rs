@@ -1,13 +1,14 @@
-use crate::{sync::UPSafeCell, task::suspend_current_and_run_next};
+use crate::{sync::SpinLock, task::suspend_current_and_run_next};
 use alloc::sync::{Arc, Weak};
 
+use super::stat::{Stat, S_IFIFO};
 use super::File;
 
 /// Structure that stores information necessary to perform pipe processing
 pub struct Pipe {
     readable: bool,
     writable: bool,
-    buffer: Arc<UPSafeCell<PipeRingBuffer>>,
+    buffer: Arc<SpinLock<PipeRingBuffer>>,
 }
 
 impl Pipe {
@@ -15,7 +16,7 @@ impl Pipe {
     /// - readable: true
     /// - writable: false
     /// - buffer: first argument
-    pub fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn read_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: true,
             writable: false,
@@ -27,7 +28,7 @@ impl Pipe {
     /// - readable: false
     /// - writable: true
     /// - buffer: first argument
-    pub fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn write_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: false,
             writable: true,
@@ -162,10 +163,10 @@ impl PipeRingBuffer {
 /// # Return
 /// (read_end, write_end)
 pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
-    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    let buffer = Arc::new(SpinLock::new(PipeRingBuffer::new()));
     let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
     let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
-    buffer.exclusive_access().set_write_end(&write_end);
+    buffer.lock().set_write_end(&write_end);
     (read_end, write_end)
 }
 
@@ -178,16 +179,16 @@ impl File for Pipe {
         self.writable
     }
 
-    fn read(&self, buf: crate::mm::UserBuffer) -> usize {
+    fn read(&self, buf: crate::mm::UserBuffer) -> isize {
         assert!(self.readable);
         let mut buf_iter = buf.into_iter();
         let mut read_size = 0usize;
         loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
+            let mut ring_buffer = self.buffer.lock();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
                 if ring_buffer.all_write_ends_closed() {
-                    return read_size;
+                    return read_size as isize;
                 }
                 drop(ring_buffer);
                 suspend_current_and_run_next();
@@ -201,18 +202,18 @@ impl File for Pipe {
                     }
                     read_size += 1;
                 } else {
-                    return read_size;
+                    return read_size as isize;
                 }
             }
         }
     }
 
-    fn write(&self, buf: crate::mm::UserBuffer) -> usize {
+    fn write(&self, buf: crate::mm::UserBuffer) -> isize {
         assert!(self.writable);
         let mut buf_iter = buf.into_iter();
         let mut write_size = 0usize;
         loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
+            let mut ring_buffer = self.buffer.lock();
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
                 drop(ring_buffer);
@@ -225,9 +226,13 @@ impl File for Pipe {
                     ring_buffer.write_byte(unsafe { *byte_ref });
                     write_size += 1;
                 } else {
-                    return write_size;
+                    return write_size as isize;
                 }
             }
         }
     }
+
+    fn stat(&self) -> Stat {
+        Stat::special(S_IFIFO | 0o600)
+    }
 }
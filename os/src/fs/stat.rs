@@ -0,0 +1,86 @@
+//! `struct stat` of Linux reported by `fstat`/`fstatat`
+use easy_fs::{Metadata, BLOCK_SZ};
+
+/// File type bits of `st_mode`: FIFO(pipe)
+pub const S_IFIFO: u32 = 0o010000;
+/// File type bits of `st_mode`: character device
+pub const S_IFCHR: u32 = 0o020000;
+
+/// File status(the layout of `struct stat` of Linux on RISC-V 64)
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stat {
+    /// Device containing the file
+    pub st_dev: u64,
+    /// Inode number
+    pub st_ino: u64,
+    /// File type and permission bits
+    pub st_mode: u32,
+    /// Number of hard links
+    pub st_nlink: u32,
+    /// User id of the owner
+    pub st_uid: u32,
+    /// Group id of the owner
+    pub st_gid: u32,
+    /// Device id(if it is a special file)
+    pub st_rdev: u64,
+    __pad1: u64,
+    /// Total size in bytes
+    pub st_size: i64,
+    /// Block size for the file system I/O
+    pub st_blksize: i32,
+    __pad2: i32,
+    /// Number of 512-byte blocks allocated
+    pub st_blocks: i64,
+    /// Time of the last access(seconds)
+    pub st_atime: i64,
+    /// Time of the last access(nanoseconds)
+    pub st_atime_nsec: u64,
+    /// Time of the last modification(seconds)
+    pub st_mtime: i64,
+    /// Time of the last modification(nanoseconds)
+    pub st_mtime_nsec: u64,
+    /// Time of the last status change(seconds)
+    pub st_ctime: i64,
+    /// Time of the last status change(nanoseconds)
+    pub st_ctime_nsec: u64,
+    __unused: [u32; 2],
+}
+
+impl Stat {
+    /// Status of a file that is not on the disk(e.g. a pipe)
+    ///
+    /// # Parameters
+    /// - `mode`: File type and permission bits
+    pub fn special(mode: u32) -> Self {
+        Self {
+            st_mode: mode,
+            st_nlink: 1,
+            st_blksize: BLOCK_SZ as i32,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Metadata> for Stat {
+    fn from(metadata: Metadata) -> Self {
+        Self {
+            st_ino: metadata.inode_id as u64,
+            st_mode: metadata.mode,
+            st_nlink: metadata.nlink,
+            st_uid: metadata.uid,
+            st_gid: metadata.gid,
+            st_size: metadata.size as i64,
+            st_blksize: BLOCK_SZ as i32,
+            // `BLOCK_SZ` is 512 bytes
+            st_blocks: metadata.blocks as i64,
+            st_atime: metadata.atime.sec as i64,
+            st_atime_nsec: metadata.atime.nsec,
+            st_mtime: metadata.mtime.sec as i64,
+            st_mtime_nsec: metadata.mtime.nsec,
+            st_ctime: metadata.ctime.sec as i64,
+            st_ctime_nsec: metadata.ctime.nsec,
+            ..Default::default()
+        }
+    }
+}
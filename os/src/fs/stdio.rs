@@ -1,6 +1,9 @@
 //! Stdin & Stdout
+//!
+//! Both of them are the console [`super::TTY`].
+use super::stat::{Stat, S_IFCHR};
+use super::tty::TTY;
 use super::File;
-use crate::{sbi::console_getchar, task::suspend_current_and_run_next};
 
 pub struct Stdin;
 pub struct Stdout;
@@ -14,30 +17,21 @@ impl File for Stdin {
         false
     }
 
-    fn read(&self, mut user_buf: crate::mm::UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-        // busy loop;
-        let mut c: usize;
-        loop {
-            c = console_getchar();
-            //If no input comes in, let it yield and execute the next task.
-            if c == 0 {
-                suspend_current_and_run_next();
-                continue;
-            } else {
-                break;
-            }
-        }
-        let ch = c as u8;
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
-        }
-        1
-    }
-
-    fn write(&self, _user_buf: crate::mm::UserBuffer) -> usize {
+    fn read(&self, user_buf: crate::mm::UserBuffer) -> isize {
+        TTY.read(user_buf)
+    }
+
+    fn write(&self, _user_buf: crate::mm::UserBuffer) -> isize {
         panic!("Cannot write to stdin!");
     }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        TTY.ioctl(cmd, arg)
+    }
+
+    fn stat(&self) -> Stat {
+        Stat::special(S_IFCHR | 0o620)
+    }
 }
 
 impl File for Stdout {
@@ -49,14 +43,19 @@ impl File for Stdout {
         true
     }
 
-    fn read(&self, _user_buf: crate::mm::UserBuffer) -> usize {
+    fn read(&self, _user_buf: crate::mm::UserBuffer) -> isize {
         panic!("Cannot read to stdin!");
     }
 
-    fn write(&self, user_buf: crate::mm::UserBuffer) -> usize {
-        for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(buffer).unwrap());
-        }
-        user_buf.len()
+    fn write(&self, user_buf: crate::mm::UserBuffer) -> isize {
+        TTY.write(user_buf) as isize
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        TTY.ioctl(cmd, arg)
+    }
+
+    fn stat(&self) -> Stat {
+        Stat::special(S_IFCHR | 0o620)
     }
 }
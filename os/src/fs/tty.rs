@@ -0,0 +1,419 @@
+//! TTY line discipline of the console
+//!
+//! Bytes received by the UART are processed here before `Stdin` reads them.
+//!
+//! - In canonical mode(`ICANON`), input is edited line by line(`VERASE`/`VKILL`)
+//!   and a reader gets at most one line at a time. `VEOF`(^D) on an empty line is the end of file.
+//! - Otherwise(raw mode), every byte is passed to readers as it is.
+//! - With `ISIG`, `VINTR`(^C)/`VSUSP`(^Z) send `SIGINT`/`SIGTSTP` to the foreground process group.
+//! - With `ECHO`, input is echoed back to the console.
+//!
+//! The mode is controlled by `ioctl` with `TCGETS`/`TCSETS`, as Linux does.
+//!
+//! The console is the controlling terminal of the session of `initproc`, which every process
+//! joins unless it calls `setsid`. A process of a background process group in the session that
+//! reads the console is stopped by `SIGTTIN`.
+use super::EIO;
+use crate::console::print_bytes;
+use crate::drivers::{CharDevice, UART};
+use crate::mm::{translated_ref, translated_refmut, UserBuffer};
+use crate::sync::SpinLock;
+use crate::task::{
+    add_task, block_current_and_run_next, current_handling_sig, current_process, current_task,
+    current_user_token, group_exists_in_session, handle_signals_in_kernel, send_signal_to_group,
+    SignalFlags, TaskControlBlock, IDLE_PID,
+};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Get the current `Termios`
+pub const TCGETS: usize = 0x5401;
+/// Set the current `Termios`
+pub const TCSETS: usize = 0x5402;
+/// Get the foreground process group
+pub const TIOCGPGRP: usize = 0x540f;
+/// Set the foreground process group
+pub const TIOCSPGRP: usize = 0x5410;
+
+/// Number of control characters
+const NCCS: usize = 19;
+/// Index of `c_cc`: interrupt(send `SIGINT`)
+const VINTR: usize = 0;
+/// Index of `c_cc`: erase a character
+const VERASE: usize = 2;
+/// Index of `c_cc`: erase the line
+const VKILL: usize = 3;
+/// Index of `c_cc`: end of file
+const VEOF: usize = 4;
+/// Index of `c_cc`: minimum number of bytes for a read in raw mode(unused: always 1)
+const VMIN: usize = 6;
+/// Index of `c_cc`: suspend(send `SIGTSTP`)
+const VSUSP: usize = 10;
+
+/// Maximum length of a line in canonical mode
+const MAX_LINE: usize = 4095;
+
+bitflags! {
+    /// Input modes(`c_iflag`)
+    pub struct InputFlags: u32 {
+        /// Translate CR to NL on input
+        const ICRNL = 0o400;
+    }
+}
+
+bitflags! {
+    /// Local modes(`c_lflag`)
+    pub struct LocalFlags: u32 {
+        /// Send signals for `VINTR`/`VSUSP`
+        const ISIG = 0o1;
+        /// Canonical mode
+        const ICANON = 0o2;
+        /// Echo input
+        const ECHO = 0o10;
+        /// Erase the character on the screen for `VERASE`
+        const ECHOE = 0o20;
+    }
+}
+
+/// Terminal attributes(the layout of `struct termios` of Linux kernel)
+///
+/// Only the flags in [`InputFlags`] and [`LocalFlags`] take effect. The others are just kept.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Termios {
+    /// Input modes
+    pub c_iflag: u32,
+    /// Output modes
+    pub c_oflag: u32,
+    /// Control modes
+    pub c_cflag: u32,
+    /// Local modes
+    pub c_lflag: u32,
+    /// Line discipline
+    pub c_line: u8,
+    /// Control characters
+    pub c_cc: [u8; NCCS],
+}
+
+impl Default for Termios {
+    fn default() -> Self {
+        let mut c_cc = [0; NCCS];
+        c_cc[VINTR] = 0x03; // ^C
+        c_cc[VERASE] = 0x7f; // DEL
+        c_cc[VKILL] = 0x15; // ^U
+        c_cc[VEOF] = 0x04; // ^D
+        c_cc[VMIN] = 1;
+        c_cc[VSUSP] = 0x1a; // ^Z
+        Self {
+            c_iflag: InputFlags::ICRNL.bits(),
+            c_oflag: 0o5,   // OPOST | ONLCR
+            c_cflag: 0o277, // B38400 | CS8 | CREAD
+            c_lflag: (LocalFlags::ISIG | LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ECHOE)
+                .bits(),
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
+
+impl Termios {
+    fn iflag(&self) -> InputFlags {
+        InputFlags::from_bits_truncate(self.c_iflag)
+    }
+
+    fn lflag(&self) -> LocalFlags {
+        LocalFlags::from_bits_truncate(self.c_lflag)
+    }
+}
+
+struct TtyInner {
+    termios: Termios,
+    /// Line being edited in canonical mode
+    line: Vec<u8>,
+    /// Input that readers can take.
+    ///
+    /// In canonical mode, each element is a line(an empty one is the end of file).
+    /// In raw mode, each element is a byte.
+    ready: VecDeque<Vec<u8>>,
+    /// Threads blocked in `read` until input is ready
+    readers: Vec<Arc<TaskControlBlock>>,
+    /// Session that the console is the controlling terminal of
+    session: usize,
+    /// Process group that receives the signals of `VINTR`/`VSUSP`
+    foreground_pgrp: Option<usize>,
+}
+
+/// TTY of the console
+pub struct Tty {
+    inner: SpinLock<TtyInner>,
+}
+
+lazy_static! {
+    /// TTY between the UART and `Stdin`
+    pub static ref TTY: Tty = Tty {
+        inner: SpinLock::new(TtyInner {
+            termios: Termios::default(),
+            line: Vec::new(),
+            ready: VecDeque::new(),
+            readers: Vec::new(),
+            // the session of `initproc`
+            session: IDLE_PID,
+            foreground_pgrp: None,
+        }),
+    };
+}
+
+impl TtyInner {
+    fn echo(&self, bytes: &[u8]) {
+        if self.termios.lflag().contains(LocalFlags::ECHO) {
+            for &ch in bytes {
+                UART.write(ch);
+            }
+        }
+    }
+
+    /// Process a received byte.
+    ///
+    /// # Return
+    /// The signal to send to the foreground process group, if any.
+    fn receive(&mut self, mut ch: u8) -> Option<SignalFlags> {
+        let termios = self.termios;
+        let lflag = termios.lflag();
+        if ch == b'\r' && termios.iflag().contains(InputFlags::ICRNL) {
+            ch = b'\n';
+        }
+        if lflag.contains(LocalFlags::ISIG) {
+            let signal = if ch == termios.c_cc[VINTR] {
+                Some((SignalFlags::SIGINT, b"^C\n"))
+            } else if ch == termios.c_cc[VSUSP] {
+                Some((SignalFlags::SIGTSTP, b"^Z\n"))
+            } else {
+                None
+            };
+            if let Some((signal, echo)) = signal {
+                self.line.clear();
+                self.echo(echo);
+                return Some(signal);
+            }
+        }
+        if !lflag.contains(LocalFlags::ICANON) {
+            self.echo(&[ch]);
+            self.ready.push_back(alloc::vec![ch]);
+            return None;
+        }
+        if ch == termios.c_cc[VERASE] || ch == b'\x08' {
+            if self.line.pop().is_some() && lflag.contains(LocalFlags::ECHOE) {
+                self.echo(b"\x08 \x08");
+            }
+        } else if ch == termios.c_cc[VKILL] {
+            while self.line.pop().is_some() {
+                if lflag.contains(LocalFlags::ECHOE) {
+                    self.echo(b"\x08 \x08");
+                }
+            }
+        } else if ch == termios.c_cc[VEOF] {
+            // pass the line without a newline. An empty one is the end of file.
+            let line = core::mem::take(&mut self.line);
+            self.ready.push_back(line);
+        } else if ch == b'\n' {
+            self.echo(b"\n");
+            let mut line = core::mem::take(&mut self.line);
+            line.push(ch);
+            self.ready.push_back(line);
+        } else if self.line.len() < MAX_LINE {
+            self.echo(&[ch]);
+            self.line.push(ch);
+        }
+        None
+    }
+
+    /// Move ready input to `buf`.
+    ///
+    /// # Return
+    /// Number of bytes moved. 0 means the end of file if `self.ready` was not empty.
+    fn take_ready(&mut self, buf: &mut UserBuffer) -> usize {
+        let canonical = self.termios.lflag().contains(LocalFlags::ICANON);
+        let mut read_size = 0;
+        'fill: for slice in buf.buffers.iter_mut() {
+            let mut filled = 0;
+            while filled < slice.len() {
+                let chunk = match self.ready.front_mut() {
+                    Some(chunk) if !chunk.is_empty() => chunk,
+                    _ => break 'fill,
+                };
+                // copy as much of the chunk as fits at once
+                let n = chunk.len().min(slice.len() - filled);
+                slice[filled..filled + n].copy_from_slice(&chunk[..n]);
+                chunk.drain(..n);
+                filled += n;
+                read_size += n;
+                if chunk.is_empty() {
+                    self.ready.pop_front();
+                    // a read returns at most one line
+                    if canonical {
+                        break 'fill;
+                    }
+                }
+            }
+        }
+        if read_size == 0 {
+            // the end of file
+            self.ready.pop_front();
+        }
+        read_size
+    }
+}
+
+impl Tty {
+    /// Process the bytes received by the UART.
+    ///
+    /// It is called after the UART interrupt is handled.
+    pub fn handle_input(&self) {
+        let mut signals = Vec::new();
+        let mut inner = self.inner.lock();
+        while let Some(ch) = UART.read() {
+            if let Some(signal) = inner.receive(ch) {
+                signals.push(signal);
+            }
+        }
+        // Send the signals before waking up the readers, so that the readers see them.
+        if let Some(pgrp) = inner.foreground_pgrp {
+            for &signal in signals.iter() {
+                send_signal_to_group(pgrp, signal);
+            }
+        }
+        let readers = if !inner.ready.is_empty() || !signals.is_empty() {
+            core::mem::take(&mut inner.readers)
+        } else {
+            Vec::new()
+        };
+        drop(inner);
+        for task in readers {
+            add_task(task);
+        }
+    }
+
+    /// Read the input to `buf`. Block the current thread until input is ready.
+    ///
+    /// A reader in a background process group of the session stops its group by `SIGTTIN`,
+    /// and goes on reading when it is continued in the foreground.
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If a signal terminates the current process or is caught by a handler while waiting => 0
+    /// - If the current process in the background blocks `SIGTTIN` => -`EIO`
+    /// - If the end of file is input(`VEOF` on an empty line) => 0
+    /// - Otherwise => Number of bytes read(at most one line in canonical mode)
+    pub fn read(&self, mut buf: UserBuffer) -> isize {
+        if buf.len() == 0 {
+            return 0;
+        }
+        let process = current_process();
+        loop {
+            let process_inner = process.inner_exclusive_access();
+            let (pgid, sid) = (process_inner.pgid, process_inner.sid);
+            let blocked = process_inner.blocked_signals(current_handling_sig());
+            let signaled = !(process_inner.signals - blocked).is_empty();
+            drop(process_inner);
+            if signaled {
+                if handle_signals_in_kernel() {
+                    return 0;
+                }
+                continue;
+            }
+
+            let mut inner = self.inner.lock();
+            if sid == inner.session && inner.foreground_pgrp.map_or(false, |fg| fg != pgid) {
+                drop(inner);
+                if blocked.contains(SignalFlags::SIGTTIN) {
+                    return -EIO;
+                }
+                send_signal_to_group(pgid, SignalFlags::SIGTTIN);
+                continue;
+            }
+            if !inner.ready.is_empty() {
+                return inner.take_ready(&mut buf) as isize;
+            }
+            // Check the signals again under the lock, since `handle_input` sends them
+            // before waking up the readers.
+            let process_inner = process.inner_exclusive_access();
+            if !(process_inner.signals - process_inner.blocked_signals(current_handling_sig()))
+                .is_empty()
+            {
+                continue;
+            }
+            drop(process_inner);
+            inner.readers.push(current_task().unwrap());
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+
+    /// Write `buf` to the console.
+    pub fn write(&self, buf: UserBuffer) -> usize {
+        for buffer in buf.buffers.iter() {
+            print_bytes(buffer);
+        }
+        buf.len()
+    }
+
+    /// Control the TTY.
+    ///
+    /// # Parameters
+    /// - `cmd`:
+    ///   - `TCGETS`: store the current `Termios` in `arg`
+    ///   - `TCSETS`: set the `Termios` at `arg`
+    ///   - `TIOCGPGRP`: store the foreground process group(`i32`) in `arg`
+    ///   - `TIOCSPGRP`: set the foreground process group to the `i32` at `arg`
+    /// - `arg`: Address of the argument in the current address space
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `cmd` is unknown, or `arg` is 0 => -1
+    /// - `TIOCGPGRP`/`TIOCSPGRP` from a process in another session => -1
+    /// - `TIOCGPGRP` when no foreground process group is set => -1
+    /// - If there is no process group at `arg` in the session of the console => -1
+    /// - Otherwise => 0
+    pub fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        if arg == 0 {
+            return -1;
+        }
+        let token = current_user_token();
+        let sid = current_process().inner_exclusive_access().sid;
+        let mut inner = self.inner.lock();
+        match cmd {
+            TCGETS => *translated_refmut(token, arg as *mut Termios) = inner.termios,
+            TCSETS => {
+                let termios = *translated_ref(token, arg as *const Termios);
+                if !termios.lflag().contains(LocalFlags::ICANON) {
+                    // the line being edited becomes input as it is
+                    let line = core::mem::take(&mut inner.line);
+                    inner
+                        .ready
+                        .extend(line.into_iter().map(|ch| alloc::vec![ch]));
+                }
+                inner.termios = termios;
+            }
+            TIOCGPGRP => match inner.foreground_pgrp {
+                Some(pgrp) if sid == inner.session => {
+                    *translated_refmut(token, arg as *mut i32) = pgrp as i32
+                }
+                _ => return -1,
+            },
+            TIOCSPGRP => {
+                let pgrp = *translated_ref(token, arg as *const i32);
+                if sid != inner.session
+                    || pgrp < 0
+                    || !group_exists_in_session(pgrp as usize, inner.session)
+                {
+                    return -1;
+                }
+                inner.foreground_pgrp = Some(pgrp as usize);
+            }
+            _ => return -1,
+        }
+        0
+    }
+}
@@ -0,0 +1,113 @@
+//! Hart(hardware thread) management
+//!
+//! The boot hart initializes the kernel and starts the other harts through the SBI HSM extension.
+//! Each hart enters `_start_secondary` in `entry.asm`, and then [`crate::rust_main_secondary`].
+//!
+//! While a hart runs in the kernel, `tp` holds its hart id(see `entry.asm` and `trap.S`).
+//!
+//! Interrupts are enabled in the kernel except while [`push_off`] is in effect.
+
+use crate::config::MAX_HARTS;
+use crate::sbi::{hart_start, remote_sfence_vma, send_ipi};
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use riscv::register::sstatus;
+
+/// Bit mask of harts that run tasks(bit i = hart i)
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const DEPTH_INIT: AtomicUsize = AtomicUsize::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ENABLED_INIT: AtomicBool = AtomicBool::new(false);
+/// Nesting depth of `push_off` of each hart
+static PUSH_OFF_DEPTH: [AtomicUsize; MAX_HARTS] = [DEPTH_INIT; MAX_HARTS];
+/// Were interrupts enabled before the outermost `push_off` of each hart?
+static INTR_ENABLED: [AtomicBool; MAX_HARTS] = [ENABLED_INIT; MAX_HARTS];
+
+/// Get the id of the hart running this code
+pub fn hart_id() -> usize {
+    let id;
+    unsafe {
+        asm!("mv {}, tp", out(reg) id);
+    }
+    id
+}
+
+/// Disable interrupts of the current hart until the matching [`pop_off`].
+///
+/// It can be nested. Interrupts are enabled again by the outermost `pop_off` if they were enabled
+/// before the outermost `push_off`.
+pub fn push_off() {
+    let enabled = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+    }
+    let id = hart_id();
+    if PUSH_OFF_DEPTH[id].fetch_add(1, Ordering::Relaxed) == 0 {
+        INTR_ENABLED[id].store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Undo one [`push_off`].
+///
+/// # Panics
+/// Panics if there is no matching `push_off`, or interrupts have been enabled since then.
+pub fn pop_off() {
+    assert!(!sstatus::read().sie(), "pop_off: interrupts are enabled");
+    let id = hart_id();
+    let depth = PUSH_OFF_DEPTH[id].fetch_sub(1, Ordering::Relaxed);
+    assert!(depth > 0, "pop_off: no matching push_off");
+    if depth == 1 && INTR_ENABLED[id].load(Ordering::Relaxed) {
+        unsafe {
+            sstatus::set_sie();
+        }
+    }
+}
+
+/// Record that the current hart is ready to run tasks.
+pub fn set_online() {
+    ONLINE_HARTS.fetch_or(1 << hart_id(), Ordering::SeqCst);
+}
+
+/// Start the harts other than the current one.
+///
+/// Ids that do not exist are just rejected by the SBI.
+pub fn start_other_harts() {
+    extern "C" {
+        fn _start_secondary();
+    }
+    for id in (0..MAX_HARTS).filter(|&id| id != hart_id()) {
+        hart_start(id, _start_secondary as usize, 0);
+    }
+}
+
+/// Bit mask of online harts except the current one
+fn other_harts() -> usize {
+    ONLINE_HARTS.load(Ordering::SeqCst) & !(1 << hart_id())
+}
+
+/// Interrupt the other harts by IPIs.
+///
+/// A hart running in user mode enters the kernel at once and checks the signals of its process.
+/// A hart running in the kernel takes the interrupt when it returns to user mode.
+pub fn kick_other_harts() {
+    let mask = other_harts();
+    if mask != 0 {
+        send_ipi(mask);
+    }
+}
+
+/// Flush the TLB entries of `[start_va, start_va + size)` on every hart.
+///
+/// Call this after removing mappings and before the unmapped frames are reused,
+/// otherwise other harts may keep accessing the frames through stale TLB entries.
+pub fn tlb_shootdown(start_va: usize, size: usize) {
+    unsafe {
+        asm!("sfence.vma");
+    }
+    let mask = other_harts();
+    if mask != 0 {
+        remote_sfence_vma(mask, start_va, size);
+    }
+}
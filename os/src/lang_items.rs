@@ -28,6 +28,7 @@ fn panic(info: &PanicInfo) -> ! {
     unsafe {
         backtrace();
     }
+    crate::console::flush();
     shutdown()
 }
 
@@ -13,6 +13,9 @@
 //!
 //! We then call [`task::run_first_task()`] and for the first time go to
 //! userspace.
+//!
+//! The other harts are started after the initialization and enter
+//! [`rust_main_secondary()`].
 
 #![deny(missing_docs)]
 #![deny(warnings)]
@@ -26,7 +29,7 @@ extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
-use core::arch::global_asm;
+use core::arch::{asm, global_asm};
 
 #[cfg(feature = "board_qemu")]
 #[path = "boards/qemu.rs"]
@@ -38,6 +41,7 @@ mod console;
 mod config;
 mod drivers;
 mod fs;
+mod hart;
 mod lang_items;
 mod mm;
 mod sbi;
@@ -64,17 +68,43 @@ fn clear_bss() {
     }
 }
 
+/// Entry of the boot hart
 #[no_mangle]
-fn rust_main() -> ! {
+fn rust_main(hart_id: usize) -> ! {
     clear_bss();
+    if hart_id >= config::MAX_HARTS {
+        // The console lock indexes the per-hart state by `tp`, so the slot of hart 0 is borrowed
+        // to report it, since no other hart runs yet.
+        unsafe {
+            asm!("mv tp, zero");
+        }
+        panic!("boot hart {} is not supported", hart_id);
+    }
     println!("[kernel] Hello, world!");
     mm::init();
     mm::remap_test();
     trap::init();
+    board::device_init();
     trap::enable_timer_interrupt();
     timer::set_next_trigger();
     fs::list_apps();
     task::add_initproc();
+    hart::set_online();
+    hart::start_other_harts();
     task::run_tasks();
     panic!("Unreachable in rust_main!");
 }
+
+/// Entry of the other harts started by [`hart::start_other_harts`]
+#[no_mangle]
+fn rust_main_secondary(hart_id: usize) -> ! {
+    mm::init_secondary();
+    trap::init();
+    board::irq_init_hart();
+    trap::enable_timer_interrupt();
+    timer::set_next_trigger();
+    println!("[kernel] hart {} started", hart_id);
+    hart::set_online();
+    task::run_tasks();
+    panic!("Unreachable in rust_main_secondary!");
+}
@@ -3,7 +3,7 @@
 
 use super::{PhysAddr, PhysPageNum};
 use crate::config::MEMORY_END;
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
 use lazy_static::*;
@@ -136,8 +136,8 @@ type FrameAllocatorImpl = StackFrameAllocator;
 
 lazy_static! {
     /// frame allocator instance through lazy_static!
-    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
-        unsafe { UPSafeCell::new(FrameAllocatorImpl::new()) };
+    pub static ref FRAME_ALLOCATOR: SpinLock<FrameAllocatorImpl> =
+        SpinLock::new(FrameAllocatorImpl::new());
 }
 
 /// initiate the frame allocator using `ekernel` and `MEMORY_END`
@@ -147,7 +147,7 @@ pub fn init_frame_allocator() {
         /// - ekernel: end kernel memory segment
         fn ekernel();
     }
-    FRAME_ALLOCATOR.exclusive_access().init(
+    FRAME_ALLOCATOR.lock().init(
         // Round up the value to make `physical memory address` > a multiple of PAGE_SIZE(4096).
         PhysAddr::from(ekernel as usize).ceil(),
         // Truncate the value to make `physical memory address` <= a multiple of PAGE_SIZE(4096).
@@ -161,10 +161,7 @@ pub fn init_frame_allocator() {
 ///
 /// and wrap it in a `FrameTracker` to automatically call `frame_dealloc` when it is no longer used.
 pub fn frame_alloc() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .alloc()
-        .map(FrameTracker::new)
+    FRAME_ALLOCATOR.lock().alloc().map(FrameTracker::new)
 }
 
 /// deallocate a frame
@@ -172,7 +169,7 @@ pub fn frame_alloc() -> Option<FrameTracker> {
 /// There is no need to use this function manually
 /// since it is called automatically by `Drop` trait implemented in `FrameTracker`.
 pub fn frame_dealloc(ppn: PhysPageNum) {
-    FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
+    FRAME_ALLOCATOR.lock().dealloc(ppn);
 }
 
 #[allow(unused)]
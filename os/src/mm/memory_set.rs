@@ -4,7 +4,8 @@ use super::{PTEFlags, PageTable, PageTableEntry};
 use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::{StepByOne, VPNRange};
 use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE};
-use crate::sync::UPSafeCell;
+use crate::hart::tlb_shootdown;
+use crate::sync::SpinLock;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -40,15 +41,15 @@ lazy_static! {
     ///
     /// KERNEL_SPACE is not actually initialized until it is first used at runtime,
     /// and the space it occupies is placed in the global data segment at compile time.
-    pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> =
-        Arc::new(unsafe { UPSafeCell::new(MemorySet::new_kernel()) });
+    pub static ref KERNEL_SPACE: Arc<SpinLock<MemorySet>> =
+        Arc::new(SpinLock::new(MemorySet::new_kernel()));
 }
 
 /// Get the physical page number of the root node of that application.
 ///
 /// Physical page number(SV39: 44bit)
 pub fn kernel_token() -> usize {
-    KERNEL_SPACE.exclusive_access().token()
+    KERNEL_SPACE.lock().token()
 }
 
 /// Expressing the address space.
@@ -101,15 +102,21 @@ impl MemorySet {
     }
 
     ///Remove `MapArea` that starts with `start_vpn`
+    ///
+    /// The frames are freed after the TLB entries of the area are flushed on every hart.
     pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
-        if let Some((idx, area)) = self
+        if let Some(idx) = self
             .areas
-            .iter_mut()
-            .enumerate()
-            .find(|(_, area)| area.vpn_range.get_start() == start_vpn)
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start_vpn)
         {
+            let mut area = self.areas.remove(idx);
+            let frames = core::mem::take(&mut area.data_frames);
             area.unmap(&mut self.page_table);
-            self.areas.remove(idx);
+            let start_va: VirtAddr = area.vpn_range.get_start().into();
+            let end_va: VirtAddr = area.vpn_range.get_end().into();
+            tlb_shootdown(start_va.0, end_va.0 - start_va.0);
+            drop(frames);
         }
     }
 
@@ -216,22 +223,29 @@ impl MemorySet {
     ///
     /// Include sections in elf and trampoline and TrapContext and user stack,
     /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `elf_data` is not a valid ELF => `None`
+    /// - Otherwise => (memory_set, user_sp, entry_point)
+    pub fn from_elf(elf_data: &[u8]) -> Option<(Self, usize, usize)> {
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+        let elf = xmas_elf::ElfFile::new(elf_data).ok()?;
         let elf_header = elf.header;
         let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if magic != [0x7f, 0x45, 0x4c, 0x46] {
+            return None;
+        }
         // ph_count: the number of all header
         let ph_count = elf_header.pt2.ph_count();
         let mut max_end_vpn = VirtPageNum(0);
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
+            let ph = elf.program_header(i).ok()?;
             // Type::Load meaning that need to read program header by kernel.
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+            if ph.get_type().ok()? == xmas_elf::program::Type::Load {
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
                 // ph.mem_size: Memory size required for the application.
                 // `mem_size` is also calculated for bss size, but not `file_size`.
@@ -247,13 +261,15 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
+                // the segment must lie inside the file and fit in its memory size
+                let data = elf
+                    .input
+                    .get(ph.offset() as usize..(ph.offset().checked_add(ph.file_size())?) as usize)
+                    .filter(|data| data.len() as u64 <= ph.mem_size())?;
                 let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
                 max_end_vpn = map_area.vpn_range.get_end();
                 // push to address space.
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                memory_set.push(map_area, Some(data));
             }
         }
         // map user stack with U flags
@@ -261,11 +277,11 @@ impl MemorySet {
         let mut user_stack_base: usize = max_end_va.into();
         // plus guard page
         user_stack_base += PAGE_SIZE;
-        (
+        Some((
             memory_set,
             user_stack_base,
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
     }
 
     /// Clone a same `MemorySet`
@@ -516,7 +532,7 @@ bitflags! {
 
 #[allow(unused)]
 pub fn remap_test() {
-    let mut kernel_space = KERNEL_SPACE.exclusive_access();
+    let mut kernel_space = KERNEL_SPACE.lock();
     let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
     let mid_rodata: VirtAddr = ((srodata as usize + erodata as usize) / 2).into();
     let mid_data: VirtAddr = ((sdata as usize + edata as usize) / 2).into();
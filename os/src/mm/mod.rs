@@ -29,5 +29,10 @@ pub fn init() {
     // Initializes the physical page frame manager (including the heap data structure Vec<T>)
     // so that available physical page frames can be allocated and reclaimed.
     frame_allocator::init_frame_allocator();
-    KERNEL_SPACE.exclusive_access().activate();
+    KERNEL_SPACE.lock().activate();
+}
+
+/// Enable paging with the kernel space on a hart started after [`init`]
+pub fn init_secondary() {
+    KERNEL_SPACE.lock().activate();
 }
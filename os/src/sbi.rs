@@ -3,18 +3,22 @@
 use core::arch::asm;
 
 const SBI_SET_TIMER: usize = 0;
-const SBI_CONSOLE_PUTCHAR: usize = 1;
-const SBI_CONSOLE_GETCHAR: usize = 2;
 
 #[cfg(not(feature = "board_qemu"))]
 const SBI_SHUTDOWN: usize = 8;
-// const SBI_CONSOLE_GETCHAR: usize = 2;
 // const SBI_CLEAR_IPI: usize = 3;
 // const SBI_SEND_IPI: usize = 4;
 // const SBI_REMOTE_FENCE_I: usize = 5;
 // const SBI_REMOTE_SFENCE_VMA: usize = 6;
 // const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 
+/// Extension ID of the IPI extension("sPI")
+const SBI_EXT_IPI: usize = 0x0073_5049;
+/// Extension ID of the RFENCE extension("RFNC")
+const SBI_EXT_RFENCE: usize = 0x5246_4e43;
+/// Extension ID of the Hart State Management extension("HSM")
+const SBI_EXT_HSM: usize = 0x0048_534d;
+
 ///  handle SBI call with `which` SBI_id and other arguments
 #[inline(always)]
 fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
@@ -31,19 +35,59 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// Handle SBI call of the function `fid` in the extension `eid`(SBI v0.2 calling convention).
+///
+/// # Return
+/// SBI error code(0 on success)
+#[inline(always)]
+fn sbi_call_ext(
+    eid: usize,
+    fid: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+) -> isize {
+    let mut error;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") arg0 => error,
+            inlateout("x11") arg1 => _,
+            in("x12") arg2,
+            in("x13") arg3,
+            in("x16") fid,
+            in("x17") eid,
+        );
+    }
+    error
+}
+
 /// use sbi call to set timer
 pub fn set_timer(timer: usize) {
     sbi_call(SBI_SET_TIMER, timer, 0, 0);
 }
 
-/// Screen output to terminal
-pub fn console_putchar(c: usize) {
-    sbi_call(SBI_CONSOLE_PUTCHAR, c, 0, 0);
+/// Start the hart `hartid` in supervisor mode at the physical address `start_addr`.
+///
+/// The hart starts with paging disabled, `a0` = `hartid` and `a1` = `opaque`.
+///
+/// # Return
+/// SBI error code(e.g. the hart does not exist or is already started)
+pub fn hart_start(hartid: usize, start_addr: usize, opaque: usize) -> isize {
+    sbi_call_ext(SBI_EXT_HSM, 0, hartid, start_addr, opaque, 0)
+}
+
+/// Send a supervisor software interrupt to the harts in `hart_mask`(bit i = hart i).
+pub fn send_ipi(hart_mask: usize) {
+    sbi_call_ext(SBI_EXT_IPI, 0, hart_mask, 0, 0, 0);
 }
 
-/// use sbi call to getchar from console (qemu uart handler)
-pub fn console_getchar() -> usize {
-    sbi_call(SBI_CONSOLE_GETCHAR, 0, 0, 0)
+/// Execute `sfence.vma` for `[start, start + size)` on the harts in `hart_mask`(bit i = hart i).
+///
+/// The SBI sends IPIs to them and returns after all of them have flushed their TLB.
+pub fn remote_sfence_vma(hart_mask: usize, start: usize, size: usize) {
+    sbi_call_ext(SBI_EXT_RFENCE, 1, hart_mask, 0, start, size);
 }
 
 #[cfg(feature = "board_qemu")]
@@ -1,4 +1,4 @@
-use crate::sync::{Mutex, UPSafeCell};
+use crate::sync::{Mutex, SpinLock};
 use crate::task::{add_task, block_current_and_run_next, current_task, TaskControlBlock};
 use alloc::{collections::VecDeque, sync::Arc};
 
@@ -9,7 +9,7 @@ use alloc::{collections::VecDeque, sync::Arc};
 /// A system that performs exclusion control by having a common resource (e.g., static variable) call
 /// wait on one thread during certain conditions and having the other thread satisfy the conditions and call signal.
 pub struct Condvar {
-    pub inner: UPSafeCell<CondvarInner>,
+    pub inner: SpinLock<CondvarInner>,
 }
 
 /// inner for mutable exclusive control
@@ -30,11 +30,9 @@ impl Condvar {
     /// ```
     pub fn new() -> Self {
         Self {
-            inner: unsafe {
-                UPSafeCell::new(CondvarInner {
-                    wait_queue: VecDeque::new(),
-                })
-            },
+            inner: SpinLock::new(CondvarInner {
+                wait_queue: VecDeque::new(),
+            }),
         }
     }
 
@@ -43,7 +41,7 @@ impl Condvar {
     /// By resuming the thread with this method, the **`lock`** method of `Mutex` given the
     /// `Condvar.wait` method is finally called.
     pub fn signal(&self) {
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         if let Some(task) = inner.wait_queue.pop_front() {
             add_task(task);
         }
@@ -60,7 +58,7 @@ impl Condvar {
     ///    finally call the **`lock`** method of `Mutex` given as the `mutex` argument.
     pub fn wait(&self, mutex: Arc<dyn Mutex>) {
         mutex.unlock();
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         inner.wait_queue.push_back(current_task().unwrap());
         drop(inner);
         block_current_and_run_next();
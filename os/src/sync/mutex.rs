@@ -1,4 +1,4 @@
-use super::UPSafeCell;
+use super::SpinLock;
 use crate::task::TaskControlBlock;
 use crate::task::{add_task, current_task};
 use crate::task::{block_current_and_run_next, suspend_current_and_run_next};
@@ -78,7 +78,7 @@ pub struct MutexSpin {
     /// Exclusive variable lock flag
     ///
     /// Currently locked?
-    locked: UPSafeCell<bool>,
+    locked: SpinLock<bool>,
 }
 
 impl MutexSpin {
@@ -90,7 +90,7 @@ impl MutexSpin {
     /// ```
     pub fn new() -> Self {
         Self {
-            locked: unsafe { UPSafeCell::new(false) },
+            locked: SpinLock::new(false),
         }
     }
 }
@@ -98,7 +98,7 @@ impl MutexSpin {
 impl Mutex for MutexSpin {
     fn lock(&self) {
         loop {
-            let mut locked = self.locked.exclusive_access();
+            let mut locked = self.locked.lock();
             if *locked {
                 drop(locked);
                 suspend_current_and_run_next();
@@ -111,7 +111,7 @@ impl Mutex for MutexSpin {
     }
 
     fn unlock(&self) {
-        let mut locked = self.locked.exclusive_access();
+        let mut locked = self.locked.lock();
         *locked = false;
     }
 }
@@ -131,7 +131,7 @@ impl Mutex for MutexSpin {
 /// | thread4 | state `Blocking`  and push_back to wait queue |                                                                             |
 pub struct MutexBlocking {
     /// Structure with variable fields storing locked, wait_queue(for thread)
-    inner: UPSafeCell<MutexBlockingInner>,
+    inner: SpinLock<MutexBlockingInner>,
 }
 
 /// inner for mutable exclusive control
@@ -153,19 +153,17 @@ impl MutexBlocking {
     /// ```
     pub fn new() -> Self {
         Self {
-            inner: unsafe {
-                UPSafeCell::new(MutexBlockingInner {
-                    locked: false,
-                    wait_queue: VecDeque::new(),
-                })
-            },
+            inner: SpinLock::new(MutexBlockingInner {
+                locked: false,
+                wait_queue: VecDeque::new(),
+            }),
         }
     }
 }
 
 impl Mutex for MutexBlocking {
     fn lock(&self) {
-        let mut mutex_inner = self.inner.exclusive_access();
+        let mut mutex_inner = self.inner.lock();
         if mutex_inner.locked {
             mutex_inner.wait_queue.push_back(current_task().unwrap());
             drop(mutex_inner);
@@ -176,7 +174,7 @@ impl Mutex for MutexBlocking {
     }
 
     fn unlock(&self) {
-        let mut mutex_inner = self.inner.exclusive_access();
+        let mut mutex_inner = self.inner.lock();
         assert!(mutex_inner.locked);
         if let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
             add_task(waking_task);
@@ -1,4 +1,4 @@
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use crate::task::{add_task, block_current_and_run_next, current_task, TaskControlBlock};
 use alloc::{collections::VecDeque, sync::Arc};
 
@@ -12,7 +12,7 @@ use alloc::{collections::VecDeque, sync::Arc};
 /// let semaphore = Semaphore::new(2);
 /// ```
 pub struct Semaphore {
-    pub inner: UPSafeCell<SemaphoreInner>,
+    pub inner: SpinLock<SemaphoreInner>,
 }
 
 /// inner for mutable exclusive control
@@ -57,12 +57,10 @@ impl Semaphore {
     /// ```
     pub fn new(res_count: usize) -> Self {
         Self {
-            inner: unsafe {
-                UPSafeCell::new(SemaphoreInner {
-                    count: res_count as isize,
-                    wait_queue: VecDeque::new(),
-                })
-            },
+            inner: SpinLock::new(SemaphoreInner {
+                count: res_count as isize,
+                wait_queue: VecDeque::new(),
+            }),
         }
     }
 
@@ -72,7 +70,7 @@ impl Semaphore {
     /// If `self.count` is less than or equal to 0, a waiting thread is popped
     /// from the top of the queue and added to the task queue (for the task to be executed).
     pub fn up(&self) {
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         inner.count += 1;
         if inner.count <= 0 {
             if let Some(task) = inner.wait_queue.pop_front() {
@@ -87,7 +85,7 @@ impl Semaphore {
     /// If `self.count` is less than 0, the currently running thread is added to the
     /// end of `self.wait_queue` and continues waiting for the lock to be released in the `Blocking` state.
     pub fn down(&self) {
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         inner.count -= 1;
         if inner.count < 0 {
             inner.wait_queue.push_back(current_task().unwrap());
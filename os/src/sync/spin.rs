@@ -0,0 +1,78 @@
+//! Spin lock shared between harts
+
+use crate::hart::{pop_off, push_off};
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Mutual exclusion by busy waiting.
+///
+/// Unlike [`super::UPSafeCell`], it can be shared between harts.
+/// The holder must not block or switch tasks while holding the lock, since the other harts
+/// spin until it is released.
+///
+/// Interrupts of the holding hart are disabled while it is held, so that an interrupt handler
+/// never spins on a lock held by the code it interrupted.
+///
+/// In order to get mutable reference of inner data, call `lock`.
+pub struct SpinLock<T> {
+    /// Is the lock held by someone?
+    locked: AtomicBool,
+    /// inner data
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+unsafe impl<T: Send> Send for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// Create an unlocked `SpinLock`
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Spin until the lock is acquired and return a guard that releases it when dropped.
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        push_off();
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // wait with plain loads to keep the cache line shared while someone holds it
+            while self.locked.load(Ordering::Relaxed) {
+                spin_loop();
+            }
+        }
+        SpinLockGuard { lock: self }
+    }
+}
+
+/// Exclusive access to the data of a [`SpinLock`]
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+        pop_off();
+    }
+}
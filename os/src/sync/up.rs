@@ -1,11 +1,17 @@
-//! Uniprocessor interior mutability primitives
+//! Uniprocessor(or per-hart) interior mutability primitives
 
+use crate::hart::{pop_off, push_off};
 use core::cell::{RefCell, RefMut};
+use core::ops::{Deref, DerefMut};
 
 /// Wrap a static data structure inside it so that we are
 /// able to access it without any `unsafe`.
 ///
-/// We should only use it in uniprocessor.
+/// We should only use it in uniprocessor, or for per-hart data that is touched only by
+/// its own hart(e.g. the `Processor` of each hart). Use [`super::SpinLock`] for data shared between harts.
+///
+/// Interrupts are disabled while the data is borrowed, so that an interrupt handler
+/// never finds it borrowed by the code it interrupted.
 ///
 /// In order to get mutable reference of inner data, call
 /// `exclusive_access`.
@@ -28,7 +34,32 @@ impl<T> UPSafeCell<T> {
     /// Mutably borrows the wrapped value.
     /// # Panics
     /// Panics if the value is currently borrowed.
-    pub fn exclusive_access(&self) -> RefMut<'_, T> {
-        self.inner.borrow_mut()
+    pub fn exclusive_access(&self) -> UPRefMut<'_, T> {
+        push_off();
+        UPRefMut(Some(self.inner.borrow_mut()))
+    }
+}
+
+/// Mutable borrow of the data of a [`UPSafeCell`]
+pub struct UPRefMut<'a, T>(Option<RefMut<'a, T>>);
+
+impl<T> Deref for UPRefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0.as_ref().unwrap().deref()
+    }
+}
+
+impl<T> DerefMut for UPRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0.as_mut().unwrap().deref_mut()
+    }
+}
+
+impl<T> Drop for UPRefMut<'_, T> {
+    fn drop(&mut self) {
+        // release the borrow before interrupts are enabled again
+        self.0 = None;
+        pop_off();
     }
 }
@@ -1,8 +1,49 @@
 //! File and filesystem-related syscalls
-use crate::fs::{make_pipe, open_file, OpenFlags};
+use crate::fs::{
+    link_at, make_pipe, mkdir_at, open_file_at, read_link_at, rename_at, stat_at, symlink_at,
+    truncate_at, unlink_at, OpenFlags, Stat, ROOT_INODE,
+};
 use crate::mm::{translated_byte_buffer, translated_refmut, translated_str, UserBuffer};
 use crate::task::{current_process, current_user_token};
 use alloc::sync::Arc;
+use easy_fs::Inode;
+
+/// `dirfd` meaning the current working directory.
+///
+/// There is no working directory for each process yet, so it is always the root directory.
+pub const AT_FDCWD: isize = -100;
+/// Flag of `unlinkat` to remove a directory
+pub const AT_REMOVEDIR: u32 = 0x200;
+/// Flag of `fstatat` to report a symbolic link itself instead of its target
+pub const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+/// Flag of `linkat` to link the target of a symbolic link
+pub const AT_SYMLINK_FOLLOW: u32 = 0x400;
+
+/// Get the directory that a relative path given with `dirfd` starts from.
+///
+/// # Return
+/// Conditional branching.
+/// - If `dirfd` is `AT_FDCWD` => The root directory
+/// - If `dirfd` is not an open directory => `None`
+/// - Otherwise => The directory of `dirfd`
+fn dir_inode_of(dirfd: isize) -> Option<Arc<Inode>> {
+    if dirfd == AT_FDCWD {
+        return Some(ROOT_INODE.clone());
+    }
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let inode = inner
+        .fd_table
+        .get(usize::try_from(dirfd).ok()?)?
+        .as_ref()?
+        .inode()?;
+    drop(inner);
+    if inode.is_dir() {
+        Some(inode)
+    } else {
+        None
+    }
+}
 
 /// Duplicates the file descriptor reference passed in the argument.
 ///
@@ -39,9 +80,42 @@ pub fn sys_dup(fd: usize) -> isize {
 /// Conditional branching.
 /// - If an error occurs
 ///   (e.g. If you put a file descriptor number in `fd` that does not exist in the file descriptor table) => -1
+/// - If `fd` is not opened for writing, or it is a directory => -1
+/// - If the file would exceed the max file size => -`EFBIG`
+/// - If the disk is full before anything is written => -`ENOSPC`
 /// - otherwise => The length of the successful write.
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    match &inner.fd_table[fd] {
+        Some(file) if file.writable() => {
+            let file = file.clone();
+            // release current task TCB(TaskControlBlock) manually to avoid multi-borrow
+            drop(inner);
+            // Convert the buffer pointed to by the application's virtual address
+            // into a vector of byte array slices pointed to by the kernel's virtual address.
+            file.write(UserBuffer::new(translated_byte_buffer(token, buf, len)))
+        }
+        _ => -1,
+    }
+}
+
+/// Control a device through a file descriptor(e.g. the terminal attributes of the console).
+///
+/// # Parameters
+/// - `fd`: File descriptor of the device.
+/// - `cmd`: Device dependent request code(e.g. `TCGETS`/`TCSETS` for the console).
+/// - `arg`: Device dependent argument, usually the address of a structure.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened, or the file does not accept `cmd` => -1
+/// - Otherwise => Depends on `cmd`(0 for the console)
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
     let process = current_process();
     let inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
@@ -49,11 +123,8 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     }
     if let Some(file) = &inner.fd_table[fd] {
         let file = file.clone();
-        // release current task TCB(TaskControlBlock) manually to avoid multi-borrow
         drop(inner);
-        // Convert the buffer pointed to by the application's virtual address
-        // into a vector of byte array slices pointed to by the kernel's virtual address.
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.ioctl(cmd, arg)
     } else {
         -1
     }
@@ -70,9 +141,40 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
 /// Conditional branching.
 /// - If an error occurs
 ///   (e.g. If you put a file descriptor number in `fd` that does not exist in the file descriptor table) => -1
+/// - If `fd` is not opened for reading => -1
+/// - If a background process reads the console with `SIGTTIN` blocked => -`EIO`
 /// - otherwise => number of bytes actually read.
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    match &inner.fd_table[fd] {
+        Some(file) if file.readable() => {
+            let file = file.clone();
+            // release current task TCB(TaskControlBlock) manually to avoid multi-borrow
+            drop(inner);
+            file.read(UserBuffer::new(translated_byte_buffer(token, buf, len)))
+        }
+        _ => -1,
+    }
+}
+
+/// Move the offset of a file used by `sys_read`/`sys_write`.
+///
+/// # Parameters
+/// - `fd`: File descriptor of the file.
+/// - `offset`: Offset in bytes relative to `whence`.
+/// - `whence`: `SEEK_SET`(the start), `SEEK_CUR`(the current offset) or `SEEK_END`(the end)
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened, `whence` is unknown or the new offset is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => The new offset from the start of the file
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
     let process = current_process();
     let inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
@@ -80,19 +182,116 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     }
     if let Some(file) = &inner.fd_table[fd] {
         let file = file.clone();
-        // release current task TCB(TaskControlBlock) manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.seek(offset, whence)
     } else {
         -1
     }
 }
 
-/// Opens a regular file and returns an accessible file descriptor.
+/// Reads a piece of content from a position of a file without moving its offset.
 ///
 /// # Parameters
-/// - `path`: Describe the filename of the file to be opened (for simplicity,
-/// the file system does not need to support directories, all files are placed in the root(`/`) directory).
+/// - `fd`: File descriptor of the file to read.
+/// - `buf`: The start address of the in-memory buffer.
+/// - `len`: Length to read.
+/// - `offset`: Position in the file to read from.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened for reading or `offset` is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => number of bytes actually read.
+pub fn sys_pread64(fd: usize, buf: *const u8, len: usize, offset: isize) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || offset < 0 {
+        return -1;
+    }
+    match &inner.fd_table[fd] {
+        Some(file) if file.readable() => {
+            let file = file.clone();
+            drop(inner);
+            file.pread(
+                UserBuffer::new(translated_byte_buffer(token, buf, len)),
+                offset as usize,
+            )
+        }
+        _ => -1,
+    }
+}
+
+/// Writes the data in the buffer to a position of a file without moving its offset.
+///
+/// # Parameters
+/// - `fd`: File descriptor of the file to be written.
+/// - `buf`: The start address of the in-memory buffer.
+/// - `len`: Length to write.
+/// - `offset`: Position in the file to write from. It may be beyond the end of the file.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened for writing, it is a directory or `offset` is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - If the file would exceed the max file size => -`EFBIG`
+/// - If the disk is full before anything is written => -`ENOSPC`
+/// - Otherwise => The length of the successful write.
+pub fn sys_pwrite64(fd: usize, buf: *const u8, len: usize, offset: isize) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || offset < 0 {
+        return -1;
+    }
+    match &inner.fd_table[fd] {
+        Some(file) if file.writable() => {
+            let file = file.clone();
+            drop(inner);
+            file.pwrite(
+                UserBuffer::new(translated_byte_buffer(token, buf, len)),
+                offset as usize,
+            )
+        }
+        _ => -1,
+    }
+}
+
+/// Read the entries of a directory as `struct linux_dirent64` records.
+///
+/// Each call continues from the entry after the last one read(`sys_lseek` to 0 rewinds it).
+///
+/// # Parameters
+/// - `fd`: File descriptor of a directory opened by `sys_openat`.
+/// - `buf`: The start address of the in-memory buffer.
+/// - `len`: Length of the buffer.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not an open directory, or `len` is too small for the next record => -1
+/// - If all entries have been read => 0
+/// - Otherwise => Number of bytes of the records
+pub fn sys_getdents64(fd: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        drop(inner);
+        file.getdents(UserBuffer::new(translated_byte_buffer(token, buf, len)))
+    } else {
+        -1
+    }
+}
+
+/// Opens a file or a directory and returns an accessible file descriptor.
+///
+/// # Parameters
+/// - `dirfd`: Directory that a relative `path` starts from(`AT_FDCWD` means the root directory).
+/// - `path`: Path of the file to be opened. An absolute path ignores `dirfd`.
 /// - `flags`: Describe the flags to be used when opening the file.
 ///
 /// # Flags
@@ -112,26 +311,323 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
 /// | 10(0x400) |        trunc | it should be cleared and the size set back to zero,                       |
 /// |           |              | i.e. `TRUNC`, when opening the file.                                      |
 /// |-----------|--------------|---------------------------------------------------------------------------|
+/// |16(0x10000)|    directory | it fails if the path is not a directory(`DIRECTORY`).                     |
+/// |-----------|--------------|---------------------------------------------------------------------------|
+/// |17(0x20000)|   no follow  | it fails if the path is a symbolic link(`NOFOLLOW`).                      |
+/// |-----------|--------------|---------------------------------------------------------------------------|
 ///
 /// # Return
 /// Conditional branching.
-/// - if there is an error => -1
+/// - if the file is created with `CREATE` and the disk is full => -`ENOSPC`
+/// - if there is another error => -1
 /// - otherwise=> returns the file descriptor of the file normally.
-///               Possible error cause: the file does not exist.
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
+///               Possible error cause: the file does not exist, `dirfd` is not a directory,
+///               a directory is opened for writing or `flags` is unknown.
+pub fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    let process = current_process();
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let (dir, flags) = match (dir_inode_of(dirfd), OpenFlags::from_bits(flags)) {
+        (Some(dir), Some(flags)) => (dir, flags),
+        _ => return -1,
+    };
+    match open_file_at(&dir, path.as_str(), flags) {
+        Ok(inode) => {
+            let mut inner = process.inner_exclusive_access();
+            let fd = inner.alloc_fd();
+            inner.fd_table[fd] = Some(inode);
+            fd as isize
+        }
+        Err(errno) => errno,
+    }
+}
+
+/// Create a directory.
+///
+/// # Parameters
+/// - `dirfd`: Directory that a relative `path` starts from(`AT_FDCWD` means the root directory).
+/// - `path`: Path of the directory to be created. An absolute path ignores `dirfd`.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `dirfd` is not a directory, `path` already exists or its parent directory is not found => -1
+/// - If the disk is full => -`ENOSPC`
+pub fn sys_mkdirat(dirfd: isize, path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    match dir_inode_of(dirfd) {
+        Some(dir) => mkdir_at(&dir, path.as_str()),
+        None => -1,
+    }
+}
+
+/// Remove a file or an empty directory.
+///
+/// The data blocks and the inode are deallocated when no other name refers to it
+/// and no process has it open.
+///
+/// # Parameters
+/// - `dirfd`: Directory that a relative `path` starts from(`AT_FDCWD` means the root directory).
+/// - `path`: Path to be removed. An absolute path ignores `dirfd`.
+/// - `flags`: `AT_REMOVEDIR` to remove a directory, otherwise 0
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `dirfd` is not a directory or `flags` is unknown => -1
+/// - If `path` is not found, is a directory without `AT_REMOVEDIR` or is not one with it => -1
+/// - If the directory is not empty => -1
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    if flags & !AT_REMOVEDIR != 0 {
+        return -1;
+    }
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    match dir_inode_of(dirfd) {
+        Some(dir) if unlink_at(&dir, path.as_str(), flags == AT_REMOVEDIR) => 0,
+        _ => -1,
+    }
+}
+
+/// Add a new name(hard link) for an existing file.
+///
+/// # Parameters
+/// - `olddirfd`/`oldpath`: The existing file, resolved as `sys_openat` does.
+/// - `newdirfd`/`newpath`: The new name, resolved as `sys_openat` does.
+/// - `flags`: `AT_SYMLINK_FOLLOW` to link the target of `oldpath` if it is a symbolic link,
+///   otherwise 0 to link the symbolic link itself
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `olddirfd`/`newdirfd` is not a directory or `flags` is unknown => -1
+/// - If `oldpath` is not found or a directory => -1
+/// - If `newpath` already exists, or its parent directory is not found => -1
+pub fn sys_linkat(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+    flags: u32,
+) -> isize {
+    if flags & !AT_SYMLINK_FOLLOW != 0 {
+        return -1;
+    }
+    let token = current_user_token();
+    let oldpath = translated_str(token, oldpath);
+    let newpath = translated_str(token, newpath);
+    let follow = flags == AT_SYMLINK_FOLLOW;
+    match (dir_inode_of(olddirfd), dir_inode_of(newdirfd)) {
+        (Some(old_dir), Some(new_dir))
+            if link_at(
+                &old_dir,
+                oldpath.as_str(),
+                &new_dir,
+                newpath.as_str(),
+                follow,
+            ) =>
+        {
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Create a symbolic link that refers to `target`.
+///
+/// # Parameters
+/// - `target`: Path stored in the link. It is not looked up, so it may not exist.
+/// - `newdirfd`/`linkpath`: Path of the new link, resolved as `sys_openat` does.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `newdirfd` is not a directory, or `target` is empty => -1
+/// - If `linkpath` already exists, or its parent directory is not found => -1
+/// - If the disk is full => -`ENOSPC`
+pub fn sys_symlinkat(target: *const u8, newdirfd: isize, linkpath: *const u8) -> isize {
+    let token = current_user_token();
+    let target = translated_str(token, target);
+    let linkpath = translated_str(token, linkpath);
+    match dir_inode_of(newdirfd) {
+        Some(dir) => symlink_at(target.as_str(), &dir, linkpath.as_str()),
+        None => -1,
+    }
+}
+
+/// Read the target of a symbolic link.
+///
+/// # Parameters
+/// - `dirfd`/`path`: The symbolic link, resolved as `sys_openat` does.
+/// - `buf`: Buffer that receives the target. No null character is appended.
+/// - `bufsiz`: Size of `buf`. A longer target is truncated.
+///
+/// # Return
+/// Conditional branching.
+/// - If `dirfd` is not a directory => -1
+/// - If `path` is not found or not a symbolic link => -1
+/// - Otherwise => Number of bytes placed in `buf`
+pub fn sys_readlinkat(dirfd: isize, path: *const u8, buf: *mut u8, bufsiz: usize) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let target = match dir_inode_of(dirfd).and_then(|dir| read_link_at(&dir, path.as_str())) {
+        Some(target) => target,
+        None => return -1,
+    };
+    let len = target.len().min(bufsiz);
+    let mut copied = 0;
+    for dst in translated_byte_buffer(token, buf, len) {
+        dst.copy_from_slice(&target.as_bytes()[copied..copied + dst.len()]);
+        copied += dst.len();
+    }
+    len as isize
+}
+
+/// Get the status(type, size, timestamps, etc.) of a file.
+///
+/// # Parameters
+/// - `dirfd`/`path`: The file, resolved as `sys_openat` does.
+/// - `st`: The address of `Stat` to store the status.
+/// - `flags`: `AT_SYMLINK_NOFOLLOW` to report a symbolic link itself instead of its target,
+///   otherwise 0
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `dirfd` is not a directory or `flags` is unknown => -1
+/// - If `path` is not found => -1
+pub fn sys_fstatat(dirfd: isize, path: *const u8, st: *mut Stat, flags: u32) -> isize {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return -1;
+    }
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let follow = flags != AT_SYMLINK_NOFOLLOW;
+    match dir_inode_of(dirfd).and_then(|dir| stat_at(&dir, path.as_str(), follow)) {
+        Some(stat) => {
+            *translated_refmut(token, st) = stat;
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Get the status(type, size, timestamps, etc.) of an open file.
+///
+/// # Parameters
+/// - `fd`: File descriptor of the file.
+/// - `st`: The address of `Stat` to store the status.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened => -1
+/// - Otherwise => 0
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
+    let token = current_user_token();
     let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        drop(inner);
+        *translated_refmut(token, st) = file.stat();
+        0
+    } else {
+        -1
+    }
+}
+
+/// Change the size of a file, freeing the data beyond it or extending it with zeros.
+///
+/// # Parameters
+/// - `path`: Path of the file, resolved as `sys_openat` with `AT_FDCWD` does.
+/// - `length`: The new size in bytes.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `path` is not found or not a regular file => -1
+/// - If `length` is negative or exceeds the max file size => -1
+pub fn sys_truncate(path: *const u8, length: isize) -> isize {
     let token = current_user_token();
     let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = process.inner_exclusive_access();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
+    if length >= 0 && truncate_at(&ROOT_INODE, path.as_str(), length as usize) {
+        0
     } else {
         -1
     }
 }
 
+/// Change the size of an open file, freeing the data beyond it or extending it with zeros.
+///
+/// The offset of the file is not changed.
+///
+/// # Parameters
+/// - `fd`: File descriptor of a regular file opened for writing.
+/// - `length`: The new size in bytes.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `fd` is not a regular file opened for writing => -1
+/// - If `length` is negative or exceeds the max file size => -1
+pub fn sys_ftruncate(fd: usize, length: isize) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || length < 0 {
+        return -1;
+    }
+    match &inner.fd_table[fd] {
+        Some(file) => {
+            let file = file.clone();
+            drop(inner);
+            if file.truncate(length as usize) {
+                0
+            } else {
+                -1
+            }
+        }
+        None => -1,
+    }
+}
+
+/// Move a file or a directory to another name atomically.
+///
+/// An existing `newpath` is replaced if it is the same type(a directory must be empty).
+///
+/// # Parameters
+/// - `olddirfd`/`oldpath`: The file to be moved, resolved as `sys_openat` does.
+/// - `newdirfd`/`newpath`: The new name, resolved as `sys_openat` does.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `olddirfd`/`newdirfd` is not a directory => -1
+/// - If `oldpath` is not found, or the parent directory of `newpath` is not found => -1
+/// - If a directory is moved under itself => -1
+/// - If `newpath` exists and is a different type or a directory that is not empty => -1
+pub fn sys_renameat(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+) -> isize {
+    let token = current_user_token();
+    let oldpath = translated_str(token, oldpath);
+    let newpath = translated_str(token, newpath);
+    match (dir_inode_of(olddirfd), dir_inode_of(newdirfd)) {
+        (Some(old_dir), Some(new_dir))
+            if rename_at(&old_dir, oldpath.as_str(), &new_dir, newpath.as_str()) =>
+        {
+            0
+        }
+        _ => -1,
+    }
+}
+
 /// The current process closes the file.
 ///
 /// # Parameter
@@ -155,7 +651,10 @@ pub fn sys_close(fd: usize) -> isize {
     // to None to indicate that it is free, which also destroys the internal reference counter type Arc,
     // which reduces the reference count of the file, and automatically regenerates the resource occupied
     // by the file when the reference count reaches zero.
-    inner.fd_table[fd].take();
+    let file = inner.fd_table[fd].take();
+    // Freeing an unlinked inode writes the disk, so the file is dropped after the PCB is released.
+    drop(inner);
+    drop(file);
     0
 }
 
@@ -1,11 +1,17 @@
 //! Process management syscalls
+use crate::drivers::RTC_DEVICE;
 use crate::fs::{open_file, OpenFlags};
 use crate::mm::{translated_ref, translated_refmut, translated_str};
 use crate::task::{
-    current_process, current_task, current_user_token, exit_current_and_run_next, pid2process,
-    suspend_current_and_run_next, SignalFlags,
+    block_current_and_run_next, current_handling_sig, current_process, current_task,
+    current_user_token, exit_current_and_run_next, filter_processes, group_exists_in_session,
+    pid2process, send_signal, send_signal_to_group, suspend_current_and_run_next, SignalAction,
+    SignalFlags, MAX_SIG, MIN_PRIORITY,
+};
+use crate::timer::{
+    add_timer, get_time_ms, get_time_ns, get_time_us, ITimer, ITimerVal, TimeSpec, TimeVal,
+    CLOCK_MONOTONIC, CLOCK_REALTIME, ITIMER_PROF, ITIMER_REAL,
 };
-use crate::timer::get_time_ms;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -34,11 +40,263 @@ pub fn sys_get_time() -> isize {
     get_time_ms() as isize
 }
 
+/// Get the time of the clock `clock_id`.
+///
+/// # Parameters
+/// - `clock_id`: `CLOCK_REALTIME(0)` or `CLOCK_MONOTONIC(1)`
+/// - `ts`: Address where the time is stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `clock_id` is unknown or `ts` is 0 => -1
+pub fn sys_clock_gettime(clock_id: usize, ts: *mut TimeSpec) -> isize {
+    if ts.is_null() {
+        return -1;
+    }
+    let time_ns = match clock_id {
+        CLOCK_REALTIME => RTC_DEVICE.get_time_ns() as usize,
+        CLOCK_MONOTONIC => get_time_ns(),
+        _ => return -1,
+    };
+    *translated_refmut(current_user_token(), ts) = TimeSpec::from_ns(time_ns);
+    0
+}
+
+/// Block the current thread for `ms` milliseconds.
+///
+/// The thread is parked in the timer queue and woken up by the timer interrupt
+/// after the deadline, so no CPU time is used while sleeping.
+/// A signal sent to the process wakes the thread up early(see `send_signal`).
+///
+/// # Parameter
+/// - `ms`: Milliseconds to sleep
+///
+/// # Return
+/// Conditional branching.
+/// - If the deadline has passed => 0
+/// - If a signal woke the thread up before the deadline => -1
+pub fn sys_sleep(ms: usize) -> isize {
+    let expire_ms = get_time_ms() + ms;
+    add_timer(expire_ms, current_task().unwrap());
+    block_current_and_run_next();
+    if get_time_ms() < expire_ms {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Current value of the interval timer `which` of the current process.
+fn current_itimer_val(which: usize) -> ITimerVal {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let itimer = inner.itimers[which];
+    let remaining_us = if which == ITIMER_REAL && itimer.value_us != 0 {
+        // at least 1us while armed, since 0 means disarmed
+        itimer.value_us.saturating_sub(get_time_us()).max(1)
+    } else {
+        itimer.value_us
+    };
+    ITimerVal {
+        it_interval: TimeVal::from_us(itimer.interval_us),
+        it_value: TimeVal::from_us(remaining_us),
+    }
+}
+
+/// Get the value of an interval timer of the current process.
+///
+/// # Parameters
+/// - `which`: `ITIMER_REAL(0)`, `ITIMER_VIRTUAL(1)` or `ITIMER_PROF(2)`
+/// - `curr_value`: Address where the current value is stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `which` is unknown or `curr_value` is 0 => -1
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> isize {
+    if which > ITIMER_PROF || curr_value.is_null() {
+        return -1;
+    }
+    *translated_refmut(current_user_token(), curr_value) = current_itimer_val(which);
+    0
+}
+
+/// Arm or disarm an interval timer of the current process.
+///
+/// When the timer expires, `SIGALRM`(`ITIMER_REAL`), `SIGVTALRM`(`ITIMER_VIRTUAL`) or
+/// `SIGPROF`(`ITIMER_PROF`) is sent to the current process.
+///
+/// # Parameters
+/// - `which`: `ITIMER_REAL(0)`, `ITIMER_VIRTUAL(1)` or `ITIMER_PROF(2)`
+/// - `new_value`: New value. Zero `it_value` disarms the timer.
+/// - `old_value`: Address where the old value is stored. If this address is 0, it is not stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `which` is unknown or `new_value` is 0 => -1
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> isize {
+    if which > ITIMER_PROF || new_value.is_null() {
+        return -1;
+    }
+    let token = current_user_token();
+    if !old_value.is_null() {
+        *translated_refmut(token, old_value) = current_itimer_val(which);
+    }
+    let new_value = *translated_ref(token, new_value);
+    let mut value_us = new_value.it_value.as_us();
+    if which == ITIMER_REAL && value_us != 0 {
+        value_us += get_time_us();
+    }
+    let process = current_process();
+    process.inner_exclusive_access().itimers[which] = ITimer {
+        interval_us: new_value.it_interval.as_us(),
+        value_us,
+    };
+    0
+}
+
+/// Set the scheduling priority of the current thread.
+///
+/// With the stride scheduler, CPU time is given in proportion to the priority.
+/// With the static priority scheduler, a thread of higher priority always runs first.
+///
+/// # Parameter
+/// - `prio`: New priority(>= 2)
+///
+/// # Return
+/// Conditional branching.
+/// - Success => `prio`
+/// - If `prio` is less than 2 => -1
+pub fn sys_set_priority(prio: isize) -> isize {
+    if prio < MIN_PRIORITY as isize {
+        return -1;
+    }
+    current_task().unwrap().inner_exclusive_access().priority = prio as usize;
+    prio
+}
+
 /// Get process identifier from current thread.
 pub fn sys_getpid() -> isize {
     current_task().unwrap().process.upgrade().unwrap().getpid() as isize
 }
 
+/// Set the process group of a process.
+///
+/// # Parameters
+/// - `pid`: The current process or one of its children(0 means the current process)
+/// - `pgid`: Process group to join(0 means `pid` itself, i.e. create a new group led by it)
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `pid` is neither the current process nor its living child => -1
+/// - If the process is a session leader or in another session => -1
+/// - If `pgid` is not `pid` and there is no such group in the session => -1
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let sid = process_inner.sid;
+    let target = if pid == 0 || pid == process.getpid() {
+        Arc::clone(&process)
+    } else {
+        match process_inner.children.iter().find(|p| p.getpid() == pid) {
+            Some(child) => Arc::clone(child),
+            None => return -1,
+        }
+    };
+    drop(process_inner);
+    let pid = target.getpid();
+    let pgid = if pgid == 0 { pid } else { pgid };
+
+    let target_inner = target.inner_exclusive_access();
+    if target_inner.is_zombie || target_inner.sid != sid || target_inner.sid == pid {
+        return -1;
+    }
+    drop(target_inner);
+    // `PID2PCB` is locked before a PCB, so the target must not be held here.
+    if pgid != pid && !group_exists_in_session(pgid, sid) {
+        return -1;
+    }
+    target.inner_exclusive_access().pgid = pgid;
+    0
+}
+
+/// Get the process group ID of a process.
+///
+/// # Parameter
+/// - `pid`: Process ID(0 means the current process)
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process of `pid` => -1
+/// - Otherwise => Process group ID
+pub fn sys_getpgid(pid: usize) -> isize {
+    let process = match pid {
+        0 => current_process(),
+        pid => match pid2process(pid) {
+            Some(process) => process,
+            None => return -1,
+        },
+    };
+    let pgid = process.inner_exclusive_access().pgid;
+    pgid as isize
+}
+
+/// Get the session ID of a process.
+///
+/// # Parameter
+/// - `pid`: Process ID(0 means the current process)
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process of `pid` => -1
+/// - Otherwise => Session ID
+pub fn sys_getsid(pid: usize) -> isize {
+    let process = match pid {
+        0 => current_process(),
+        pid => match pid2process(pid) {
+            Some(process) => process,
+            None => return -1,
+        },
+    };
+    let sid = process.inner_exclusive_access().sid;
+    sid as isize
+}
+
+/// Create a new session led by the current process.
+///
+/// The current process also becomes the leader of a new process group,
+/// and loses the console as its controlling terminal.
+///
+/// # Return
+/// Conditional branching.
+/// - If a process group whose ID is the pid of the current process exists => -1
+/// - Otherwise => New session ID(the pid of the current process)
+pub fn sys_setsid() -> isize {
+    let process = current_process();
+    let pid = process.getpid();
+    // `PID2PCB` is locked before a PCB, so the current process must not be held here.
+    if !filter_processes(|p| !Arc::ptr_eq(p, &process) && p.inner_exclusive_access().pgid == pid)
+        .is_empty()
+    {
+        return -1;
+    }
+    // check and become the leader at once, so that no `setpgid` can get in between
+    let mut process_inner = process.inner_exclusive_access();
+    if process_inner.pgid == pid {
+        return -1;
+    }
+    process_inner.pgid = pid;
+    process_inner.sid = pid;
+    pid as isize
+}
+
 /// Create a child process with a new address space that inherits the stack of the parent process.
 /// The current process forks a child process.
 ///
@@ -48,15 +306,8 @@ pub fn sys_getpid() -> isize {
 pub fn sys_fork() -> isize {
     let current_process = current_process();
     let new_process = current_process.fork();
-    let new_pid = new_process.getpid();
-    // modify trap context of new_task, because it returns immediately after switching
-    let new_process_inner = new_process.inner_exclusive_access();
-    let task = new_process_inner.tasks[0].as_ref().unwrap();
-    let trap_cx = task.inner_exclusive_access().get_trap_cx();
-    // we do not have to move to next instruction since we have done it before
-    // for child process, fork returns 0
-    trap_cx.x[10] = 0; //x[10] is a0 register
-    new_pid as isize
+    // the trap context of the child has been modified to return 0 in `fork`
+    new_process.getpid() as isize
 }
 
 /// Clear the address space of the current process, load a specific executable file,
@@ -69,6 +320,7 @@ pub fn sys_fork() -> isize {
 /// # Return
 /// Conditional branching.
 /// - If there is an error => -1 (e.g. no executable file with matching name found)
+/// - If `path` is not a regular file, or not a valid ELF => -1
 /// - Otherwise => The length of `args` array
 pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
     let token = current_user_token();
@@ -87,101 +339,249 @@ pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
         }
     }
 
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
-        let all_data = app_inode.read_all();
-        let process = current_process();
-        let argc = args_vec.len();
-        process.exec(all_data.as_slice(), args_vec);
-        // return argc because cx.x[10] will be covered with it later
-        argc as isize
-    } else {
-        -1
+    match open_file(path.as_str(), OpenFlags::RDONLY) {
+        Some(app_inode) if app_inode.is_file() => {
+            let all_data = app_inode.read_all();
+            let process = current_process();
+            let argc = args_vec.len();
+            if !process.exec(all_data.as_slice(), args_vec) {
+                return -1;
+            }
+            // return argc because cx.x[10] will be covered with it later
+            argc as isize
+        }
+        _ => -1,
+    }
+}
+
+bitflags! {
+    /// Options of `sys_waitpid`
+    pub struct WaitFlags: u32 {
+        /// Return at once if no child process has exited.
+        const WNOHANG = 1 << 0;
+        /// Also report children stopped by a job control signal.
+        const WUNTRACED = 1 << 1;
     }
 }
 
 /// The current process waits for a child process to become a zombie process, collects all resources,
 /// and collects its return value.
 ///
+/// Unless `WNOHANG` is given, the current thread is blocked until a child process exits
+/// (or stops with `WUNTRACED`), or a signal arrives at the current process.
+///
 /// # Parameters
 /// - `pid`: Process ID of the child process to wait. If -1, it means to wait for any child process.
 /// - `exit_code_ptr`: Address where the return value of the child process is stored.
 ///              If this address is 0, it means that there is no need to store the return value.
+/// - `options`: [`WaitFlags`]
 ///
 /// # Return
 /// Conditional branching.
 /// - If there is not a child process whose pid is same as given => -1
-/// - If there is a child process but it is still running => -2
+/// - If `options` contains undefined bits => -1
+/// - If there is a child process but it is still running with `WNOHANG` => -2
+/// - If interrupted by a signal => -2
 /// - Otherwise => The process ID of the terminated child process
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_process();
-    // find a child process
+///
+/// # Information
+/// With `WUNTRACED`, a child stopped since the last report is also returned.
+/// Then `(signum << 8) | 0x7f` is stored in `exit_code_ptr`, as `WIFSTOPPED` of Linux expects.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: u32) -> isize {
+    let options = match WaitFlags::from_bits(options) {
+        Some(options) => options,
+        None => return -1,
+    };
+    let process = current_process();
+    loop {
+        // find a child process
 
-    // ---- access current TCB exclusively
-    let mut inner = task.inner_exclusive_access();
-    if !inner
-        .children
-        .iter()
-        .any(|p| pid == -1 || pid as usize == p.getpid())
-    {
-        return -1;
+        // ---- access current PCB exclusively
+        let mut inner = process.inner_exclusive_access();
+        if !inner
+            .children
+            .iter()
+            .any(|p| pid == -1 || pid as usize == p.getpid())
+        {
+            return -1;
+            // ---- release current PCB
+        }
+        let token = inner.memory_set.token();
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily access child PCB lock exclusively
+            p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
+            // ++++ release child PCB
+        });
+        if let Some((idx, _)) = pair {
+            // The child is deallocated when the exiting hart drops its last reference.
+            let child = inner.children.remove(idx);
+            let found_pid = child.getpid();
+            // ++++ temporarily access child TCB exclusively
+            let exit_code = child.inner_exclusive_access().exit_code;
+            // ++++ release child PCB
+            if !exit_code_ptr.is_null() {
+                *translated_refmut(token, exit_code_ptr) = exit_code;
+            }
+            return found_pid as isize;
+        }
+        if options.contains(WaitFlags::WUNTRACED) {
+            for child in inner.children.iter() {
+                if pid != -1 && pid as usize != child.getpid() {
+                    continue;
+                }
+                // ++++ temporarily access child PCB lock exclusively
+                if let Some(sig) = child.inner_exclusive_access().stop_signal.take() {
+                    if !exit_code_ptr.is_null() {
+                        *translated_refmut(token, exit_code_ptr) = ((sig as i32) << 8) | 0x7f;
+                    }
+                    return child.getpid() as isize;
+                }
+            }
+        }
+        // return to user mode to handle the pending signals
+        if options.contains(WaitFlags::WNOHANG)
+            || !(inner.signals - inner.blocked_signals(current_handling_sig())).is_empty()
+        {
+            return -2;
+        }
+        inner.wait_queue.push_back(current_task().unwrap());
+        drop(inner);
         // ---- release current PCB
+        block_current_and_run_next();
     }
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily access child PCB lock exclusively
-        p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
-        // ++++ release child PCB
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily access child TCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // ++++ release child PCB
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
-    }
-    // ---- release current PCB lock automatically
 }
 
-/// send a signal to the process
+/// send a signal to a process or a process group
 ///
 /// # Parameters
-/// - `pid`: pid of the process
+/// - `pid`:
+///   - `> 0`: pid of the process
+///   - `0`: every process in the process group of the current process
+///   - `< -1`: every process in the process group `-pid`
+///   - `-1`: (every process the caller can signal on Linux) unsupported
 /// - `signal`: integer value representing the signal
 ///
 /// # Return
 /// Conditional branching.
 /// - If the bit corresponding to `signum` in the signal of the process control block is successfully
 ///   set to 1. => 0
+/// - If `signum` is 0, nothing is sent and only the existence of the process(group) is checked. => 0
 ///
-/// - No `TaskControlBlock` corresponding to `pid`(1st arg) => -1
+/// - No `TaskControlBlock` corresponding to `pid`(1st arg), or no process in the group => -1
+/// - If `pid` is -1 => -1
 /// - no `signal` corresponding to `signum` => -1
-/// - If the bit of `signum` is already included in `signals` in the `TaskControlBlockInner`
-///   corresponding to `pid` => -1
+///
+/// A signal that is already pending in the process is not queued twice, but it is not an error.
 ///
 /// # Information
 /// It is to send a signal with the value signum to the process with process number pid.
 /// Specifically, it finds the process control block by `pid` and sets the bit corresponding to `signum`
 /// in the signal of that process control block to 1.
-pub fn sys_kill(pid: usize, signum: i32) -> isize {
-    // Extract corresponding task from process ID.
-    if let Some(task) = pid2process(pid) {
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            // insert the signal if legal
-            let inner = task.inner_exclusive_access();
-            let mut signals = inner.signals;
-            if signals.contains(flag) {
-                return -1;
-            }
-            signals.insert(flag);
-            0
+pub fn sys_kill(pid: isize, signum: i32) -> isize {
+    if signum < 0 || signum as usize > MAX_SIG || pid == -1 {
+        return -1;
+    }
+    let signal = SignalFlags::from_bits_truncate(1 << signum);
+    if pid <= 0 {
+        let pgid = if pid == 0 {
+            current_process().inner_exclusive_access().pgid
         } else {
-            -1
-        }
+            -pid as usize
+        };
+        let exists = if signum == 0 {
+            !filter_processes(|p| p.inner_exclusive_access().pgid == pgid).is_empty()
+        } else {
+            send_signal_to_group(pgid, signal)
+        };
+        return if exists { 0 } else { -1 };
+    }
+    // Extract corresponding task from process ID.
+    let process = match pid2process(pid as usize) {
+        Some(process) => process,
+        None => return -1,
+    };
+    if signum == 0 {
+        return 0;
+    }
+    // insert the signal if legal
+    send_signal(&process, signal);
+    0
+}
+
+/// Registers a new handler (`action` argument) corresponding to the `signum` given as argument
+/// and writes the original handler to `old_action`.
+///
+/// # Parameters
+/// - `signum`: A signal bit digit corresponding to the process to be registered.
+/// - `action`: new signal processing configuration(null => only query the current one)
+/// - `old_action`: old signal processing configuration(null => not written)
+///
+/// # Return
+/// Conditional branching.
+/// - If the `signum` and `action` arguments are successfully tied together => 0
+///
+/// - If `signum` exceeds the bit digits of `MAX_SIG` => -1
+/// - If `action` is not null and `signum` is `SIGKILL(1<<9)` or `SIGSTOP(1<<19)` => -1
+pub fn sys_sigaction(
+    signum: i32,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if signum <= 0 || signum as usize > MAX_SIG {
+        return -1;
+    }
+    let flag = SignalFlags::from_bits_truncate(1 << signum);
+    if !action.is_null() && (flag == SignalFlags::SIGKILL || flag == SignalFlags::SIGSTOP) {
+        return -1;
+    }
+    if !old_action.is_null() {
+        *translated_refmut(token, old_action) = inner.signal_actions.table[signum as usize];
+    }
+    if !action.is_null() {
+        inner.signal_actions.table[signum as usize] = *translated_ref(token, action);
+    }
+    0
+}
+
+/// Set signal to block
+///
+/// # Parameters
+/// - `mask`: signal mask
+///
+/// # Return
+/// Conditional branching.
+/// - When `signal_mask` is rewritten successfully => old `signal_mask`
+/// - If `mask` contains undefined bits => -1
+pub fn sys_sigprocmask(mask: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let old_mask = inner.signal_mask;
+    if let Some(flag) = SignalFlags::from_bits(mask as i32) {
+        inner.signal_mask = flag;
+        old_mask.bits() as isize
+    } else {
+        -1
+    }
+}
+
+/// Set the signal being processed to -1 (none) and restoring a backup of a trap context.
+///
+/// # Return
+/// Conditional branching.
+/// - Success to restore a backup of a trap context => a0 of the restored trap context
+///   (since the return value is written back to a0, it is kept as it was)
+/// - If no signal handler is running => -1
+pub fn sys_sigreturn() -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if let Some(backup) = inner.trap_ctx_backup.take() {
+        inner.handling_sig = -1;
+        let trap_cx = inner.get_trap_cx();
+        *trap_cx = backup;
+        trap_cx.x[10] as isize
     } else {
         -1
     }
@@ -0,0 +1,41 @@
+//! Signal actions registered by user programs
+use super::{SignalFlags, MAX_SIG};
+
+/// Action for a signal
+///
+/// Same layout as `user_lib::SignalAction`, so it can be copied to/from user space as is.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalAction {
+    /// Address of signal handle routine
+    /// - 0 => No handler is registered, the default action is taken.
+    pub handler: usize,
+    /// Signals to be blocked while this handler is running
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+/// Table of signal actions owned by a single process
+///
+/// Indexed by signal bit digit(e.g. `table[10]` => `SIGUSR1`).
+#[derive(Clone)]
+pub struct SignalActions {
+    /// Signal action of each signal
+    pub table: [SignalAction; MAX_SIG + 1],
+}
+
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+}
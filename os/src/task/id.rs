@@ -1,7 +1,10 @@
 //!Implementation of [`PidAllocator`]
-use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE};
+use crate::config::{
+    KERNEL_STACK_SIZE, KERNEL_STACK_SLOT_SIZE, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE,
+    USER_STACK_SIZE,
+};
 use crate::mm::{MapPermission, PhysPageNum, VirtAddr, KERNEL_SPACE};
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use alloc::sync::{Arc, Weak};
 use alloc::vec::Vec;
 use lazy_static::*;
@@ -47,10 +50,9 @@ impl RecycleAllocator {
 }
 
 lazy_static! {
-    static ref PID_ALLOCATOR: UPSafeCell<RecycleAllocator> =
-        unsafe { UPSafeCell::new(RecycleAllocator::new()) };
-    static ref KSTACK_ALLOCATOR: UPSafeCell<RecycleAllocator> =
-        unsafe { UPSafeCell::new(RecycleAllocator::new()) };
+    static ref PID_ALLOCATOR: SpinLock<RecycleAllocator> = SpinLock::new(RecycleAllocator::new());
+    static ref KSTACK_ALLOCATOR: SpinLock<RecycleAllocator> =
+        SpinLock::new(RecycleAllocator::new());
 }
 
 pub const IDLE_PID: usize = 0;
@@ -64,13 +66,13 @@ pub struct PidHandle(pub usize);
 impl Drop for PidHandle {
     fn drop(&mut self) {
         //println!("drop pid {}", self.0);
-        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
+        PID_ALLOCATOR.lock().dealloc(self.0);
     }
 }
 
 ///Allocate a pid(process identifier) from PID_ALLOCATOR
 pub fn pid_alloc() -> PidHandle {
-    PidHandle(PID_ALLOCATOR.exclusive_access().alloc())
+    PidHandle(PID_ALLOCATOR.lock().alloc())
 }
 
 /// Returns the (bottom, top) of the stack (kernel stack) allocated for each application's trap
@@ -80,16 +82,35 @@ pub fn pid_alloc() -> PidHandle {
 /// |----------------------|--------|
 /// |      trampoline      | --high |
 /// |  app's KernelStack   |        |
-/// |  app's guard page    |        |
+/// |  app's guard pages   |        |
 /// |  app's KernelStack   |        |
-/// |  app's guard page    | --low  |
+/// |  app's guard pages   | --low  |
 pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
-    // `+ PAGE_SIZE` is guard page
-    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    // the rest of a slot is guard pages
+    let top = TRAMPOLINE - app_id * KERNEL_STACK_SLOT_SIZE;
     let bottom = top - KERNEL_STACK_SIZE;
     (bottom, top)
 }
 
+/// Find the kernel stack whose guard pages contain `addr`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `addr` is in the guard pages below a kernel stack => Some(ID of the kernel stack)
+/// - Otherwise => None
+pub fn kernel_stack_guard_of(addr: usize) -> Option<usize> {
+    // kernel stacks are in the highest half of the address space
+    if (addr as isize) >= 0 || addr >= TRAMPOLINE {
+        return None;
+    }
+    let offset = TRAMPOLINE - 1 - addr;
+    if offset % KERNEL_STACK_SLOT_SIZE >= KERNEL_STACK_SIZE {
+        Some(offset / KERNEL_STACK_SLOT_SIZE)
+    } else {
+        None
+    }
+}
+
 ///KernelStack ID for each application
 pub struct KernelStack(pub usize);
 
@@ -98,9 +119,9 @@ pub struct KernelStack(pub usize);
 /// # Why?
 /// Because each application requires its own stack for trap processing.
 pub fn kstack_alloc() -> KernelStack {
-    let kstack_id = KSTACK_ALLOCATOR.exclusive_access().alloc();
+    let kstack_id = KSTACK_ALLOCATOR.lock().alloc();
     let (kstack_bottom, kstack_top) = kernel_stack_position(kstack_id);
-    KERNEL_SPACE.exclusive_access().insert_framed_area(
+    KERNEL_SPACE.lock().insert_framed_area(
         kstack_bottom.into(),
         kstack_top.into(),
         MapPermission::R | MapPermission::W,
@@ -113,7 +134,7 @@ impl Drop for KernelStack {
         let (kernel_stack_bottom, _) = kernel_stack_position(self.0);
         let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
         KERNEL_SPACE
-            .exclusive_access()
+            .lock()
             .remove_area_with_start_vpn(kernel_stack_bottom_va.into());
     }
 }
@@ -1,74 +1,77 @@
 //!Implementation of [`TaskManager`]
+use super::scheduler::{Scheduler, SchedulerImpl};
 use super::{process::ProcessControlBlock, TaskControlBlock};
-use crate::sync::UPSafeCell;
-use alloc::{
-    collections::{BTreeMap, VecDeque},
-    sync::Arc,
-};
+use crate::config::MAX_HARTS;
+use crate::hart::hart_id;
+use crate::sync::SpinLock;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use lazy_static::*;
 
 pub struct TaskManager {
-    /// Array of references to prepared tasks
+    /// Scheduling policy that holds references to prepared tasks
     ///
     /// # Information
     /// Instead of putting the task control block directly into the TaskManager,
     /// place it on the kernel heap and store only the smart pointer of its reference count in the TaskManager,
     /// the unit of operation of the TaskManager, to reduce the overhead of data copying.
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    scheduler: SchedulerImpl,
 }
 
-/// A simple FIFO scheduler.
+/// Ready tasks scheduled by the policy selected at build time(see [`super::scheduler`]).
 impl TaskManager {
     ///Create an empty `TaskManager`
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            scheduler: SchedulerImpl::new(),
         }
     }
 
     ///Add a task to `TaskManager`
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+        self.scheduler.add(task);
     }
 
-    ///Remove the first task and return it,or `None` if `TaskManager` is empty
+    ///Remove the task that runs next and return it,or `None` if `TaskManager` is empty
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
+        self.scheduler.fetch()
     }
 
-    /// Finds references in the ready_queue array that are identical to the `task` argument and removes them
+    /// Finds references in the ready tasks that are identical to the `task` argument and removes them
     pub fn remove(&mut self, task: Arc<TaskControlBlock>) {
-        if let Some((id, _)) = self
-            .ready_queue
-            .iter()
-            .enumerate()
-            .find(|(_, t)| Arc::as_ptr(t) == Arc::as_ptr(&task))
-        {
-            self.ready_queue.remove(id);
-        }
+        self.scheduler.remove(&task);
     }
 }
 
 lazy_static! {
-    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
-        unsafe { UPSafeCell::new(TaskManager::new()) };
-    pub static ref PID2PCB: UPSafeCell<BTreeMap<usize, Arc<ProcessControlBlock>>> =
-        unsafe { UPSafeCell::new(BTreeMap::new()) };
+    /// Ready queue of each hart, indexed by hart id
+    pub static ref TASK_MANAGERS: Vec<SpinLock<TaskManager>> = (0..MAX_HARTS)
+        .map(|_| SpinLock::new(TaskManager::new()))
+        .collect();
+    pub static ref PID2PCB: SpinLock<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        SpinLock::new(BTreeMap::new());
 }
 
-/// Appends an element to the back of the deque.
+/// Appends an element to the ready queue of the current hart.
 pub fn add_task(task: Arc<TaskControlBlock>) {
-    TASK_MANAGER.exclusive_access().add(task);
+    TASK_MANAGERS[hart_id()].lock().add(task);
 }
 
-/// Remove an element to the back of the deque.
+/// Remove an element from the ready queue of any hart.
 pub fn remove_task(task: Arc<TaskControlBlock>) {
-    TASK_MANAGER.exclusive_access().remove(task);
+    for manager in TASK_MANAGERS.iter() {
+        manager.lock().remove(Arc::clone(&task));
+    }
 }
 
-///Interface offered to pop the first task
+///Interface offered to pop the task that runs next on the current hart.
+///
+/// If the ready queue of the current hart is empty, a task is stolen from another hart.
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.exclusive_access().fetch()
+    let id = hart_id();
+    if let Some(task) = TASK_MANAGERS[id].lock().fetch() {
+        return Some(task);
+    }
+    (1..MAX_HARTS).find_map(|i| TASK_MANAGERS[(id + i) % MAX_HARTS].lock().fetch())
 }
 
 /// Insert into Map that ties process and process ID together
@@ -80,7 +83,7 @@ pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
 /// # Return
 /// - `ProcessControlBlock` of pid
 pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
-    PID2PCB.exclusive_access().insert(pid, process);
+    PID2PCB.lock().insert(pid, process);
 }
 
 /// Get ProcessControlBlock (one process data) from process ID
@@ -91,7 +94,7 @@ pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
 /// # Return
 /// - `ProcessControlBlock` of pid
 pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
-    let map = PID2PCB.exclusive_access();
+    let map = PID2PCB.lock();
     map.get(&pid).map(Arc::clone)
 }
 
@@ -103,8 +106,19 @@ pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
 /// # Panic
 /// If there is no corresponding process ID.
 pub fn remove_from_pid2process(pid: usize) {
-    let mut map = PID2PCB.exclusive_access();
+    let mut map = PID2PCB.lock();
     if map.remove(&pid).is_none() {
         panic!("cannot find pid {} in pid2task!", pid);
     }
 }
+
+/// Collect processes for which `pred` returns true
+///
+/// # Parameter
+/// - `pred`: Called once for every process
+pub fn filter_processes(
+    mut pred: impl FnMut(&Arc<ProcessControlBlock>) -> bool,
+) -> Vec<Arc<ProcessControlBlock>> {
+    let map = PID2PCB.lock();
+    map.values().filter(|p| pred(p)).map(Arc::clone).collect()
+}
@@ -3,22 +3,24 @@
 //! Everything about task management, like starting and switching tasks is
 //! implemented here.
 //!
-//! A single global instance of [`TaskManager`] called `TASK_MANAGER` controls
-//! all the tasks in the whole operating system.
+//! Each hart has its own [`TaskManager`] in `TASK_MANAGERS` that holds ready tasks.
+//! A hart whose ready queue is empty steals a task from the other harts.
 //!
-//! A single global instance of [`Processor`] called `PROCESSOR` monitors running
-//! task(s) for each core.
+//! Each hart has its own [`Processor`] in `PROCESSORS` that monitors the task
+//! running on it.
 //!
 //! A single global instance of [`PidAllocator`] called `PID_ALLOCATOR` allocates
 //! pid for user apps.
 //!
 //! Be careful when you see `__switch` ASM function in `switch.S`. Control flow around this function
 //! might not be what you expect.
+mod action;
 mod context;
 mod id;
 mod manager;
 mod process;
 mod processor;
+mod scheduler;
 mod signal;
 mod switch;
 #[allow(clippy::module_inception)]
@@ -26,20 +28,28 @@ mod task;
 
 use self::id::TaskUserRes;
 use crate::fs::{open_file, OpenFlags};
+use crate::hart::kick_other_harts;
+use crate::timer::{
+    get_time_us, remove_timer, wakeup_timer, ITIMER_PROF, ITIMER_REAL, ITIMER_VIRTUAL,
+    USEC_PER_TICK,
+};
 use alloc::{sync::Arc, vec::Vec};
+use core::hint::spin_loop;
 use lazy_static::*;
 use manager::fetch_task;
 use process::ProcessControlBlock;
 use switch::__switch;
 
+pub use action::{SignalAction, SignalActions};
 pub use context::TaskContext;
-pub use id::{kstack_alloc, pid_alloc, KernelStack, PidHandle, IDLE_PID};
-pub use manager::{add_task, pid2process, remove_from_pid2process, remove_task};
+pub use id::{kernel_stack_guard_of, kstack_alloc, pid_alloc, KernelStack, PidHandle, IDLE_PID};
+pub use manager::{add_task, filter_processes, pid2process, remove_from_pid2process, remove_task};
 pub use processor::{
     current_kstack_top, current_process, current_task, current_trap_cx, current_trap_cx_user_va,
     current_user_token, run_tasks, schedule, take_current_task,
 };
-pub use signal::SignalFlags;
+pub use scheduler::MIN_PRIORITY;
+pub use signal::{SignalDefaultAction, SignalFlags, MAX_SIG};
 pub use task::{TaskControlBlock, TaskStatus};
 
 /// Suspend the current 'Running' task and run the next task in task list.
@@ -71,6 +81,18 @@ pub fn block_current_and_run_next() {
     schedule(task_cx_ptr);
 }
 
+/// Stop the currently running thread by setting its state to `Stopped` and switch to another task.
+///
+/// The thread is not pushed back to the ready queue. It is re-queued by [`continue_process`].
+pub fn stop_current_and_run_next() {
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    task_inner.task_status = TaskStatus::Stopped;
+    drop(task_inner);
+    schedule(task_cx_ptr);
+}
+
 #[cfg(feature = "board_qemu")]
 use crate::board::QEMUExit;
 
@@ -83,11 +105,12 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     let tid = task_inner.res.as_ref().unwrap().tid;
     // Record exit code
     task_inner.exit_code = Some(exit_code);
-    // Delete thread
-    task_inner.res = None;
+    // Delete thread. `TaskUserRes` accesses the PCB when dropped, so release the TCB first.
+    let res = task_inner.res.take();
     // here we do not remove the thread since we are still using the kstack
     // it will be deallocated when sys_waittid is called
     drop(task_inner);
+    drop(res);
     drop(task);
 
     // however, if this is the main thread of current process
@@ -100,6 +123,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
                 "[kernel] Idle process exit with exit_code {} ...",
                 exit_code
             );
+            crate::console::flush();
             if exit_code != 0 {
                 //crate::sbi::shutdown(255); //255 == -1 for err hint
                 crate::board::QEMU_EXIT_HANDLE.exit_failure();
@@ -110,53 +134,50 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         }
 
         remove_from_pid2process(pid);
-        let mut process_inner = process.inner_exclusive_access();
-        // Mark this process as a zombie process
-        process_inner.is_zombie = true;
-        // Record exit code
-        process_inner.exit_code = exit_code;
-
-        {
-            // move all child processes under init process
-            let mut initproc_inner = INITPROC.inner_exclusive_access();
-            for child in process_inner.children.iter() {
-                child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
-                initproc_inner.children.push(child.clone());
-            }
-        }
-
         // deallocate user res (including tid/trap_cx/ustack) of all threads
         // it has to be done before we dealloc the whole memory_set
         // otherwise they will be deallocated twice
-        let mut recycle_res = Vec::<TaskUserRes>::new();
-        for task in process_inner.tasks.iter().filter(|t| t.is_some()) {
-            let task = task.as_ref().unwrap();
-            // if other tasks are Ready in TaskManager or waiting for a timer to be
-            // expired, we should remove them.
-            //
-            // Mention that we do not need to consider Mutex/Semaphore since they
-            // are limited in a single process. Therefore, the blocked tasks are
-            // removed when the PCB is deallocated.
-            remove_inactive_task(Arc::clone(task));
-            let mut task_inner = task.inner_exclusive_access();
-            if let Some(res) = task_inner.res.take() {
-                recycle_res.push(res);
-            }
-        }
-        // dealloc_tid and dealloc_user_res require access to PCB inner, so we
-        // need to collect those user res first, then release process_inner
-        // for now to avoid deadlock/double borrow problem.
+        kill_other_threads(&process);
+
+        // Never hold the PCB while accessing the parent or the children, since `waitpid` of
+        // the parent accesses them in the order parent -> child.
+        let mut process_inner = process.inner_exclusive_access();
+        let children = core::mem::take(&mut process_inner.children);
+        let parent = process_inner.parent.as_ref().and_then(|p| p.upgrade());
+        // Freeing an unlinked inode writes the disk, so the files are dropped after the PCB is released.
+        let fd_table = core::mem::take(&mut process_inner.fd_table);
         drop(process_inner);
-        recycle_res.clear();
+        drop(fd_table);
+
+        // move all child processes under init process
+        for child in children.iter() {
+            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+        }
+        if !children.is_empty() {
+            let mut initproc_inner = INITPROC.inner_exclusive_access();
+            initproc_inner.children.extend(children);
+            // some of them may be zombies already
+            initproc_inner.wakeup_waiters();
+        }
 
         let mut process_inner = process.inner_exclusive_access();
-        process_inner.children.clear();
         // deallocate other data in user space i.e. program code/data section
         process_inner.memory_set.recycle_data_pages();
-        // drop file descriptors
-        process_inner.fd_table.clear();
+        // no thread of this process can wait any more
+        process_inner.wait_queue.clear();
         // remove all tasks
         process_inner.tasks.clear();
+        // Mark this process as a zombie process at last, since the parent may reap it at once.
+        process_inner.is_zombie = true;
+        // Record exit code
+        process_inner.exit_code = exit_code;
+        drop(process_inner);
+
+        // notify the parent waiting in `waitpid`
+        if let Some(parent) = parent {
+            send_signal(&parent, SignalFlags::SIGCHLD);
+            parent.inner_exclusive_access().wakeup_waiters();
+        }
     }
 
     drop(process);
@@ -165,6 +186,46 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     schedule(&mut _unused as *mut _);
 }
 
+/// Make all threads of `process` except the current one exit, and deallocate their user res.
+///
+/// Threads running on other harts are interrupted by IPIs and exit by themselves,
+/// since `SIGKILL` is pending. The other threads are removed from the ready queue and the timer queue,
+/// and are never run again once their user res is taken(see `run_tasks`).
+///
+/// Mention that we do not need to consider Mutex/Semaphore since they
+/// are limited in a single process. Therefore, the blocked tasks are
+/// removed when the PCB is deallocated.
+fn kill_other_threads(process: &Arc<ProcessControlBlock>) {
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.signals.insert(SignalFlags::SIGKILL);
+    // stopped threads must not be re-queued by `SIGCONT`
+    process_inner.is_stopped = false;
+    let tasks: Vec<_> = process_inner.tasks.iter().flatten().cloned().collect();
+    drop(process_inner);
+    kick_other_harts();
+
+    let mut recycle_res = Vec::<TaskUserRes>::new();
+    for task in tasks {
+        loop {
+            remove_inactive_task(Arc::clone(&task));
+            let mut task_inner = task.inner_exclusive_access();
+            // the current thread or a thread that has exited by itself
+            if task_inner.res.is_none() {
+                break;
+            }
+            if !task_inner.on_cpu {
+                recycle_res.extend(task_inner.res.take());
+                break;
+            }
+            drop(task_inner);
+            spin_loop();
+        }
+    }
+    // dealloc_tid and dealloc_user_res require access to PCB inner, so we
+    // need to collect those user res first.
+    recycle_res.clear();
+}
+
 lazy_static! {
     ///Global process that init user shell
     pub static ref INITPROC: Arc<ProcessControlBlock> = {
@@ -179,12 +240,263 @@ pub fn add_initproc() {
     let _initproc = INITPROC.clone();
 }
 
+/// The signal whose handler is running on the current thread(-1 if none)
+pub fn current_handling_sig() -> isize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .handling_sig
+}
+
 /// If the signal representing the error is in the current task signals (self == SignalFlags)
 /// => return (- signum, description)
+///
+/// Blocked signals and signals caught by a user handler are not treated as errors.
+/// Pending signals whose default action is to be ignored or to continue are discarded here,
+/// and a pending stop signal stops the current process(see [`is_current_stopped`]).
 pub fn check_signals_of_current() -> Option<(i32, &'static str)> {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let mut pending = process_inner.signals - process_inner.blocked_signals(current_handling_sig());
+    let mut stop_sig = None;
+    for sig in 0..=MAX_SIG {
+        let signal = SignalFlags::from_bits_truncate(1 << sig);
+        if process_inner.signal_actions.table[sig].handler != 0 || !pending.contains(signal) {
+            pending.remove(signal);
+            continue;
+        }
+        match signal.default_action() {
+            SignalDefaultAction::Ignore | SignalDefaultAction::Continue => {
+                pending.remove(signal);
+                process_inner.signals.remove(signal);
+            }
+            SignalDefaultAction::Stop => {
+                pending.remove(signal);
+                process_inner.signals.remove(signal);
+                stop_sig.get_or_insert(sig);
+            }
+            SignalDefaultAction::Terminate | SignalDefaultAction::CoreDump => {}
+        }
+    }
+    if let Some((errno, msg)) = pending.check_error() {
+        let signal = SignalFlags::from_bits_truncate(1 << -errno);
+        if signal.default_action() == SignalDefaultAction::CoreDump {
+            let trap_cx = current_trap_cx();
+            println!(
+                "[kernel] core dumped: sepc = {:#x}, sp = {:#x}",
+                trap_cx.sepc, trap_cx.x[2]
+            );
+        }
+        return Some((errno, msg));
+    }
+    if let Some(sig) = stop_sig {
+        process_inner.is_stopped = true;
+        process_inner.stop_signal = Some(sig);
+        // Ready threads are taken out of the ready queue here, the current thread is stopped
+        // by the trap handler and the blocking ones stop themselves when they are woken up.
+        for task in process_inner.tasks.iter().flatten() {
+            let mut task_inner = task.inner_exclusive_access();
+            if task_inner.task_status == TaskStatus::Ready {
+                task_inner.task_status = TaskStatus::Stopped;
+                drop(task_inner);
+                remove_task(Arc::clone(task));
+            }
+        }
+        // threads running on other harts stop at the end of their next trap
+        kick_other_harts();
+        // notify the parent waiting in `waitpid` with `WUNTRACED`
+        let parent = process_inner.parent.as_ref().and_then(|p| p.upgrade());
+        // Freeing an unlinked inode writes the disk, so the files are dropped after the PCB is released.
+        let fd_table = core::mem::take(&mut process_inner.fd_table);
+        drop(process_inner);
+        drop(fd_table);
+        if let Some(parent) = parent {
+            send_signal(&parent, SignalFlags::SIGCHLD);
+            parent.inner_exclusive_access().wakeup_waiters();
+        }
+    }
+    None
+}
+
+/// Is the process of the current thread stopped by a job control signal?
+pub fn is_current_stopped() -> bool {
+    current_process().inner_exclusive_access().is_stopped
+}
+
+/// Re-queue all stopped threads of `process` to the ready queue.
+///
+/// Do nothing if `process` is not stopped.
+pub fn continue_process(process: &Arc<ProcessControlBlock>) {
+    let mut process_inner = process.inner_exclusive_access();
+    if !process_inner.is_stopped {
+        return;
+    }
+    process_inner.is_stopped = false;
+    process_inner.stop_signal = None;
+    for task in process_inner.tasks.iter().flatten() {
+        let mut task_inner = task.inner_exclusive_access();
+        if task_inner.task_status == TaskStatus::Stopped {
+            task_inner.task_status = TaskStatus::Ready;
+            drop(task_inner);
+            add_task(Arc::clone(task));
+        }
+    }
+}
+
+/// Add `signal` to the signals waiting to be processed by `process`.
+///
+/// `SIGCONT` and `SIGKILL` continue a stopped process at once, since it cannot handle signals
+/// by itself until it runs again. `SIGCONT` discards pending stop signals and vice versa.
+/// A signal that would be ignored by default is discarded here unless a handler is registered.
+///
+/// Threads of `process` blocked in `waitpid` or `sleep` are woken up to handle the signal,
+/// unless it is blocked by `sigprocmask`.
+/// A signal that is already waiting to be processed is merged into the pending one.
+pub fn send_signal(process: &Arc<ProcessControlBlock>, signal: SignalFlags) {
+    let mut process_inner = process.inner_exclusive_access();
+    if process_inner.signals.contains(signal) {
+        return;
+    }
+    let sig = signal.bits().trailing_zeros() as usize;
+    if process_inner.signal_actions.table[sig].handler == 0
+        && signal.default_action() == SignalDefaultAction::Ignore
+    {
+        return;
+    }
+    if signal == SignalFlags::SIGCONT {
+        process_inner
+            .signals
+            .remove(SignalFlags::job_control_stop());
+    } else if SignalFlags::job_control_stop().contains(signal) {
+        process_inner.signals.remove(SignalFlags::SIGCONT);
+    }
+    process_inner.signals.insert(signal);
+    process_inner.wakeup_waiters();
+    let blocked = process_inner.signal_mask - (SignalFlags::SIGKILL | SignalFlags::SIGSTOP);
+    let sleepers: Vec<_> = if blocked.contains(signal) {
+        Vec::new()
+    } else {
+        process_inner.tasks.iter().flatten().cloned().collect()
+    };
+    drop(process_inner);
+    for task in sleepers.iter() {
+        wakeup_timer(task);
+    }
+    if signal == SignalFlags::SIGCONT || signal == SignalFlags::SIGKILL {
+        continue_process(process);
+    }
+}
+
+/// Send `signal` to every process of the process group `pgid`.
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process in the group => false
+/// - Otherwise => true
+pub fn send_signal_to_group(pgid: usize, signal: SignalFlags) -> bool {
+    let processes = filter_processes(|process| process.inner_exclusive_access().pgid == pgid);
+    for process in processes.iter() {
+        send_signal(process, signal);
+    }
+    !processes.is_empty()
+}
+
+/// Is there a process of the process group `pgid` in the session `sid`?
+pub fn group_exists_in_session(pgid: usize, sid: usize) -> bool {
+    !filter_processes(|process| {
+        let process_inner = process.inner_exclusive_access();
+        process_inner.pgid == pgid && process_inner.sid == sid
+    })
+    .is_empty()
+}
+
+/// Handle the pending signals of the current process in the kernel, for a thread waiting
+/// in a system call that can go on waiting after them.
+///
+/// A stop signal stops the current thread here until the process is continued,
+/// so that the system call does not have to return to user mode for it.
+///
+/// # Return
+/// Conditional branching.
+/// - If a pending signal terminates the process or is caught by a user handler => true
+///   (the system call should return to user mode)
+/// - Otherwise => false
+pub fn handle_signals_in_kernel() -> bool {
+    loop {
+        if check_signals_of_current().is_some() {
+            return true;
+        }
+        if !is_current_stopped() {
+            break;
+        }
+        stop_current_and_run_next();
+    }
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
-    process_inner.signals.check_error()
+    !(process_inner.signals - process_inner.blocked_signals(current_handling_sig())).is_empty()
+}
+
+/// Charge one timer tick of CPU time to the current process.
+///
+/// `SIGVTALRM`/`SIGPROF` is sent when its virtual/profiling interval timer expires.
+///
+/// # Parameter
+/// - `user`: Did the tick interrupt user mode? Ticks in the kernel only count for `ITIMER_PROF`.
+pub fn account_tick_of_current(user: bool) {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let virt_expired = user && process_inner.itimers[ITIMER_VIRTUAL].expire_cpu(USEC_PER_TICK);
+    let prof_expired = process_inner.itimers[ITIMER_PROF].expire_cpu(USEC_PER_TICK);
+    drop(process_inner);
+    if virt_expired {
+        send_signal(&process, SignalFlags::SIGVTALRM);
+    }
+    if prof_expired {
+        send_signal(&process, SignalFlags::SIGPROF);
+    }
+}
+
+/// Send `SIGALRM` to every process whose real time interval timer has expired.
+pub fn check_itimers_real() {
+    let now_us = get_time_us();
+    let expired = filter_processes(|process| {
+        process.inner_exclusive_access().itimers[ITIMER_REAL].expire_real(now_us)
+    });
+    for process in expired {
+        send_signal(&process, SignalFlags::SIGALRM);
+    }
+}
+
+/// Deliver one pending signal of the current process to its user handler.
+///
+/// The trap context of the current thread is backed up and rewritten, so that returning to
+/// user mode jumps to the handler with the signal bit digit as the 1st argument(a0).
+/// The handler has to call `sigreturn` to resume the interrupted context.
+///
+/// Only one handler runs at a time on a thread. Other caught signals stay pending until `sigreturn`.
+pub fn handle_signals() {
+    let task = current_task().unwrap();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let handling_sig = task.inner_exclusive_access().handling_sig;
+    if handling_sig != -1 {
+        return;
+    }
+    let pending = process_inner.signals - process_inner.blocked_signals(handling_sig);
+    for sig in 1..=MAX_SIG {
+        let signal = SignalFlags::from_bits_truncate(1 << sig);
+        let handler = process_inner.signal_actions.table[sig].handler;
+        if pending.contains(signal) && handler != 0 {
+            process_inner.signals.remove(signal);
+            let mut task_inner = task.inner_exclusive_access();
+            task_inner.handling_sig = sig as isize;
+            let trap_cx = task_inner.get_trap_cx();
+            task_inner.trap_ctx_backup = Some(*trap_cx);
+            trap_cx.sepc = handler;
+            trap_cx.x[10] = sig;
+            return;
+        }
+    }
 }
 
 /// Add a signal for the `signal` argument to the signals(`TaskBlockInner.signals`) waiting to be processed.
@@ -194,6 +506,8 @@ pub fn current_add_signal(signal: SignalFlags) {
     process_inner.signals |= signal;
 }
 
+/// Remove `task` from the ready queue and the timer queue.
 pub fn remove_inactive_task(task: Arc<TaskControlBlock>) {
     remove_task(Arc::clone(&task));
+    remove_timer(Arc::clone(&task));
 }
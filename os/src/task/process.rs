@@ -2,29 +2,35 @@
 use super::id::RecycleAllocator;
 use super::manager::insert_into_pid2process;
 use super::TaskControlBlock;
-use super::{add_task, SignalFlags};
+use super::{add_task, SignalActions, SignalFlags};
 use super::{pid_alloc, PidHandle};
 use crate::fs::{File, Stdin, Stdout};
 use crate::mm::{translated_refmut, MemorySet, KERNEL_SPACE};
-use crate::sync::{Condvar, Mutex, Semaphore, UPSafeCell};
+use crate::sync::{Condvar, Mutex, Semaphore, SpinLock, SpinLockGuard};
+use crate::timer::ITimer;
 use crate::trap::{trap_handler, TrapContext};
+use alloc::collections::VecDeque;
 use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
 use alloc::vec::Vec;
-use core::cell::RefMut;
 
 /// A structure of the components of a single task
 pub struct ProcessControlBlock {
     // immutable
     pub pid: PidHandle,
     // mutable
-    inner: UPSafeCell<ProcessControlBlockInner>,
+    inner: SpinLock<ProcessControlBlockInner>,
 }
 
 pub struct ProcessControlBlockInner {
     /// Is it in a zombie state (waiting for the process to finish running and be deleted)?
     pub is_zombie: bool,
+    /// Is it stopped by a job control signal (all threads are `TaskStatus::Stopped`)?
+    pub is_stopped: bool,
+    /// Bit digit of the signal that stopped this process.
+    /// - Some => the stop has not been reported to the parent by `waitpid` yet
+    pub stop_signal: Option<usize>,
     /// Address space for the application.
     pub memory_set: MemorySet,
     /// Parent of the current process (if it exists).
@@ -35,6 +41,15 @@ pub struct ProcessControlBlockInner {
     /// Instead, all task control blocks of the current process's children are held in the vector
     /// as `Arc` smart pointers so that they can be found more easily.
     pub children: Vec<Arc<ProcessControlBlock>>,
+    /// Process group ID
+    ///
+    /// Signals from the console(`^C`/`^Z`) and `kill` with a negative pid are sent to
+    /// every process of a group.
+    pub pgid: usize,
+    /// Session ID(the pid of the session leader)
+    ///
+    /// A process can only join a process group in its own session.
+    pub sid: usize,
     /// When a process exits spontaneously by invoking the exit system call or is terminated by the kernel
     /// with an error, its `exit_code` is stored in its task control block by the kernel and waits
     /// for the parent process to retrieve its PID and exit code while retrieving resources via `waitpid`.
@@ -58,6 +73,16 @@ pub struct ProcessControlBlockInner {
     ///
     /// Signals registered here are those that are to be processed.
     pub signals: SignalFlags,
+    /// Signals blocked by `sigprocmask`
+    ///
+    /// Blocked signals stay in `signals` until they are unblocked.
+    pub signal_mask: SignalFlags,
+    /// User handlers registered by `sigaction`
+    pub signal_actions: SignalActions,
+    /// Interval timers indexed by `ITIMER_REAL`, `ITIMER_VIRTUAL` and `ITIMER_PROF`
+    pub itimers: [ITimer; 3],
+    /// Threads blocked in `waitpid` until a child process exits or stops, or a signal arrives
+    pub wait_queue: VecDeque<Arc<TaskControlBlock>>,
     /// Threads
     pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
     /// Relatively generic resource allocator that can allocate process identifiers (PIDs) and thread KernelStacks.
@@ -109,6 +134,28 @@ impl ProcessControlBlockInner {
         self.task_res_allocator.dealloc(tid)
     }
 
+    /// Signals that are blocked now for a thread of this process.
+    ///
+    /// Both the signals in `signal_mask` and the mask of the handler running on the thread
+    /// are blocked, but `SIGKILL` and `SIGSTOP` can never be blocked.
+    ///
+    /// # Parameter
+    /// - `handling_sig`: The signal whose handler is running on the thread(-1 if none)
+    pub fn blocked_signals(&self, handling_sig: isize) -> SignalFlags {
+        let mut blocked = self.signal_mask;
+        if handling_sig != -1 {
+            blocked |= self.signal_actions.table[handling_sig as usize].mask;
+        }
+        blocked - (SignalFlags::SIGKILL | SignalFlags::SIGSTOP)
+    }
+
+    /// Wake up all threads blocked in `waitpid` of this process.
+    pub fn wakeup_waiters(&mut self) {
+        while let Some(task) = self.wait_queue.pop_front() {
+            add_task(task);
+        }
+    }
+
     /// Get all threads number of this process
     ///
     /// # Return
@@ -127,42 +174,52 @@ impl ProcessControlBlockInner {
 }
 
 impl ProcessControlBlock {
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, ProcessControlBlockInner> {
-        self.inner.exclusive_access()
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, ProcessControlBlockInner> {
+        self.inner.lock()
     }
 
     pub fn new(elf_data: &[u8]) -> Arc<Self> {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, ustack_base, entry_point) =
+            MemorySet::from_elf(elf_data).expect("invalid elf!");
 
         // allocate a pid
         let pid_handle = pid_alloc();
+        let pid = pid_handle.0;
         // push a task context which goes to trap_return to the top of kernel stack
         let process = Arc::new(Self {
             pid: pid_handle,
-            inner: unsafe {
-                UPSafeCell::new(ProcessControlBlockInner {
-                    is_zombie: false,
-                    memory_set,
-                    parent: None,
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: vec![
-                        // 0 -> stdin
-                        Some(Arc::new(Stdin)),
-                        // 1 -> stdin
-                        Some(Arc::new(Stdout)),
-                        // 2 -> stderr
-                        Some(Arc::new(Stdout)),
-                    ],
-                    signals: SignalFlags::empty(),
-                    tasks: Vec::new(),
-                    task_res_allocator: RecycleAllocator::new(),
-                    mutex_list: Vec::new(),
-                    semaphore_list: Vec::new(),
-                    condvar_list: Vec::new(),
-                })
-            },
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
+                is_stopped: false,
+                stop_signal: None,
+                memory_set,
+                parent: None,
+                children: Vec::new(),
+                // the first process leads its own session and process group
+                pgid: pid,
+                sid: pid,
+                exit_code: 0,
+                fd_table: vec![
+                    // 0 -> stdin
+                    Some(Arc::new(Stdin)),
+                    // 1 -> stdin
+                    Some(Arc::new(Stdout)),
+                    // 2 -> stderr
+                    Some(Arc::new(Stdout)),
+                ],
+                signals: SignalFlags::empty(),
+                signal_mask: SignalFlags::empty(),
+                signal_actions: SignalActions::default(),
+                // interval timers are not inherited by the child
+                itimers: [ITimer::default(); 3],
+                wait_queue: VecDeque::new(),
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+            }),
         });
 
         // prepare TrapContext in user space
@@ -179,7 +236,7 @@ impl ProcessControlBlock {
         *trap_cx = TrapContext::app_init_context(
             entry_point,
             ustack_top,
-            KERNEL_SPACE.exclusive_access().token(),
+            KERNEL_SPACE.lock().token(),
             kstack_top,
             trap_handler as usize,
         );
@@ -200,17 +257,31 @@ impl ProcessControlBlock {
     /// # Parameters
     /// - `elf_data`: elf
     /// - `args`: command arguments
-    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
+    ///
+    /// # Return
+    /// Conditional branching.
+    /// - If `elf_data` is not a valid ELF => false(the current program is kept)
+    /// - Otherwise => true
+    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) -> bool {
         assert_eq!(self.inner_exclusive_access().thread_count(), 1);
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, ustack_base, entry_point) = match MemorySet::from_elf(elf_data) {
+            Some(elf) => elf,
+            None => return false,
+        };
         let new_token = memory_set.token();
         // substitute memory_set
-        self.inner_exclusive_access().memory_set = memory_set;
+        let mut inner = self.inner_exclusive_access();
+        inner.memory_set = memory_set;
+        // handlers point into the old program image, so reset them to default
+        inner.signal_actions = SignalActions::default();
+        drop(inner);
         // then we alloc user resource for main thread again
         // since memory_set has been changed
         let task = self.inner_exclusive_access().get_task(0);
         let mut task_inner = task.inner_exclusive_access();
+        task_inner.handling_sig = -1;
+        task_inner.trap_ctx_backup = None;
         task_inner.res.as_mut().unwrap().ustack_base = ustack_base;
         task_inner.res.as_mut().unwrap().alloc_user_res();
         task_inner.trap_cx_ppn = task_inner.res.as_mut().unwrap().trap_cx_ppn();
@@ -276,7 +347,7 @@ impl ProcessControlBlock {
         let mut trap_cx = TrapContext::app_init_context(
             entry_point,
             user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
+            KERNEL_SPACE.lock().token(),
             task.kstack.get_top(),
             trap_handler as usize,
         );
@@ -285,6 +356,7 @@ impl ProcessControlBlock {
         // x11 => user application 2nd argument(a1)
         trap_cx.x[11] = argv_base;
         *task_inner.get_trap_cx() = trap_cx;
+        true
     }
 
     /// Fork this process
@@ -311,22 +383,31 @@ impl ProcessControlBlock {
         let pid = pid_alloc();
         let child = Arc::new(ProcessControlBlock {
             pid,
-            inner: unsafe {
-                UPSafeCell::new(ProcessControlBlockInner {
-                    is_zombie: false,
-                    memory_set,
-                    parent: Some(Arc::downgrade(self)),
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: new_fd_table,
-                    signals: SignalFlags::empty(),
-                    tasks: Vec::new(),
-                    task_res_allocator: RecycleAllocator::new(),
-                    mutex_list: Vec::new(),
-                    semaphore_list: Vec::new(),
-                    condvar_list: Vec::new(),
-                })
-            },
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
+                is_stopped: false,
+                stop_signal: None,
+                memory_set,
+                parent: Some(Arc::downgrade(self)),
+                children: Vec::new(),
+                // inherit the process group and session of the parent
+                pgid: parent.pgid,
+                sid: parent.sid,
+                exit_code: 0,
+                fd_table: new_fd_table,
+                signals: SignalFlags::empty(),
+                // inherit the signal mask and handlers of the parent
+                signal_mask: parent.signal_mask,
+                signal_actions: parent.signal_actions.clone(),
+                // interval timers are not inherited by the child
+                itimers: [ITimer::default(); 3],
+                wait_queue: VecDeque::new(),
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+            }),
         });
         // add child
         parent.children.push(Arc::clone(&child));
@@ -345,6 +426,8 @@ impl ProcessControlBlock {
             // but mention that we allocate a new kstack here
             false,
         ));
+        // `PID2PCB` is locked before a PCB in `filter_processes`, so release the parent first
+        drop(parent);
 
         // attach task process
         let mut child_inner = child.inner_exclusive_access();
@@ -355,6 +438,10 @@ impl ProcessControlBlock {
         let task_inner = task.inner_exclusive_access();
         let trap_cx = task_inner.get_trap_cx();
         trap_cx.kernel_sp = task.kstack.get_top();
+        // we do not have to move to next instruction since we have done it before
+        // for child process, fork returns 0.
+        // This must be done before the thread is added to the scheduler, since another hart may run it at once.
+        trap_cx.x[10] = 0; //x[10] is a0 register
         drop(task_inner);
         insert_into_pid2process(child.getpid(), Arc::clone(&child));
 
@@ -3,10 +3,14 @@ use super::__switch;
 use super::task::TaskControlBlock;
 use super::{fetch_task, TaskStatus};
 use super::{ProcessControlBlock, TaskContext};
+use crate::config::MAX_HARTS;
+use crate::hart::hart_id;
 use crate::sync::UPSafeCell;
 use crate::trap::TrapContext;
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
+use core::hint::spin_loop;
 use lazy_static::*;
+use riscv::register::sstatus;
 
 /// Maintain CPU status
 pub struct Processor {
@@ -42,46 +46,76 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+    /// One `Processor` for each hart, indexed by hart id.
+    ///
+    /// Each of them is touched only by its own hart, so it needs no lock.
+    static ref PROCESSORS: Vec<UPSafeCell<Processor>> = (0..MAX_HARTS)
+        .map(|_| unsafe { UPSafeCell::new(Processor::new()) })
+        .collect();
+}
+
+/// Get the `Processor` of the current hart
+fn current_processor() -> &'static UPSafeCell<Processor> {
+    &PROCESSORS[hart_id()]
 }
 
 ///The main part of process execution and scheduling
 ///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
 pub fn run_tasks() {
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
         if let Some(task) = fetch_task() {
-            let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
             // access coming task TCB(TaskControlBlock) exclusively
             let mut task_inner = task.inner_exclusive_access();
+            // The task may have been woken up by another hart before the hart that ran it last
+            // finishes saving its context. Wait until the hart switches out of it.
+            while task_inner.on_cpu {
+                drop(task_inner);
+                spin_loop();
+                task_inner = task.inner_exclusive_access();
+            }
+            // killed while waiting in a queue(see `exit_current_and_run_next`)
+            if task_inner.res.is_none() {
+                continue;
+            }
             let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
             task_inner.task_status = TaskStatus::Running;
+            task_inner.on_cpu = true;
             // stop exclusively accessing coming task TCB manually
             //
             // Because it calls `__switch` before the automatic recall in the middle of the process,
             // so when it actually finishes accessing the task, it switches to the next task without requesting it again,
-            // which may violate UPSafeCell's borrowing rules and cause the kernel to exit with an error.
+            // which may keep the lock held and cause other harts to spin forever.
             drop(task_inner);
-            processor.current = Some(task);
+            let mut processor = current_processor().exclusive_access();
+            let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
+            processor.current = Some(Arc::clone(&task));
             // stop exclusively accessing processor manually
             drop(processor);
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+            // The task has switched back here and its context is saved.
+            // Holding `task` until here also keeps the kernel stack of an exited task alive.
+            task.inner_exclusive_access().on_cpu = false;
         } else {
-            println!("no tasks available in run_tasks");
+            // Nothing to run. Take interrupts here, so that timer interrupts wake up
+            // sleeping threads(see `trap::trap_from_kernel`).
+            unsafe {
+                sstatus::set_sie();
+            }
+            spin_loop();
         }
     }
 }
 
 ///Take the current task,leaving a None in its place
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
+    current_processor().exclusive_access().take_current()
 }
 
 ///Get running task
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().current()
+    current_processor().exclusive_access().current()
 }
 
 /// Determine the process to which it belongs from task(thread) and return a reference to it.
@@ -127,7 +161,7 @@ pub fn current_kstack_top() -> usize {
 
 /// Stops the task in the task context passed as the argument (currently running task) and switches to another task that is idle.
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    let mut processor = current_processor().exclusive_access();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
@@ -0,0 +1,182 @@
+//! Scheduling policies of [`super::manager::TaskManager`]
+//!
+//! The policy is selected at build time by a cargo feature.
+//! - `sched_stride` => `StrideScheduler`
+//! - `sched_priority` => `PriorityScheduler`
+//! - Otherwise => `FifoScheduler`
+//!
+//! Only the selected policy is compiled.
+use super::TaskControlBlock;
+#[cfg(not(feature = "sched_stride"))]
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+#[cfg(feature = "sched_stride")]
+use alloc::vec::Vec;
+
+/// Priority given to a new thread
+pub const DEFAULT_PRIORITY: usize = 16;
+/// Minimum priority accepted by `sys_set_priority`
+pub const MIN_PRIORITY: usize = 2;
+/// Stride of a thread whose priority is 1.
+/// The stride of each thread advances by `BIG_STRIDE / priority` every time it is scheduled.
+#[cfg(feature = "sched_stride")]
+const BIG_STRIDE: u64 = 1 << 20;
+
+/// Policy that decides which ready thread runs next
+pub trait Scheduler {
+    /// Add a ready thread
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    /// Remove the thread that runs next and return it, or `None` if there is no ready thread
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// Remove `task` if it is ready
+    fn remove(&mut self, task: &Arc<TaskControlBlock>);
+}
+
+#[cfg(feature = "sched_stride")]
+pub type SchedulerImpl = StrideScheduler;
+#[cfg(all(feature = "sched_priority", not(feature = "sched_stride")))]
+pub type SchedulerImpl = PriorityScheduler;
+#[cfg(not(any(feature = "sched_stride", feature = "sched_priority")))]
+pub type SchedulerImpl = FifoScheduler;
+
+/// A simple FIFO scheduler.
+#[cfg(not(any(feature = "sched_stride", feature = "sched_priority")))]
+pub struct FifoScheduler {
+    /// Array of references to prepared tasks
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+#[cfg(not(any(feature = "sched_stride", feature = "sched_priority")))]
+impl FifoScheduler {
+    /// Create an empty `FifoScheduler`
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+#[cfg(not(any(feature = "sched_stride", feature = "sched_priority")))]
+impl Scheduler for FifoScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready_queue.pop_front()
+    }
+
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        if let Some(id) = self.ready_queue.iter().position(|t| Arc::ptr_eq(t, task)) {
+            self.ready_queue.remove(id);
+        }
+    }
+}
+
+/// Stride scheduler
+///
+/// The thread with the smallest stride runs next, so the CPU time of each thread is
+/// proportional to its priority.
+#[cfg(feature = "sched_stride")]
+pub struct StrideScheduler {
+    /// Ready threads with their stride
+    ready_queue: Vec<(u64, Arc<TaskControlBlock>)>,
+    /// Stride of the thread fetched last.
+    ///
+    /// A thread added with a smaller stride(e.g. a new thread or one that has slept for a long time)
+    /// starts from here, so that it cannot monopolize the CPU.
+    min_stride: u64,
+}
+
+#[cfg(feature = "sched_stride")]
+impl StrideScheduler {
+    /// Create an empty `StrideScheduler`
+    pub fn new() -> Self {
+        Self {
+            ready_queue: Vec::new(),
+            min_stride: 0,
+        }
+    }
+}
+
+#[cfg(feature = "sched_stride")]
+impl Scheduler for StrideScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.stride = task_inner.stride.max(self.min_stride);
+        let stride = task_inner.stride;
+        drop(task_inner);
+        self.ready_queue.push((stride, task));
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        // the first one among the smallest, to keep FIFO order between equal strides
+        let (id, _) = self
+            .ready_queue
+            .iter()
+            .enumerate()
+            .min_by_key(|(id, (stride, _))| (*stride, *id))?;
+        let (stride, task) = self.ready_queue.remove(id);
+        self.min_stride = stride;
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.stride += BIG_STRIDE / task_inner.priority as u64;
+        drop(task_inner);
+        Some(task)
+    }
+
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        if let Some(id) = self
+            .ready_queue
+            .iter()
+            .position(|(_, t)| Arc::ptr_eq(t, task))
+        {
+            self.ready_queue.remove(id);
+        }
+    }
+}
+
+/// Static priority scheduler
+///
+/// The thread with the highest priority runs next. Threads of the same priority run in FIFO order.
+#[cfg(all(feature = "sched_priority", not(feature = "sched_stride")))]
+pub struct PriorityScheduler {
+    /// Ready threads with their priority
+    ready_queue: VecDeque<(usize, Arc<TaskControlBlock>)>,
+}
+
+#[cfg(all(feature = "sched_priority", not(feature = "sched_stride")))]
+impl PriorityScheduler {
+    /// Create an empty `PriorityScheduler`
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+#[cfg(all(feature = "sched_priority", not(feature = "sched_stride")))]
+impl Scheduler for PriorityScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let priority = task.inner_exclusive_access().priority;
+        self.ready_queue.push_back((priority, task));
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let (id, _) = self
+            .ready_queue
+            .iter()
+            .enumerate()
+            .max_by_key(|(id, (priority, _))| (*priority, core::cmp::Reverse(*id)))?;
+        self.ready_queue.remove(id).map(|(_, task)| task)
+    }
+
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        if let Some(id) = self
+            .ready_queue
+            .iter()
+            .position(|(_, t)| Arc::ptr_eq(t, task))
+        {
+            self.ready_queue.remove(id);
+        }
+    }
+}
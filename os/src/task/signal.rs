@@ -1,5 +1,8 @@
 use bitflags::*;
 
+/// Maximum bit digit of signal(`SIGSYS = 1 << 31`)
+pub const MAX_SIG: usize = 31;
+
 bitflags! {
     /// Signals
     /// - https://man7.org/linux/man-pages/man2/sigaction.2.html
@@ -64,24 +67,101 @@ bitflags! {
     }
 }
 
+/// What the kernel does with a signal that has no user handler.
+///
+/// - https://man7.org/linux/man-pages/man7/signal.7.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalDefaultAction {
+    /// Terminate the process.
+    Terminate,
+    /// Discard the signal.
+    Ignore,
+    /// Terminate the process and dump the context where it was interrupted.
+    CoreDump,
+    /// Stop all threads of the process.
+    Stop,
+    /// Continue the process if it is stopped.
+    Continue,
+}
+
 impl SignalFlags {
-    /// If the signal representing the error is in the current signals (self == SignalFlags) => return (- `signum`, description)
-    pub fn check_error(&self) -> Option<(i32, &'static str)> {
-        if self.contains(Self::SIGINT) {
-            Some((-2, "Killed, SIGINT=2"))
-        } else if self.contains(Self::SIGILL) {
-            Some((-4, "Illegal Instruction, SIGILL=4"))
-        } else if self.contains(Self::SIGABRT) {
-            Some((-6, "Aborted, SIGABRT=6"))
-        } else if self.contains(Self::SIGFPE) {
-            Some((-8, "Erroneous Arithmetic Operation, SIGFPE=8"))
-        } else if self.contains(Self::SIGKILL) {
-            Some((-9, "Killed, SIGKILL=9"))
-        } else if self.contains(Self::SIGSEGV) {
-            Some((-11, "Segmentation Fault, SIGSEGV=11"))
+    /// Default action of a single signal.
+    pub fn default_action(&self) -> SignalDefaultAction {
+        if self.intersects(
+            Self::SIGQUIT
+                | Self::SIGILL
+                | Self::SIGTRAP
+                | Self::SIGABRT
+                | Self::SIGBUS
+                | Self::SIGFPE
+                | Self::SIGSEGV
+                | Self::SIGXCPU
+                | Self::SIGXFSZ
+                | Self::SIGSYS,
+        ) {
+            SignalDefaultAction::CoreDump
+        } else if self.intersects(Self::SIGCHLD | Self::SIGURG | Self::SIGWINCH) {
+            SignalDefaultAction::Ignore
+        } else if self.intersects(Self::job_control_stop()) {
+            SignalDefaultAction::Stop
+        } else if self.contains(Self::SIGCONT) {
+            SignalDefaultAction::Continue
         } else {
-            //println!("[K] signalFlags check_error  {:?}", self);
-            None
+            SignalDefaultAction::Terminate
         }
     }
+
+    /// Signals whose default action is [`SignalDefaultAction::Stop`]
+    pub fn job_control_stop() -> Self {
+        Self::SIGSTOP | Self::SIGTSTP | Self::SIGTTIN | Self::SIGTTOU
+    }
+
+    /// Description of a single signal printed when it terminates a process.
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Self::SIGDEF => "Killed, SIGDEF=0",
+            Self::SIGHUP => "Hangup, SIGHUP=1",
+            Self::SIGINT => "Killed, SIGINT=2",
+            Self::SIGQUIT => "Quit, SIGQUIT=3",
+            Self::SIGILL => "Illegal Instruction, SIGILL=4",
+            Self::SIGTRAP => "Trace/Breakpoint Trap, SIGTRAP=5",
+            Self::SIGABRT => "Aborted, SIGABRT=6",
+            Self::SIGBUS => "Bus Error, SIGBUS=7",
+            Self::SIGFPE => "Erroneous Arithmetic Operation, SIGFPE=8",
+            Self::SIGKILL => "Killed, SIGKILL=9",
+            Self::SIGUSR1 => "User Defined Signal 1, SIGUSR1=10",
+            Self::SIGSEGV => "Segmentation Fault, SIGSEGV=11",
+            Self::SIGUSR2 => "User Defined Signal 2, SIGUSR2=12",
+            Self::SIGPIPE => "Broken Pipe, SIGPIPE=13",
+            Self::SIGALRM => "Alarm Clock, SIGALRM=14",
+            Self::SIGTERM => "Terminated, SIGTERM=15",
+            Self::SIGSTKFLT => "Stack Fault, SIGSTKFLT=16",
+            Self::SIGXCPU => "CPU Time Limit Exceeded, SIGXCPU=24",
+            Self::SIGXFSZ => "File Size Limit Exceeded, SIGXFSZ=25",
+            Self::SIGVTALRM => "Virtual Timer Expired, SIGVTALRM=26",
+            Self::SIGPROF => "Profiling Timer Expired, SIGPROF=27",
+            Self::SIGIO => "I/O Possible, SIGIO=29",
+            Self::SIGPWR => "Power Failure, SIGPWR=30",
+            Self::SIGSYS => "Bad System Call, SIGSYS=31",
+            _ => "Killed by signal",
+        }
+    }
+
+    /// If the signal representing the error is in the current signals (self == SignalFlags)
+    /// => return (- `signum`, description)
+    ///
+    /// Signals are checked in the order of their bit digits.
+    /// Only signals whose default action is to terminate the process are errors.
+    pub fn check_error(&self) -> Option<(i32, &'static str)> {
+        (0..=MAX_SIG)
+            .map(|sig| (sig, Self::from_bits_truncate(1 << sig)))
+            .find(|(_, signal)| {
+                self.contains(*signal)
+                    && matches!(
+                        signal.default_action(),
+                        SignalDefaultAction::Terminate | SignalDefaultAction::CoreDump
+                    )
+            })
+            .map(|(sig, signal)| (-(sig as i32), signal.description()))
+    }
 }
@@ -1,10 +1,11 @@
 //! Types related to task management
 use super::id::TaskUserRes;
+use super::scheduler::DEFAULT_PRIORITY;
 use super::{kstack_alloc, KernelStack, ProcessControlBlock, TaskContext};
+use crate::mm::PhysPageNum;
+use crate::sync::{SpinLock, SpinLockGuard};
 use crate::trap::TrapContext;
-use crate::{mm::PhysPageNum, sync::UPSafeCell};
 use alloc::sync::{Arc, Weak};
-use core::cell::RefMut;
 
 /// A structure of the components of a single thread task
 pub struct TaskControlBlock {
@@ -16,12 +17,12 @@ pub struct TaskControlBlock {
 
     // - mutable
     /// Mutable information of the thread
-    inner: UPSafeCell<TaskControlBlockInner>,
+    inner: SpinLock<TaskControlBlockInner>,
 }
 
 impl TaskControlBlock {
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
-        self.inner.exclusive_access()
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, TaskControlBlockInner> {
+        self.inner.lock()
     }
 
     /// Get page table of the root node(user token) of the process to which this thread belongs
@@ -42,8 +43,24 @@ pub struct TaskControlBlockInner {
     pub task_cx: TaskContext,
     /// Current thread execution status
     pub task_status: TaskStatus,
+    /// Is a hart running on the kernel stack of this thread?
+    ///
+    /// It is cleared after the thread is switched out, so that no other hart resumes
+    /// the thread before its task context is saved(see `run_tasks`).
+    pub on_cpu: bool,
     /// Thread exit code(Number indicating the state of the thread when it is finished.)
     pub exit_code: Option<i32>,
+    /// Scheduling priority(>= 2). The larger, the more CPU time is given.
+    pub priority: usize,
+    /// Accumulated stride used by the stride scheduler
+    pub stride: u64,
+    /// Bit digit of the signal whose user handler is running on this thread.
+    /// - -1 => no handler is running
+    pub handling_sig: isize,
+    /// Trap context of this thread interrupted by the running signal handler.
+    ///
+    /// Restored by `sigreturn`.
+    pub trap_ctx_backup: Option<TrapContext>,
 }
 
 impl TaskControlBlockInner {
@@ -88,23 +105,28 @@ impl TaskControlBlock {
         Self {
             process: Arc::downgrade(&process),
             kstack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    res: Some(res),
-                    trap_cx_ppn,
-                    task_cx: TaskContext::goto_trap_return(kstack_top),
-                    task_status: TaskStatus::Ready,
-                    exit_code: None,
-                })
-            },
+            inner: SpinLock::new(TaskControlBlockInner {
+                res: Some(res),
+                trap_cx_ppn,
+                task_cx: TaskContext::goto_trap_return(kstack_top),
+                task_status: TaskStatus::Ready,
+                on_cpu: false,
+                exit_code: None,
+                priority: DEFAULT_PRIORITY,
+                stride: 0,
+                handling_sig: -1,
+                trap_ctx_backup: None,
+            }),
         }
     }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
-/// task status: Ready/Running/Blocking
+/// task status: Ready/Running/Blocking/Stopped
 pub enum TaskStatus {
     Ready,
     Running,
     Blocking,
+    /// Stopped by a job control signal(e.g. `SIGSTOP`) until `SIGCONT` is received
+    Stopped,
 }
@@ -2,12 +2,22 @@
 
 use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
+use crate::sync::SpinLock;
+use crate::task::{add_task, TaskControlBlock};
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use lazy_static::*;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
 /// Pre-set clock frequency (Hertz) for each platform,
 /// i.e., time interval for incrementing the counter in 1 second
 const MSEC_PER_SEC: usize = 1000;
+const USEC_PER_SEC: usize = 1_000_000;
+const NSEC_PER_SEC: usize = 1_000_000_000;
+/// Interval of timer interrupts in microseconds
+pub const USEC_PER_TICK: usize = USEC_PER_SEC / TICKS_PER_SEC;
 
 /// read the `mtime` register
 pub fn get_time() -> usize {
@@ -19,7 +29,223 @@ pub fn get_time_ms() -> usize {
     get_time() / (CLOCK_FREQ / MSEC_PER_SEC)
 }
 
+/// get current time in microseconds
+pub fn get_time_us() -> usize {
+    let time = get_time();
+    // split to avoid overflow of `time * USEC_PER_SEC`
+    time / CLOCK_FREQ * USEC_PER_SEC + time % CLOCK_FREQ * USEC_PER_SEC / CLOCK_FREQ
+}
+
+/// get current time in nanoseconds since boot
+pub fn get_time_ns() -> usize {
+    let time = get_time();
+    // split to avoid overflow of `time * NSEC_PER_SEC`
+    time / CLOCK_FREQ * NSEC_PER_SEC + time % CLOCK_FREQ * NSEC_PER_SEC / CLOCK_FREQ
+}
+
 /// set the next timer interrupt
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
+
+/// A thread waiting in the timer queue until `expire_ms`
+pub struct TimerCondVar {
+    /// Time in milliseconds when the thread is woken up
+    pub expire_ms: usize,
+    /// Waiting thread
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for TimerCondVar {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire_ms == other.expire_ms
+    }
+}
+impl Eq for TimerCondVar {}
+impl PartialOrd for TimerCondVar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerCondVar {
+    /// Reversed, so that `BinaryHeap`(max-heap) pops the earliest deadline first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire_ms.cmp(&self.expire_ms)
+    }
+}
+
+lazy_static! {
+    /// Timer queue ordered by deadline
+    static ref TIMERS: SpinLock<BinaryHeap<TimerCondVar>> =
+        SpinLock::new(BinaryHeap::<TimerCondVar>::new());
+}
+
+/// Put `task` in the timer queue until `expire_ms`.
+///
+/// The caller is responsible for blocking `task`(e.g. `block_current_and_run_next`).
+pub fn add_timer(expire_ms: usize, task: Arc<TaskControlBlock>) {
+    let mut timers = TIMERS.lock();
+    timers.push(TimerCondVar { expire_ms, task });
+}
+
+/// Remove all timers of `task` from the timer queue.
+pub fn remove_timer(task: Arc<TaskControlBlock>) {
+    let mut timers = TIMERS.lock();
+    let remaining: BinaryHeap<TimerCondVar> = timers
+        .drain()
+        .filter(|timer| !Arc::ptr_eq(&timer.task, &task))
+        .collect();
+    *timers = remaining;
+}
+
+/// Wake up `task` before its deadline if it is in the timer queue.
+///
+/// # Return
+/// Conditional branching.
+/// - If `task` was waiting for a timer => true
+/// - Otherwise => false
+pub fn wakeup_timer(task: &Arc<TaskControlBlock>) -> bool {
+    let mut timers = TIMERS.lock();
+    let count = timers.len();
+    let remaining: BinaryHeap<TimerCondVar> = timers
+        .drain()
+        .filter(|timer| !Arc::ptr_eq(&timer.task, task))
+        .collect();
+    let found = remaining.len() != count;
+    *timers = remaining;
+    drop(timers);
+    if found {
+        add_task(Arc::clone(task));
+    }
+    found
+}
+
+/// Wake up all threads whose deadline has passed.
+///
+/// Called on every timer interrupt.
+pub fn check_timer() {
+    let current_ms = get_time_ms();
+    let mut timers = TIMERS.lock();
+    while let Some(timer) = timers.peek() {
+        if timer.expire_ms > current_ms {
+            break;
+        }
+        add_task(Arc::clone(&timer.task));
+        timers.pop();
+    }
+}
+
+/// Wall-clock time since the UNIX epoch, read from the RTC
+pub const CLOCK_REALTIME: usize = 0;
+/// Time since boot, read from the `time` CSR. It never goes back.
+pub const CLOCK_MONOTONIC: usize = 1;
+
+/// Time value of `clock_gettime`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSpec {
+    /// seconds
+    pub sec: usize,
+    /// nanoseconds
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    /// Create from nanoseconds
+    pub fn from_ns(ns: usize) -> Self {
+        Self {
+            sec: ns / NSEC_PER_SEC,
+            nsec: ns % NSEC_PER_SEC,
+        }
+    }
+}
+
+/// Time value of `getitimer`/`setitimer`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeVal {
+    /// seconds
+    pub sec: usize,
+    /// microseconds
+    pub usec: usize,
+}
+
+impl TimeVal {
+    /// Create from microseconds
+    pub fn from_us(us: usize) -> Self {
+        Self {
+            sec: us / USEC_PER_SEC,
+            usec: us % USEC_PER_SEC,
+        }
+    }
+
+    /// Convert to microseconds
+    pub fn as_us(&self) -> usize {
+        self.sec * USEC_PER_SEC + self.usec
+    }
+}
+
+/// Value of an interval timer passed to `getitimer`/`setitimer`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimerVal {
+    /// Value reloaded when the timer expires (zero => one-shot)
+    pub it_interval: TimeVal,
+    /// Time until the next expiration (zero => disarmed)
+    pub it_value: TimeVal,
+}
+
+/// Interval timer decremented in real time, delivers `SIGALRM`
+pub const ITIMER_REAL: usize = 0;
+/// Interval timer decremented in user CPU time, delivers `SIGVTALRM`
+pub const ITIMER_VIRTUAL: usize = 1;
+/// Interval timer decremented in user and kernel CPU time, delivers `SIGPROF`
+pub const ITIMER_PROF: usize = 2;
+
+/// Interval timer of a process
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimer {
+    /// Value reloaded when the timer expires in microseconds
+    /// - 0 => the timer is disarmed after it expires
+    pub interval_us: usize,
+    /// Conditional branching.
+    /// - `ITIMER_REAL` => deadline in microseconds(compared with [`get_time_us`])
+    /// - `ITIMER_VIRTUAL`/`ITIMER_PROF` => remaining CPU time in microseconds
+    /// - 0 => disarmed
+    pub value_us: usize,
+}
+
+impl ITimer {
+    /// Check the deadline of a real time timer and reload it if it has expired.
+    ///
+    /// # Return
+    /// Is the timer expired?
+    pub fn expire_real(&mut self, now_us: usize) -> bool {
+        if self.value_us == 0 || self.value_us > now_us {
+            return false;
+        }
+        self.value_us = if self.interval_us == 0 {
+            0
+        } else {
+            now_us + self.interval_us
+        };
+        true
+    }
+
+    /// Decrement a CPU time timer by `elapsed_us` and reload it if it has expired.
+    ///
+    /// # Return
+    /// Is the timer expired?
+    pub fn expire_cpu(&mut self, elapsed_us: usize) -> bool {
+        if self.value_us == 0 {
+            return false;
+        }
+        if self.value_us > elapsed_us {
+            self.value_us -= elapsed_us;
+            return false;
+        }
+        self.value_us = self.interval_us;
+        true
+    }
+}
@@ -31,6 +31,10 @@ pub struct TrapContext {
     pub kernel_sp: usize,
     /// Addr of trap_handler function
     pub trap_handler: usize,
+    /// Id of the hart that returned to user mode with this context.
+    ///
+    /// `__alltraps` loads it into `tp` for the kernel.
+    pub kernel_tp: usize,
 }
 
 impl TrapContext {
@@ -68,6 +72,7 @@ impl TrapContext {
             kernel_satp,  // addr of page table
             kernel_sp,    // kernel stack
             trap_handler, // addr of trap_handler function
+            kernel_tp: 0, // set by `__restore`
         };
         cx.set_sp(sp); // app's user stack pointer
         cx // return initial Trap Context of app
@@ -11,20 +11,27 @@
 //! It then calls different functionality based on what exactly the exception
 //! was. For example, timer interrupts trigger task preemption, and syscalls go
 //! to [`syscall()`].
+//!
+//! Traps from the kernel itself go through `__kerneltrap`, which saves the registers
+//! on the kernel stack and calls [`trap_from_kernel()`]. Interrupts are enabled while the kernel
+//! handles a trap from user space, but the kernel is not preempted: a timer interrupt in the kernel
+//! only wakes up threads and accounts CPU time.
 mod context;
 
 use crate::config::TRAMPOLINE;
 use crate::syscall::syscall;
 use crate::task::{
-    check_signals_of_current, current_add_signal, current_trap_cx, current_trap_cx_user_va,
-    current_user_token, exit_current_and_run_next, suspend_current_and_run_next, SignalFlags,
+    account_tick_of_current, check_itimers_real, check_signals_of_current, current_add_signal,
+    current_task, current_trap_cx, current_trap_cx_user_va, current_user_token,
+    exit_current_and_run_next, handle_signals, is_current_stopped, kernel_stack_guard_of,
+    stop_current_and_run_next, suspend_current_and_run_next, SignalFlags,
 };
-use crate::timer::set_next_trigger;
+use crate::timer::{check_timer, set_next_trigger};
 use core::arch::{asm, global_asm};
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sepc, sie, sstatus, stval, stvec,
 };
 
 global_asm!(include_str!("trap.S"));
@@ -34,12 +41,19 @@ pub fn init() {
     set_kernel_trap_entry();
 }
 
-/// Write the `trap_from_kernel` address to the stvec(supervisor trap vector) register.
+/// Write the `__kerneltrap` address to the stvec(supervisor trap vector) register.
 ///
 /// For horizontal trap(S-state -> S-state)
 fn set_kernel_trap_entry() {
+    extern "C" {
+        /// Save the registers on the current kernel stack, call `trap_from_kernel`
+        /// and restore them.
+        ///
+        /// (This symbol is defined in "trap.S")
+        fn __kerneltrap();
+    }
     unsafe {
-        stvec::write(trap_from_kernel as usize, TrapMode::Direct);
+        stvec::write(__kerneltrap as usize, TrapMode::Direct);
     }
 }
 
@@ -54,13 +68,20 @@ fn set_user_trap_entry() {
     }
 }
 
-/// timer interrupt enabled
+/// timer interrupt, software interrupt(IPI) and external interrupt enabled
+///
+/// They are taken in user mode, or in the kernel while `sstatus.SIE` is set.
 pub fn enable_timer_interrupt() {
     unsafe {
         sie::set_stimer();
+        sie::set_ssoft();
+        sie::set_sext();
     }
 }
 
+/// Supervisor software interrupt pending bit of `sip`
+const SIP_SSIP: usize = 1 << 1;
+
 /// handle an interrupt, exception, or system call from user space
 /// Print trap exception.
 ///
@@ -68,26 +89,33 @@ pub fn enable_timer_interrupt() {
 #[no_mangle]
 pub fn trap_handler() -> ! {
     // If the S-state trap occurs again after entering the kernel,
-    // the hardware skips the general-purpose register save process and jumps
-    // to the trap_from_kernel function after setting some CSR registers, where it directly exits the panic.
+    // the hardware jumps to `__kerneltrap`, which saves the registers on the kernel stack
+    // instead of the Trap context.
     //
     // This is because the logic for saving and recovering the Trap context
     // is different for U-state→S-state and S-state→S-state
     // after the kernel and application address spaces are separated.
-    // For simplicity, the S-state→S-state Trap process is weakened here, making it a straight panic.
     set_kernel_trap_entry();
     // Since the application's Trap context is not in the kernel address space,
     // call current_trap_cx to get a mutable reference to the current application's Trap context
     // instead of passing it as an argument to trap_handler as before.
     let scause = scause::read(); // get trap cause;
     let stval = stval::read(); // get extra value
+
+    // `sepc` of the application has been saved in the Trap context, so nested traps are harmless now.
+    unsafe {
+        sstatus::set_sie();
+    }
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             // jump to next instruction anyway
             let mut cx = current_trap_cx();
             cx.sepc += 4;
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            let result = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            ) as usize;
             // cx is changed during sys_exec, so we have to call it again
             cx = current_trap_cx();
             cx.x[10] = result as usize;
@@ -108,8 +136,20 @@ pub fn trap_handler() -> ! {
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
+            check_timer();
+            // the tick interrupted user mode
+            account_tick_of_current(true);
+            check_itimers_real();
             suspend_current_and_run_next();
         }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            // IPI from another hart(see `hart::kick_other_harts`).
+            // Nothing to do but checking the signals below.
+            clear_soft_interrupt();
+        }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            crate::board::irq_handler();
+        }
         _ => {
             panic!(
                 "Unsupported trap {:?}, stval = {:#x}!",
@@ -119,10 +159,22 @@ pub fn trap_handler() -> ! {
         }
     }
 
-    // check error signals (if error then exit)
-    if let Some((errno, msg)) = check_signals_of_current() {
-        println!("[kernel] {}", msg);
-        exit_current_and_run_next(errno);
+    loop {
+        // jump to the user handler if a caught signal is pending
+        handle_signals();
+
+        // check error signals (if error then exit)
+        if let Some((errno, msg)) = check_signals_of_current() {
+            println!("[kernel] {}", msg);
+            exit_current_and_run_next(errno);
+        }
+
+        // stay here while the process is stopped, then check the signals again
+        // since `SIGKILL` also continues a stopped process.
+        if !is_current_stopped() {
+            break;
+        }
+        stop_current_and_run_next();
     }
 
     // After processing the trap, call and return the user status.
@@ -134,6 +186,11 @@ pub fn trap_handler() -> ! {
 /// set the reg a0 = trap_cx_ptr, reg a1 = phy addr of usr page table,
 /// finally, jump to new addr of __restore asm function
 pub fn trap_return() -> ! {
+    // Interrupts must not be taken in the kernel from here,
+    // since stvec points to the user trap entry. `sret` enables them in user mode.
+    unsafe {
+        sstatus::clear_sie();
+    }
     // We allow applications to jump to `__alltraps` when trapping to Supervisor state.
     set_user_trap_entry();
     let trap_cx_user_va = current_trap_cx_user_va();
@@ -198,13 +255,95 @@ pub fn trap_return() -> ! {
     }
 }
 
+/// Clear the pending bit of the supervisor software interrupt(IPI)
+fn clear_soft_interrupt() {
+    unsafe {
+        asm!("csrc sip, {}", in(reg) SIP_SSIP);
+    }
+}
+
+#[no_mangle]
+/// Handle a trap from kernel mode.
+///
+/// It is called by `__kerneltrap` in `trap.S` with interrupts disabled, and returns to
+/// the interrupted kernel code. The current task is never switched here.
+///
+/// # Panics
+/// Exceptions in the kernel(e.g. page faults) are bugs, so it panics with a diagnostic.
+pub fn trap_from_kernel() {
+    let scause = scause::read();
+    let stval = stval::read();
+    match scause.cause() {
+        Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            set_next_trigger();
+            check_timer();
+            // the tick interrupted the kernel working for the current thread, or the idle loop
+            if current_task().is_some() {
+                account_tick_of_current(false);
+            }
+            check_itimers_real();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            // The signals are checked when the current thread returns to user mode.
+            clear_soft_interrupt();
+        }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            crate::board::irq_handler();
+        }
+        Trap::Exception(Exception::StoreFault)
+        | Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::InstructionFault)
+        | Trap::Exception(Exception::InstructionPageFault)
+        | Trap::Exception(Exception::LoadFault)
+        | Trap::Exception(Exception::LoadPageFault) => {
+            println!(
+                "[kernel] {:?} in kernel, bad addr = {:#x}, bad instruction = {:#x}",
+                scause.cause(),
+                stval,
+                sepc::read()
+            );
+            if let Some(kstack_id) = kernel_stack_guard_of(stval) {
+                println!(
+                    "[kernel] bad addr is in the guard pages of kernel stack {}(stack overflow?)",
+                    kstack_id
+                );
+            }
+            panic!("page fault in kernel!");
+        }
+        _ => {
+            panic!(
+                "Unsupported trap {:?} from kernel, stval = {:#x}, sepc = {:#x}!",
+                scause.cause(),
+                stval,
+                sepc::read()
+            );
+        }
+    }
+}
+
 #[no_mangle]
-/// Unimplemented: traps/interrupts/exceptions from kernel mode
-/// Todo: Chapter 9: I/O device
-pub fn trap_from_kernel() -> ! {
-    use riscv::register::sepc;
-    println!("stval = {:#x}, sepc = {:#x}", stval::read(), sepc::read());
-    todo!("a trap {:?} from kernel!", scause::read().cause());
+/// Report an overflow of a kernel stack detected by `__kerneltrap` in `trap.S`.
+///
+/// It runs on the emergency stack of the current hart, since the kernel stack is unusable.
+///
+/// # Parameters
+/// - `sp`: stack pointer when the trap occurred
+pub fn kernel_stack_overflow(sp: usize) -> ! {
+    println!(
+        "[kernel] kernel stack overflow: {:?}, sp = {:#x}, stval = {:#x}, sepc = {:#x}",
+        scause::read().cause(),
+        sp,
+        stval::read(),
+        sepc::read()
+    );
+    // `__kerneltrap` found that the trap frame below sp would be in the guard pages
+    if let Some(kstack_id) = kernel_stack_guard_of(sp.wrapping_sub(34 * 8)) {
+        println!(
+            "[kernel] sp reached the guard pages of kernel stack {}",
+            kstack_id
+        );
+    }
+    panic!("kernel stack overflow!");
 }
 
 pub use context::TrapContext;
@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{gettimeofday, sleep, Duration, Instant, SystemTime, TimeVal};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = Instant::now();
+    sleep(100);
+    let elapsed = start.elapsed();
+    println!("clock_test: slept {} us", elapsed.as_micros());
+    assert!(elapsed >= Duration::from_millis(100));
+
+    let now = SystemTime::now().since_unix_epoch();
+    println!("clock_test: {} s since the UNIX epoch", now.as_secs());
+    // 2020-01-01 00:00:00 UTC
+    assert!(now.as_secs() > 1_577_836_800);
+
+    let mut tv = TimeVal::default();
+    assert_eq!(gettimeofday(&mut tv), 0);
+    assert!(tv.sec >= now.as_secs() as usize);
+    println!("clock_test passed!");
+    0
+}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, mkdir, mkdirat, open, openat, read, rmdir, unlinkat, write, OpenFlags, AT_REMOVEDIR,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, directory!";
+    assert_eq!(mkdir("dir_test\0"), 0);
+    assert_eq!(mkdir("dir_test\0"), -1);
+    // no parent
+    assert_eq!(mkdir("no_such_dir/sub\0"), -1);
+
+    let fd = open("dir_test/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    write(fd, test_str.as_bytes());
+    close(fd);
+
+    // relative to a directory fd
+    let dirfd = open("/dir_test\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    assert!(dirfd > 0);
+    let fd = openat(dirfd, "file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut buffer = [0u8; 100];
+    let read_len = read(fd, &mut buffer) as usize;
+    assert_eq!(test_str, core::str::from_utf8(&buffer[..read_len]).unwrap());
+    // a file is not a directory
+    assert_eq!(openat(fd as isize, "file\0", OpenFlags::RDONLY), -1);
+    assert_eq!(open("dir_test/file\0", OpenFlags::DIRECTORY), -1);
+    close(fd);
+    // a rejected open does not clear the file, and neither does `CREATE` without `TRUNC`
+    assert_eq!(
+        open(
+            "dir_test/file\0",
+            OpenFlags::WRONLY | OpenFlags::TRUNC | OpenFlags::DIRECTORY
+        ),
+        -1
+    );
+    assert_eq!(
+        open("dir_test/file\0", OpenFlags::RDONLY | OpenFlags::TRUNC),
+        -1
+    );
+    let fd = open("dir_test/file\0", OpenFlags::CREATE | OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let read_len = read(fd as usize, &mut buffer) as usize;
+    assert_eq!(test_str, core::str::from_utf8(&buffer[..read_len]).unwrap());
+    close(fd as usize);
+
+    mkdirat(dirfd, "sub\0");
+    assert_eq!(mkdirat(dirfd, "sub\0"), -1);
+    assert_eq!(unlinkat(dirfd, "sub\0", 0), -1);
+    // `.` and `..`
+    let fd = open("/dir_test/sub/../sub/./../file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    let fd = openat(dirfd, "../dir_test/sub\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    // a directory cannot be written
+    assert_eq!(open("dir_test\0", OpenFlags::WRONLY), -1);
+    // clean up
+    assert_eq!(unlinkat(dirfd, "sub\0", AT_REMOVEDIR), 0);
+    assert_eq!(unlinkat(dirfd, "file\0", 0), 0);
+    close(dirfd as usize);
+    assert_eq!(rmdir("dir_test\0"), 0);
+    println!("dir_test passed!");
+    0
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, mkdir, open, pwrite, rmdir, write, OpenFlags, Stat};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, directory!";
+    assert_eq!(mkdir("dir_write_test\0"), 0);
+    let dirfd = open("dir_write_test\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    assert!(dirfd > 0);
+    let dirfd = dirfd as usize;
+    let mut stat = Stat::default();
+    assert_eq!(fstat(dirfd, &mut stat), 0);
+    let size = stat.size;
+
+    // a directory is written only through its entries
+    assert_eq!(write(dirfd, test_str.as_bytes()), -1);
+    assert_eq!(pwrite(dirfd, test_str.as_bytes(), 0), -1);
+    assert_eq!(fstat(dirfd, &mut stat), 0);
+    assert_eq!(stat.size, size);
+    // neither is a file opened only for reading(stdin)
+    assert_eq!(write(0, test_str.as_bytes()), -1);
+
+    close(dirfd);
+    assert_eq!(rmdir("dir_write_test\0"), 0);
+    println!("dir_write_test passed!");
+    0
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{
+    close, getdents64, lseek, mkdir, open, opendir, readdir, rmdir, stat, symlink, unlink,
+    DirEntry, OpenFlags, Stat, DT_DIR, DT_LNK, DT_REG, SEEK_SET,
+};
+
+/// Number of files created, more than a buffer of `Dir` holds
+const FILES: usize = 40;
+
+fn file_name(i: usize) -> String {
+    format!("getdents_test/file{}\0", i)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir("getdents_test\0"), 0);
+    assert_eq!(mkdir("getdents_test/sub\0"), 0);
+    assert_eq!(symlink("sub\0", "getdents_test/link\0"), 0);
+    for i in 0..FILES {
+        let fd = open(file_name(i).as_str(), OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd > 0);
+        close(fd as usize);
+    }
+    // a removed entry is skipped
+    assert_eq!(unlink(file_name(0).as_str()), 0);
+
+    let entries: Vec<DirEntry> = opendir("getdents_test\0").unwrap().collect();
+    assert_eq!(entries.len(), 2 + 2 + FILES - 1);
+    let find = |name: &str| entries.iter().find(|entry| entry.name == name).unwrap();
+    let mut st = Stat::default();
+    assert_eq!(find(".").file_type, DT_DIR);
+    assert_eq!(stat("getdents_test\0", &mut st), 0);
+    assert_eq!(find(".").ino, st.ino);
+    assert_eq!(find("..").file_type, DT_DIR);
+    assert_eq!(find("sub").file_type, DT_DIR);
+    assert_eq!(find("link").file_type, DT_LNK);
+    for i in 1..FILES {
+        let name = format!("file{}", i);
+        assert_eq!(find(name.as_str()).file_type, DT_REG);
+    }
+    assert!(entries.iter().all(|entry| entry.name != "file0"));
+
+    // the listing resumes across calls and rewinds with `lseek`
+    let fd = open("getdents_test\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut buffer = [0u8; 64];
+    let mut total = 0;
+    loop {
+        let len = getdents64(fd, &mut buffer);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        total += len;
+    }
+    assert!(total > 0);
+    assert_eq!(getdents64(fd, &mut buffer), 0);
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert!(getdents64(fd, &mut buffer) > 0);
+    // too small for a record
+    assert_eq!(getdents64(fd, &mut buffer[..8]), -1);
+    close(fd);
+
+    // not a directory
+    let fd = open(file_name(1).as_str(), OpenFlags::RDONLY);
+    assert_eq!(getdents64(fd as usize, &mut buffer), -1);
+    close(fd as usize);
+    assert!(opendir(file_name(1).as_str()).is_none());
+
+    // `readdir` by hand
+    let mut dir = opendir("getdents_test/sub\0").unwrap();
+    let mut names = Vec::new();
+    while let Some(entry) = readdir(&mut dir) {
+        names.push(entry.name);
+    }
+    assert_eq!(names, [".", ".."]);
+    drop(dir);
+
+    // clean up
+    for i in 1..FILES {
+        assert_eq!(unlink(file_name(i).as_str()), 0);
+    }
+    assert_eq!(unlink("getdents_test/link\0"), 0);
+    assert_eq!(rmdir("getdents_test/sub\0"), 0);
+    assert_eq!(rmdir("getdents_test\0"), 0);
+    println!("getdents_test passed!");
+    0
+}
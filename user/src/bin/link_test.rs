@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, link, mkdir, open, read, rename, rmdir, unlink, write, OpenFlags};
+
+fn read_str<'a>(path: &str, buffer: &'a mut [u8]) -> &'a str {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let len = read(fd, buffer) as usize;
+    close(fd);
+    core::str::from_utf8(&buffer[..len]).unwrap()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, link!";
+    let mut buffer = [0u8; 64];
+    assert_eq!(mkdir("link_test\0"), 0);
+    let fd = open("link_test/a\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    // two names of one file
+    assert_eq!(link("link_test/a\0", "link_test/b\0"), 0);
+    assert_eq!(link("link_test/a\0", "link_test/b\0"), -1);
+    assert_eq!(link("link_test\0", "link_test/c\0"), -1);
+    assert_eq!(unlink("link_test/a\0"), 0);
+    assert_eq!(read_str("link_test/b\0", &mut buffer), test_str);
+
+    // an open file survives the removal of its last name
+    let fd = open("link_test/b\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(unlink("link_test/b\0"), 0);
+    assert_eq!(open("link_test/b\0", OpenFlags::RDONLY), -1);
+    let len = read(fd as usize, &mut buffer) as usize;
+    assert_eq!(core::str::from_utf8(&buffer[..len]).unwrap(), test_str);
+    close(fd as usize);
+
+    // rename a file, replacing another one
+    for name in ["link_test/c\0", "link_test/d\0"] {
+        let fd = open(name, OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd > 0);
+        write(fd as usize, name.as_bytes());
+        close(fd as usize);
+    }
+    assert_eq!(rename("link_test/c\0", "link_test/d\0"), 0);
+    assert_eq!(open("link_test/c\0", OpenFlags::RDONLY), -1);
+    assert_eq!(read_str("link_test/d\0", &mut buffer), "link_test/c\0");
+
+    // move a directory
+    assert_eq!(mkdir("link_test/e\0"), 0);
+    assert_eq!(rename("link_test/d\0", "link_test/e/d\0"), 0);
+    assert_eq!(rename("link_test/e\0", "link_test/e/f\0"), -1);
+    assert_eq!(rename("link_test/e/d\0", "link_test/e\0"), -1);
+    assert_eq!(rename("link_test/e\0", "link_test_e\0"), 0);
+    assert_eq!(
+        read_str("link_test_e/../link_test_e/d\0", &mut buffer),
+        "link_test/c\0"
+    );
+
+    // clean up
+    assert_eq!(unlink("link_test_e/d\0"), 0);
+    assert_eq!(rmdir("link_test_e\0"), 0);
+    assert_eq!(rmdir("link_test\0"), 0);
+    println!("link_test passed!");
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{opendir, DT_DIR, DT_LNK};
+
+#[no_mangle]
+/// Lists the entries of the directory specified by the command argument(the root directory by default).
+///
+/// A directory is followed by `/` and a symbolic link by `@`.
+///
+/// # Command usage
+/// ls \[dirName\]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    let path = if argc >= 2 { argv[1] } else { "/\0" };
+    let dir = match opendir(path) {
+        Some(dir) => dir,
+        None => {
+            println!("ls: cannot open directory");
+            return -1;
+        }
+    };
+    for entry in dir {
+        if entry.name == "." || entry.name == ".." {
+            continue;
+        }
+        let suffix = match entry.file_type {
+            DT_DIR => "/",
+            DT_LNK => "@",
+            _ => "",
+        };
+        println!("{}{}", entry.name, suffix);
+    }
+    0
+}
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, getpgid, getpid, getsid, kill, setpgid, setsid, tcgetpgrp, waitpid, yield_,
+    SignalFlags,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let sid = getsid(0);
+    assert!(sid >= 0);
+    assert!(getpgid(0) >= 0);
+
+    // two children in a new process group led by the first one
+    let leader = fork();
+    if leader == 0 {
+        setpgid(0, 0);
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(setpgid(leader as usize, leader as usize), 0);
+    let member = fork();
+    if member == 0 {
+        setpgid(0, leader as usize);
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(setpgid(member as usize, leader as usize), 0);
+    assert_eq!(getpgid(leader as usize), leader);
+    assert_eq!(getpgid(member as usize), leader);
+    assert_eq!(getsid(member as usize), sid);
+    // no such group in the session
+    assert_eq!(setpgid(member as usize, getpid() as usize + 1000), -1);
+    println!("pgrp_test: group {} created", leader);
+
+    // kill the whole group
+    let sigkill = SignalFlags::to_bit_digit(SignalFlags::SIGKILL) as i32;
+    assert_eq!(kill(-leader, 0), 0);
+    assert_eq!(kill(-leader, sigkill), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(leader as usize, &mut exit_code), leader);
+    assert_eq!(exit_code, -sigkill);
+    assert_eq!(waitpid(member as usize, &mut exit_code), member);
+    assert_eq!(exit_code, -sigkill);
+    assert_eq!(kill(-leader, 0), -1);
+    assert_eq!(kill(-1, 0), -1);
+
+    // a new session has no controlling terminal
+    let pid = fork();
+    if pid == 0 {
+        let pid = getpid();
+        assert_eq!(setsid(), pid);
+        assert_eq!(getsid(0), pid);
+        assert_eq!(getpgid(0), pid);
+        // a process group leader cannot create a session
+        assert_eq!(setsid(), -1);
+        assert_eq!(tcgetpgrp(0), -1);
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(getsid(0), sid);
+    println!("pgrp_test passed!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, set_priority, waitpid};
+
+/// How long each child counts, in milliseconds
+const RUN_MS: isize = 300;
+
+/// Read the clock as often as possible for `RUN_MS` and return how many times it was read.
+fn count() -> i32 {
+    let start = get_time();
+    let mut count = 0;
+    while get_time() - start < RUN_MS {
+        count += 1;
+    }
+    count
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(set_priority(0), -1);
+    assert_eq!(set_priority(1), -1);
+    assert_eq!(set_priority(2), 2);
+    assert_eq!(set_priority(16), 16);
+
+    // Children of different priorities compete for the CPU.
+    // How the CPU time is shared depends on the scheduling policy of the kernel.
+    let priorities = [2, 4, 8];
+    let mut pids = [0isize; 3];
+    for (i, &priority) in priorities.iter().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            assert_eq!(set_priority(priority), priority);
+            exit(count());
+        }
+        pids[i] = pid;
+    }
+    for (&pid, &priority) in pids.iter().zip(priorities.iter()) {
+        let mut exit_code: i32 = 0;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert!(exit_code >= 0);
+        println!(
+            "priority_test: priority {} counted {} times",
+            priority, exit_code
+        );
+    }
+    println!("priority_test passed!");
+    0
+}
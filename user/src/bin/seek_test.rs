@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, lseek, open, pipe, pread, pwrite, read, unlink, write, OpenFlags, ESPIPE, SEEK_CUR,
+    SEEK_END, SEEK_SET,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open("seek_test\0", OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"0123456789"), 10);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 10);
+    assert_eq!(lseek(fd, 0, SEEK_END), 10);
+
+    // re-read from the start
+    let mut buffer = [0u8; 16];
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buffer[..4]), 4);
+    assert_eq!(&buffer[..4], b"0123");
+    assert_eq!(lseek(fd, 2, SEEK_CUR), 6);
+    assert_eq!(read(fd, &mut buffer), 4);
+    assert_eq!(&buffer[..4], b"6789");
+    assert_eq!(lseek(fd, -3, SEEK_END), 7);
+    assert_eq!(write(fd, b"abc"), 3);
+    // invalid offset and whence
+    assert_eq!(lseek(fd, -1, SEEK_SET), -1);
+    assert_eq!(lseek(fd, -11, SEEK_END), -1);
+    assert_eq!(lseek(fd, 0, 3), -1);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 10);
+
+    // positional I/O leaves the offset alone
+    assert_eq!(pread(fd, &mut buffer[..3], 1), 3);
+    assert_eq!(&buffer[..3], b"123");
+    assert_eq!(pwrite(fd, b"XY", 4), 2);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 10);
+    assert_eq!(pread(fd, &mut buffer, 0), 10);
+    assert_eq!(&buffer[..10], b"0123XY6abc");
+    assert_eq!(pread(fd, &mut buffer, 100), 0);
+    assert_eq!(pread(fd, &mut buffer, -1), -1);
+
+    // a write beyond the end leaves a hole of zeros
+    assert_eq!(lseek(fd, 20, SEEK_SET), 20);
+    assert_eq!(write(fd, b"!"), 1);
+    assert_eq!(lseek(fd, 0, SEEK_END), 21);
+    assert_eq!(pread(fd, &mut buffer, 10), 11);
+    assert!(buffer[..10].iter().all(|&b| b == 0));
+    assert_eq!(buffer[10], b'!');
+    close(fd);
+
+    // a read-only file cannot be written even with `pwrite`
+    let fd = open("seek_test\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(pwrite(fd as usize, b"x", 0), -1);
+    close(fd as usize);
+    assert_eq!(unlink("seek_test\0"), 0);
+
+    // pipes and the console have no offset
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(lseek(pipe_fd[0], 0, SEEK_SET), -ESPIPE);
+    assert_eq!(pread(pipe_fd[0], &mut buffer, 0), -ESPIPE);
+    assert_eq!(pwrite(pipe_fd[1], b"x", 0), -ESPIPE);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    assert_eq!(lseek(1, 0, SEEK_CUR), -ESPIPE);
+    println!("seek_test passed!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    alarm, get_time, setitimer, sigaction, sigreturn, yield_, ITimerVal, SignalAction, SignalFlags,
+    TimeVal, ITIMER_REAL,
+};
+
+static ALARM_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn on_alarm() {
+    ALARM_COUNT.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut new = SignalAction::default();
+    let mut old = SignalAction::default();
+    new.handler = on_alarm as usize;
+    let signum = SignalFlags::to_bit_digit(SignalFlags::SIGALRM) as i32;
+    assert_eq!(sigaction(signum, &new, &mut old), 0);
+
+    // fire every 50ms
+    let period = TimeVal {
+        sec: 0,
+        usec: 50_000,
+    };
+    let value = ITimerVal {
+        it_interval: period,
+        it_value: period,
+    };
+    let start = get_time();
+    assert_eq!(setitimer(ITIMER_REAL, &value, None), 0);
+    while ALARM_COUNT.load(Ordering::SeqCst) < 3 {
+        yield_();
+    }
+    let elapsed = get_time() - start;
+    // disarm
+    assert_eq!(setitimer(ITIMER_REAL, &ITimerVal::default(), None), 0);
+    println!("sig_alarm: 3 alarms in {} ms", elapsed);
+    assert!(elapsed >= 150);
+
+    assert_eq!(alarm(10), 0);
+    assert_eq!(alarm(0), 10);
+    println!("sig_alarm passed!");
+    0
+}
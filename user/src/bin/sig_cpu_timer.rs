@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    get_time, setitimer, sigaction, sigreturn, ITimerVal, SignalAction, SignalFlags, TimeVal,
+    ITIMER_PROF, ITIMER_VIRTUAL,
+};
+
+static VTALRM_COUNT: AtomicUsize = AtomicUsize::new(0);
+static PROF_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn on_vtalrm() {
+    VTALRM_COUNT.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn on_prof() {
+    PROF_COUNT.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn catch(signal: SignalFlags, handler: fn()) {
+    let mut new = SignalAction::default();
+    let mut old = SignalAction::default();
+    new.handler = handler as usize;
+    let signum = SignalFlags::to_bit_digit(signal) as i32;
+    assert_eq!(sigaction(signum, &new, &mut old), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    catch(SignalFlags::SIGVTALRM, on_vtalrm);
+    catch(SignalFlags::SIGPROF, on_prof);
+
+    // fire every 50ms of CPU time
+    let period = TimeVal {
+        sec: 0,
+        usec: 50_000,
+    };
+    let value = ITimerVal {
+        it_interval: period,
+        it_value: period,
+    };
+    let start = get_time();
+    assert_eq!(setitimer(ITIMER_VIRTUAL, &value, None), 0);
+    assert_eq!(setitimer(ITIMER_PROF, &value, None), 0);
+    // burn CPU time in user mode, which both timers count
+    while VTALRM_COUNT.load(Ordering::SeqCst) < 3 || PROF_COUNT.load(Ordering::SeqCst) < 3 {
+        spin_loop();
+    }
+    let elapsed = get_time() - start;
+    // disarm
+    assert_eq!(setitimer(ITIMER_VIRTUAL, &ITimerVal::default(), None), 0);
+    assert_eq!(setitimer(ITIMER_PROF, &ITimerVal::default(), None), 0);
+    println!(
+        "sig_cpu_timer: {} SIGVTALRM and {} SIGPROF in {} ms",
+        VTALRM_COUNT.load(Ordering::SeqCst),
+        PROF_COUNT.load(Ordering::SeqCst),
+        elapsed
+    );
+    assert!(elapsed >= 150);
+    println!("sig_cpu_timer passed!");
+    0
+}
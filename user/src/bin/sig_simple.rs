@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::{null, null_mut};
+use user_lib::{getpid, kill, sigaction, sigreturn, SignalAction, SignalFlags};
+
+fn func() {
+    println!("user_sig_test passed");
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut new = SignalAction::default();
+    let mut old = SignalAction::default();
+    new.handler = func as usize;
+
+    let signum = SignalFlags::to_bit_digit(SignalFlags::SIGUSR1) as i32;
+    println!("signal_simple: sigaction");
+    if sigaction(signum, &new, &mut old) < 0 {
+        panic!("Sigaction failed!");
+    }
+    println!("signal_simple: query the handler");
+    let mut current = SignalAction::default();
+    if sigaction(signum, null(), &mut current) < 0 || current.handler != func as usize {
+        panic!("Sigaction query failed!");
+    }
+    if sigaction(signum, &new, null_mut()) < 0 {
+        panic!("Sigaction without old action failed!");
+    }
+    println!("signal_simple: kill");
+    if kill(getpid(), signum) < 0 {
+        println!("Kill failed!");
+        return -1;
+    }
+    println!("signal_simple: Done");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, kill, waitpid, waitpid_options, wifstopped, wstopsig, yield_, SignalFlags,
+    WaitFlags,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    let sigstop = SignalFlags::to_bit_digit(SignalFlags::SIGSTOP) as i32;
+    let sigcont = SignalFlags::to_bit_digit(SignalFlags::SIGCONT) as i32;
+    let sigkill = SignalFlags::to_bit_digit(SignalFlags::SIGKILL) as i32;
+
+    let mut exit_code: i32 = 0;
+    assert_eq!(kill(pid, sigstop), 0);
+    assert_eq!(
+        waitpid_options(pid, &mut exit_code, WaitFlags::WUNTRACED),
+        pid
+    );
+    assert!(wifstopped(exit_code));
+    assert_eq!(wstopsig(exit_code), sigstop);
+    println!("sig_stop: child {} stopped", pid);
+
+    assert_eq!(kill(pid, sigcont), 0);
+    assert_eq!(kill(pid, sigstop), 0);
+    assert_eq!(kill(pid, sigkill), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -sigkill);
+    println!("sig_stop passed!");
+    exit(0);
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, get_time, kill, setitimer, sigaction, sigreturn, sleep, waitpid, ITimerVal,
+    SignalAction, SignalFlags, TimeVal, ITIMER_REAL,
+};
+
+fn on_alarm() {
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a caught signal ends the sleep early
+    let mut new = SignalAction::default();
+    let mut old = SignalAction::default();
+    new.handler = on_alarm as usize;
+    let signum = SignalFlags::to_bit_digit(SignalFlags::SIGALRM) as i32;
+    assert_eq!(sigaction(signum, &new, &mut old), 0);
+    let value = ITimerVal {
+        it_interval: TimeVal::default(),
+        it_value: TimeVal {
+            sec: 0,
+            usec: 50_000,
+        },
+    };
+    let start = get_time();
+    assert_eq!(setitimer(ITIMER_REAL, &value, None), 0);
+    sleep(5000);
+    let elapsed = get_time() - start;
+    println!("sleep_signal: woken up by SIGALRM after {} ms", elapsed);
+    assert!(elapsed < 5000);
+
+    // so does SIGKILL
+    let pid = fork();
+    if pid == 0 {
+        sleep(5000);
+        exit(0);
+    }
+    let start = get_time();
+    sleep(50);
+    let sigkill = SignalFlags::to_bit_digit(SignalFlags::SIGKILL) as i32;
+    assert_eq!(kill(pid, sigkill), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    let elapsed = get_time() - start;
+    println!("sleep_signal: child killed after {} ms", elapsed);
+    assert_eq!(exit_code, -sigkill);
+    assert!(elapsed < 5000);
+    println!("sleep_signal passed!");
+    0
+}
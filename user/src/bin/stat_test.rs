@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstat, fstatat, link, lstat, mkdir, open, pipe, rmdir, stat, symlink, unlink, write,
+    Duration, OpenFlags, Stat, AT_FDCWD, AT_SYMLINK_NOFOLLOW, S_IFCHR, S_IFDIR, S_IFIFO, S_IFLNK,
+    S_IFREG,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut st = Stat::default();
+    assert_eq!(mkdir("stat_test\0"), 0);
+    assert_eq!(stat("stat_test\0", &mut st), 0);
+    assert_eq!(st.file_type(), S_IFDIR);
+    assert_eq!(st.nlink, 2);
+
+    // an empty regular file
+    let fd = open("stat_test/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.file_type(), S_IFREG);
+    assert_eq!(st.mode & 0o777, 0o644);
+    assert_eq!(st.nlink, 1);
+    assert_eq!(st.size, 0);
+    assert_eq!(st.blocks, 0);
+    let created = Duration::from(st.mtime);
+
+    // size, blocks and timestamps follow a write
+    let buffer = [b'x'; 1000];
+    assert_eq!(write(fd, &buffer), 1000);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, 1000);
+    assert!(st.blocks >= 2);
+    assert!(Duration::from(st.mtime) >= created);
+    assert!(Duration::from(st.ctime) >= created);
+    close(fd);
+    let ino = st.ino;
+
+    // hard links share the inode
+    assert_eq!(link("stat_test/file\0", "stat_test/link\0"), 0);
+    assert_eq!(stat("stat_test/link\0", &mut st), 0);
+    assert_eq!(st.ino, ino);
+    assert_eq!(st.nlink, 2);
+
+    // `lstat` reports a symbolic link itself, `stat` its target
+    let target = "file";
+    assert_eq!(symlink("file\0", "stat_test/symlink\0"), 0);
+    assert_eq!(lstat("stat_test/symlink\0", &mut st), 0);
+    assert_eq!(st.file_type(), S_IFLNK);
+    assert_eq!(st.size, target.len() as i64);
+    assert_eq!(stat("stat_test/symlink\0", &mut st), 0);
+    assert_eq!(st.ino, ino);
+    let dirfd = open("stat_test\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    assert!(dirfd > 0);
+    assert_eq!(fstatat(dirfd, "symlink\0", &mut st, AT_SYMLINK_NOFOLLOW), 0);
+    assert_eq!(st.file_type(), S_IFLNK);
+    assert_eq!(fstatat(dirfd, "symlink\0", &mut st, 0), 0);
+    assert_eq!(st.file_type(), S_IFREG);
+    // unknown flags
+    assert_eq!(fstatat(dirfd, "file\0", &mut st, 0x1), -1);
+    close(dirfd as usize);
+
+    // files that are not on the disk
+    assert_eq!(fstat(1, &mut st), 0);
+    assert_eq!(st.file_type(), S_IFCHR);
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(fstat(pipe_fd[0], &mut st), 0);
+    assert_eq!(st.file_type(), S_IFIFO);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // not found
+    assert_eq!(stat("stat_test/no_such_file\0", &mut st), -1);
+    assert_eq!(
+        fstatat(AT_FDCWD, "stat_test/no_such_file\0", &mut st, 0),
+        -1
+    );
+    assert_eq!(fstat(100, &mut st), -1);
+
+    // clean up
+    assert_eq!(unlink("stat_test/symlink\0"), 0);
+    assert_eq!(unlink("stat_test/link\0"), 0);
+    assert_eq!(unlink("stat_test/file\0"), 0);
+    assert_eq!(rmdir("stat_test\0"), 0);
+    println!("stat_test passed!");
+    0
+}
@@ -0,0 +1,145 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, link, linkat, mkdir, open, read, readlink, rmdir, symlink, unlink, write, OpenFlags,
+    AT_FDCWD, AT_SYMLINK_FOLLOW,
+};
+
+fn read_str<'a>(path: &str, buffer: &'a mut [u8]) -> Option<&'a str> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let len = read(fd as usize, buffer) as usize;
+    close(fd as usize);
+    Some(core::str::from_utf8(&buffer[..len]).unwrap())
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, symlink!";
+    let mut buffer = [0u8; 256];
+    assert_eq!(mkdir("symlink_test\0"), 0);
+    assert_eq!(mkdir("symlink_test/dir\0"), 0);
+    let fd = open(
+        "symlink_test/dir/file\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    );
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    // relative to the directory of the link
+    assert_eq!(symlink("dir/file\0", "symlink_test/to_file\0"), 0);
+    assert_eq!(symlink("dir\0", "symlink_test/to_dir\0"), 0);
+    assert_eq!(symlink("dir\0", "symlink_test/to_dir\0"), -1);
+    assert_eq!(
+        read_str("symlink_test/to_file\0", &mut buffer),
+        Some(test_str)
+    );
+    assert_eq!(
+        read_str("symlink_test/to_dir/file\0", &mut buffer),
+        Some(test_str)
+    );
+    assert_eq!(
+        read_str("symlink_test/to_dir/../to_dir/file\0", &mut buffer),
+        Some(test_str)
+    );
+    let len = readlink("symlink_test/to_file\0", &mut buffer);
+    assert_eq!(&buffer[..len as usize], b"dir/file");
+    // truncated
+    assert_eq!(readlink("symlink_test/to_file\0", &mut buffer[..3]), 3);
+    assert_eq!(readlink("symlink_test/dir/file\0", &mut buffer), -1);
+
+    // `NOFOLLOW` only looks at the last component
+    assert_eq!(
+        open(
+            "symlink_test/to_file\0",
+            OpenFlags::RDONLY | OpenFlags::NOFOLLOW
+        ),
+        -1
+    );
+    let fd = open(
+        "symlink_test/to_dir/file\0",
+        OpenFlags::RDONLY | OpenFlags::NOFOLLOW,
+    );
+    assert!(fd > 0);
+    close(fd as usize);
+
+    // an absolute target longer than the inline area of the inode
+    let mut long_target = [b'/'; 200];
+    let tail = b"symlink_test/dir/file";
+    long_target[200 - tail.len()..].copy_from_slice(tail);
+    let long_target = core::str::from_utf8(&long_target).unwrap();
+    let mut long_target_z = [0u8; 201];
+    long_target_z[..200].copy_from_slice(long_target.as_bytes());
+    let long_target_z = core::str::from_utf8(&long_target_z).unwrap();
+    assert_eq!(symlink(long_target_z, "symlink_test/long\0"), 0);
+    assert_eq!(readlink("symlink_test/long\0", &mut buffer), 200);
+    assert_eq!(&buffer[..200], long_target.as_bytes());
+    assert_eq!(read_str("symlink_test/long\0", &mut buffer), Some(test_str));
+
+    // loops
+    assert_eq!(symlink("loop_b\0", "symlink_test/loop_a\0"), 0);
+    assert_eq!(symlink("loop_a\0", "symlink_test/loop_b\0"), 0);
+    assert_eq!(read_str("symlink_test/loop_a\0", &mut buffer), None);
+    // dangling
+    assert_eq!(symlink("none\0", "symlink_test/dangling\0"), 0);
+    assert_eq!(read_str("symlink_test/dangling\0", &mut buffer), None);
+    // `CREATE` creates the target of a dangling link
+    let fd = open(
+        "symlink_test/dangling\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    );
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+    assert_eq!(read_str("symlink_test/none\0", &mut buffer), Some(test_str));
+    assert_eq!(
+        open(
+            "symlink_test/loop_a\0",
+            OpenFlags::CREATE | OpenFlags::WRONLY
+        ),
+        -1
+    );
+
+    // a hard link to the link itself, or to its target
+    assert_eq!(link("symlink_test/to_file\0", "symlink_test/link\0"), 0);
+    assert_eq!(readlink("symlink_test/link\0", &mut buffer), 8);
+    assert_eq!(
+        linkat(
+            AT_FDCWD,
+            "symlink_test/to_file\0",
+            AT_FDCWD,
+            "symlink_test/hard\0",
+            AT_SYMLINK_FOLLOW
+        ),
+        0
+    );
+    assert_eq!(readlink("symlink_test/hard\0", &mut buffer), -1);
+
+    // `unlink` removes the link, not its target
+    for name in [
+        "symlink_test/to_file\0",
+        "symlink_test/link\0",
+        "symlink_test/to_dir\0",
+        "symlink_test/long\0",
+        "symlink_test/loop_a\0",
+        "symlink_test/loop_b\0",
+        "symlink_test/dangling\0",
+    ] {
+        assert_eq!(unlink(name), 0);
+    }
+    assert_eq!(read_str("symlink_test/hard\0", &mut buffer), Some(test_str));
+    assert_eq!(unlink("symlink_test/hard\0"), 0);
+    assert_eq!(unlink("symlink_test/none\0"), 0);
+    assert_eq!(unlink("symlink_test/dir/file\0"), 0);
+    assert_eq!(rmdir("symlink_test/dir\0"), 0);
+    assert_eq!(rmdir("symlink_test\0"), 0);
+    println!("symlink_test passed!");
+    0
+}
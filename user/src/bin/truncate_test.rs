@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstat, ftruncate, lseek, mkdir, open, pipe, pread, pwrite, read, rmdir, stat, truncate,
+    unlink, write, OpenFlags, Stat, SEEK_CUR, SEEK_SET,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut st = Stat::default();
+    let mut buffer = [0u8; 1024];
+    let fd = open("truncate_test\0", OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let data = [b'x'; 3000];
+    assert_eq!(write(fd, &data), 3000);
+
+    // shrink
+    assert_eq!(ftruncate(fd, 1000), 0);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, 1000);
+    assert_eq!(st.blocks, 2);
+    // the offset is not changed
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 3000);
+    assert_eq!(read(fd, &mut buffer), 0);
+
+    // grow with zeros
+    assert_eq!(truncate("truncate_test\0", 2000), 0);
+    assert_eq!(stat("truncate_test\0", &mut st), 0);
+    assert_eq!(st.size, 2000);
+    assert_eq!(st.blocks, 2);
+    assert_eq!(pread(fd, &mut buffer, 900), 1024);
+    assert!(buffer[..100].iter().all(|&b| b == b'x'));
+    assert!(buffer[100..].iter().all(|&b| b == 0));
+
+    // a sparse file takes no space for the hole
+    let offset = 2 * 1024 * 1024;
+    assert_eq!(pwrite(fd, b"end", offset), 3);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, offset as i64 + 3);
+    assert!(st.blocks < 8);
+    assert_eq!(lseek(fd, offset - 1024, SEEK_SET), offset - 1024);
+    assert_eq!(read(fd, &mut buffer), 1024);
+    assert!(buffer.iter().all(|&b| b == 0));
+    assert_eq!(ftruncate(fd, 0), 0);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!((st.size, st.blocks), (0, 0));
+    // invalid length
+    assert_eq!(ftruncate(fd, -1), -1);
+    assert_eq!(ftruncate(fd, isize::MAX), -1);
+    close(fd);
+
+    // only a regular file opened for writing
+    let fd = open("truncate_test\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(ftruncate(fd as usize, 0), -1);
+    close(fd as usize);
+    assert_eq!(mkdir("truncate_test_dir\0"), 0);
+    assert_eq!(truncate("truncate_test_dir\0", 0), -1);
+    assert_eq!(rmdir("truncate_test_dir\0"), 0);
+    assert_eq!(truncate("no_such_file\0", 0), -1);
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(ftruncate(pipe_fd[1], 0), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    assert_eq!(unlink("truncate_test\0"), 0);
+    println!("truncate_test passed!");
+    0
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, getpgid, getpid, ioctl, pipe, tcgetattr, tcgetpgrp, tcsetattr, tcsetpgrp, LocalFlags,
+    Termios, TCGETS, VEOF, VINTR, VSUSP,
+};
+
+const STDIN: usize = 0;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // default: canonical mode with echo and signals
+    let mut orig = Termios::default();
+    assert_eq!(tcgetattr(STDIN, &mut orig), 0);
+    assert!(orig
+        .lflag()
+        .contains(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG));
+    assert_eq!(orig.c_cc[VINTR], 0x03);
+    assert_eq!(orig.c_cc[VSUSP], 0x1a);
+    assert_eq!(orig.c_cc[VEOF], 0x04);
+
+    // raw mode
+    let mut raw = orig;
+    raw.set_lflag(orig.lflag() - (LocalFlags::ICANON | LocalFlags::ECHO));
+    assert_eq!(tcsetattr(STDIN, &raw), 0);
+    let mut current = Termios::default();
+    assert_eq!(tcgetattr(STDIN, &mut current), 0);
+    assert!(!current.lflag().contains(LocalFlags::ICANON));
+    assert!(!current.lflag().contains(LocalFlags::ECHO));
+    assert!(current.lflag().contains(LocalFlags::ISIG));
+    assert_eq!(tcsetattr(STDIN, &orig), 0);
+    assert_eq!(tcgetattr(STDIN, &mut current), 0);
+    assert_eq!(current.c_lflag, orig.c_lflag);
+
+    // foreground process group(-1 if none is set yet)
+    let orig_pgrp = tcgetpgrp(STDIN);
+    let pgid = getpgid(0);
+    assert_eq!(tcsetpgrp(STDIN, pgid as usize), 0);
+    assert_eq!(tcgetpgrp(STDIN), pgid);
+    // no such group
+    assert_eq!(tcsetpgrp(STDIN, getpid() as usize + 1000), -1);
+    if orig_pgrp >= 0 {
+        assert_eq!(tcsetpgrp(STDIN, orig_pgrp as usize), 0);
+    }
+
+    // a pipe is not a terminal
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(tcgetattr(pipe_fd[0], &mut current), -1);
+    assert_eq!(ioctl(pipe_fd[1], TCGETS, 0), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    println!("tty_termios passed!");
+    0
+}
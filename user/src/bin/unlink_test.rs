@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, mkdir, open, read, rmdir, unlink, write, OpenFlags};
+
+/// Write more than the whole file system(16MiB) in total, which succeeds only if
+/// the blocks of the removed files are reclaimed.
+const ROUNDS: usize = 24;
+const FILE_SIZE_MB: usize = 1;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buffer = [0u8; 1024]; // 1KiB
+    for round in 0..ROUNDS {
+        buffer.fill(round as u8);
+        let fd = open("unlink_testf\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd > 0);
+        let fd = fd as usize;
+        for _ in 0..1024 * FILE_SIZE_MB {
+            assert_eq!(write(fd, &buffer), buffer.len() as isize);
+        }
+        close(fd);
+        // the new file must not see the data of the old one
+        let fd = open("unlink_testf\0", OpenFlags::RDONLY);
+        assert!(fd > 0);
+        let fd = fd as usize;
+        let mut read_buffer = [0u8; 1024];
+        assert_eq!(read(fd, &mut read_buffer), 1024);
+        assert!(read_buffer.iter().all(|&b| b == round as u8));
+        close(fd);
+        assert_eq!(unlink("unlink_testf\0"), 0);
+        assert_eq!(open("unlink_testf\0", OpenFlags::RDONLY), -1);
+    }
+    println!(
+        "unlink_test: {}MiB written and reclaimed",
+        ROUNDS * FILE_SIZE_MB
+    );
+
+    assert_eq!(unlink("unlink_testf\0"), -1);
+    assert_eq!(mkdir("unlink_testd\0"), 0);
+    let fd = open("unlink_testd/f\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    // type mismatch
+    assert_eq!(unlink("unlink_testd\0"), -1);
+    assert_eq!(rmdir("unlink_testd/f\0"), -1);
+    // not empty
+    assert_eq!(rmdir("unlink_testd\0"), -1);
+    assert_eq!(unlink("unlink_testd/f\0"), 0);
+    assert_eq!(rmdir("unlink_testd/.\0"), -1);
+    assert_eq!(rmdir("unlink_testd\0"), 0);
+    assert_eq!(open("unlink_testd\0", OpenFlags::RDONLY), -1);
+    println!("unlink_test passed!");
+    0
+}
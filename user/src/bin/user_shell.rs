@@ -1,6 +1,5 @@
 #![no_std]
 #![no_main]
-#![allow(clippy::println_empty_string)]
 
 extern crate alloc;
 
@@ -8,19 +7,30 @@ extern crate alloc;
 extern crate user_lib;
 
 /// LF line feed [\n](https://www.barcodefaq.com/ascii-chart-char-set/)
+///
+/// The console(TTY) edits and echoes the line, and translates CR into it.
 const LF: u8 = 0x0au8;
-/// CR carriage return [\r](https://www.barcodefaq.com/ascii-chart-char-set/)
-const CR: u8 = 0x0du8;
-/// Keyboard keycode: Delete
-const DL: u8 = 0x7fu8;
-/// Keyboard keycode: BackSpace
-const BS: u8 = 0x08u8;
+/// End of file, or interrupted by a signal
+const EOF: u8 = 0;
 const LINE_START: &str = "\x1b[32m|-[\x1b[0m/\x1b[32m]\n|-$ \x1b[0m";
 
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
 use user_lib::console::getchar;
-use user_lib::{close, dup, exec, fork, open, pipe, waitpid, OpenFlags};
+use user_lib::{
+    close, dup, exec, exit, fork, getpid, kill, open, pipe, setpgid, sigaction, sigreturn,
+    tcsetpgrp, waitpid_options, wifstopped, OpenFlags, SignalAction, SignalFlags, WaitFlags,
+};
+
+/// Was the input line interrupted by Ctrl-C/Ctrl-Z?
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Handler of `SIGINT`/`SIGTSTP` that the shell receives in the foreground
+fn interrupt_handler() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+    sigreturn();
+}
 
 #[derive(Debug)]
 /// # Example
@@ -102,141 +112,380 @@ impl ProcessArguments {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobState {
+    Running,
+    Stopped,
+}
+
+/// A pipeline run as one process group
+struct Job {
+    /// Job number(`%n`). 0 until the job is added to the table.
+    id: usize,
+    /// Process group of the pipeline(the pid of the first process)
+    pgid: usize,
+    /// Processes that have not exited yet
+    pids: Vec<isize>,
+    /// Command line without `&`
+    command: String,
+    state: JobState,
+}
+
+/// Jobs running in the background or stopped
+#[derive(Default)]
+struct JobTable {
+    jobs: Vec<Job>,
+}
+
+impl JobTable {
+    /// Add `job` to the table with a new job number.
+    ///
+    /// # Return
+    /// Job number
+    fn add(&mut self, mut job: Job) -> usize {
+        job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        let id = job.id;
+        self.jobs.push(job);
+        id
+    }
+
+    /// Find a job by `%n`(or `n`). The latest job if `arg` is `None`.
+    ///
+    /// # Return
+    /// Index of the job in the table
+    fn find(&self, arg: Option<&str>) -> Option<usize> {
+        match arg {
+            None => self.jobs.len().checked_sub(1),
+            Some(arg) => {
+                let id: usize = arg.trim_start_matches('%').parse().ok()?;
+                self.jobs.iter().position(|job| job.id == id)
+            }
+        }
+    }
+
+    /// `jobs`: list the jobs.
+    fn print(&self) {
+        for job in self.jobs.iter() {
+            let state = match job.state {
+                JobState::Running => "Running",
+                JobState::Stopped => "Stopped",
+            };
+            println!("[{}] {:<8} {}", job.id, state, job.command);
+        }
+    }
+
+    /// `fg %n`: continue a job in the foreground and wait for it.
+    fn fg(&mut self, arg: Option<&str>, shell_pgid: usize) {
+        match self.find(arg) {
+            Some(idx) => {
+                let job = self.jobs.remove(idx);
+                println!("{}", job.command);
+                self.foreground(job, shell_pgid);
+            }
+            None => println!("fg: no such job"),
+        }
+    }
+
+    /// `bg %n`: continue a stopped job in the background.
+    fn bg(&mut self, arg: Option<&str>) {
+        match self.find(arg) {
+            Some(idx) => {
+                let job = &mut self.jobs[idx];
+                if job.state == JobState::Stopped {
+                    kill(
+                        -(job.pgid as isize),
+                        SignalFlags::to_bit_digit(SignalFlags::SIGCONT) as i32,
+                    );
+                    job.state = JobState::Running;
+                }
+                println!("[{}] {} &", job.id, job.command);
+            }
+            None => println!("bg: no such job"),
+        }
+    }
+
+    /// Give the console to `job`, continue it if stopped, and wait until it exits or stops.
+    ///
+    /// A stopped job(e.g. by Ctrl-Z) is added to the table.
+    fn foreground(&mut self, mut job: Job, shell_pgid: usize) {
+        tcsetpgrp(0, job.pgid);
+        if job.state == JobState::Stopped {
+            kill(
+                -(job.pgid as isize),
+                SignalFlags::to_bit_digit(SignalFlags::SIGCONT) as i32,
+            );
+            job.state = JobState::Running;
+        }
+        while let Some(&pid) = job.pids.first() {
+            let mut exit_code: i32 = 0;
+            let exit_pid = waitpid_options(pid, &mut exit_code, WaitFlags::WUNTRACED);
+            if exit_pid == pid && wifstopped(exit_code) {
+                job.state = JobState::Stopped;
+                break;
+            }
+            job.pids.remove(0);
+        }
+        tcsetpgrp(0, shell_pgid);
+        if job.state == JobState::Stopped {
+            let id = if job.id == 0 {
+                self.add(job)
+            } else {
+                let id = job.id;
+                self.jobs.push(job);
+                id
+            };
+            println!("[{}] Stopped  {}", id, self.jobs.last().unwrap().command);
+        }
+    }
+
+    /// Reap the processes of the jobs without blocking, and report the jobs that have
+    /// finished or stopped(e.g. by reading the console in the background).
+    fn report(&mut self) {
+        loop {
+            let mut exit_code: i32 = 0;
+            let pid = waitpid_options(
+                -1,
+                &mut exit_code,
+                WaitFlags::WNOHANG | WaitFlags::WUNTRACED,
+            );
+            if pid <= 0 {
+                break;
+            }
+            let idx = match self.jobs.iter().position(|job| job.pids.contains(&pid)) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let job = &mut self.jobs[idx];
+            if wifstopped(exit_code) {
+                if job.state == JobState::Running {
+                    job.state = JobState::Stopped;
+                    println!("[{}] Stopped  {}", job.id, job.command);
+                }
+                continue;
+            }
+            job.pids.retain(|&p| p != pid);
+            if job.pids.is_empty() {
+                if exit_code == 0 {
+                    println!("[{}] Done     {}", job.id, job.command);
+                } else {
+                    println!("[{}] Exit {}  {}", job.id, exit_code, job.command);
+                }
+                self.jobs.remove(idx);
+            }
+        }
+    }
+}
+
+/// Run `command` if it is empty or one of the job control commands(`jobs`, `fg`, `bg`).
+///
+/// # Return
+/// Conditional branching.
+/// - If `command` is handled here => true
+/// - If `command` has to be run as a pipeline => false
+fn run_job_command(jobs: &mut JobTable, command: &str, shell_pgid: usize) -> bool {
+    let mut words = command.split_whitespace();
+    match words.next() {
+        None => {}
+        Some("jobs") => jobs.print(),
+        Some("fg") => jobs.fg(words.next(), shell_pgid),
+        Some("bg") => jobs.bg(words.next()),
+        Some(_) => return false,
+    }
+    true
+}
+
+/// Only the first process of a pipeline may redirect its input, and only the last one its output.
+fn redirections_valid(process_arguments_list: &[ProcessArguments]) -> bool {
+    if process_arguments_list.len() == 1 {
+        return true;
+    }
+    let last = process_arguments_list.len() - 1;
+    process_arguments_list
+        .iter()
+        .enumerate()
+        .all(|(i, process_args)| {
+            (i == 0 || process_args.input.is_empty())
+                && (i == last || process_args.output.is_empty())
+        })
+}
+
+/// Set up the `i`th process of a pipeline in the forked child and execute it.
+///
+/// # Parameters
+/// - `pipes_fd`: Pipes between the processes. The `i`th process reads `pipes_fd[i - 1]` and writes `pipes_fd[i]`.
+/// - `pgid`: Process group of the pipeline(0 if this is the first process)
+fn exec_pipeline_process(
+    i: usize,
+    process_argument: &ProcessArguments,
+    pipes_fd: &[[usize; 2]],
+    pgid: usize,
+    background: bool,
+) -> ! {
+    // Join the group and move it to the foreground also here,
+    // since the parent may not have done it yet.
+    let pgid = if pgid == 0 { getpid() as usize } else { pgid };
+    setpgid(0, pgid);
+    if !background {
+        tcsetpgrp(0, pgid);
+    }
+    let input = &process_argument.input;
+    let output = &process_argument.output;
+    // redirect input
+    if !input.is_empty() {
+        let input_fd = open(input.as_str(), OpenFlags::RDONLY);
+        if input_fd == -1 {
+            println!("Error when opening file {}", input);
+            exit(-4);
+        }
+        let input_fd = input_fd as usize;
+        close(0);
+        assert_eq!(dup(input_fd), 0);
+        close(input_fd);
+    }
+    // redirect output
+    if !output.is_empty() {
+        let output_fd = open(
+            output.as_str(),
+            OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+        );
+        if output_fd == -1 {
+            println!("Error when opening file {}", output);
+            exit(-4);
+        }
+        let output_fd = output_fd as usize;
+        close(1);
+        assert_eq!(dup(output_fd), 1);
+        close(output_fd);
+    }
+    // receive input from the previous process
+    if i > 0 {
+        close(0);
+        let read_end = pipes_fd[i - 1][0];
+        assert_eq!(dup(read_end), 0);
+    }
+    // send output to the next process
+    if i < pipes_fd.len() {
+        close(1);
+        let write_end = pipes_fd[i][1];
+        assert_eq!(dup(write_end), 1);
+    }
+    // close all pipe ends inherited from the parent process
+    for pipe_fd in pipes_fd.iter() {
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+    }
+    // execute new application
+    let args_copy = &process_argument.args_copy;
+    if exec(args_copy[0].as_str(), process_argument.args_addr.as_slice()) == -1 {
+        println!("Error when executing!");
+        exit(-4);
+    }
+    unreachable!();
+}
+
+/// Run `command`(e.g. `cat filea | cat fileb`) as a pipeline in a new process group.
+///
+/// # Return
+/// Conditional branching.
+/// - If the redirections of `command` are invalid => None
+/// - Otherwise => The job of the pipeline, which is not in the job table yet
+fn run_pipeline(command: &str, background: bool) -> Option<Job> {
+    // cat filea | cat fileb
+    //  => [filea, fileb]
+    let process_arguments_list: Vec<_> = command.split('|').map(ProcessArguments::new).collect();
+    if !redirections_valid(&process_arguments_list) {
+        println!("Invalid command: Inputs/Outputs cannot be combined correctly!");
+        return None;
+    }
+    // create pipes
+    let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
+    for _ in 1..process_arguments_list.len() {
+        let mut pipe_fd = [0usize; 2];
+        pipe(&mut pipe_fd);
+        pipes_fd.push(pipe_fd);
+    }
+    let mut children: Vec<_> = Vec::new();
+    // process group of the pipeline(the pid of the first process)
+    let mut pgid = 0;
+    for (i, process_argument) in process_arguments_list.iter().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            exec_pipeline_process(i, process_argument, &pipes_fd, pgid, background);
+        }
+        // Ctrl-C/Ctrl-Z are sent to every process of the pipeline
+        if pgid == 0 {
+            pgid = pid as usize;
+        }
+        setpgid(pid as usize, pgid);
+        if !background {
+            tcsetpgrp(0, pgid);
+        }
+        children.push(pid);
+    }
+    for pipe_fd in pipes_fd.iter() {
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+    }
+    Some(Job {
+        id: 0,
+        pgid,
+        pids: children,
+        command: String::from(command),
+        state: JobState::Running,
+    })
+}
+
 #[no_mangle]
 pub fn main() -> i32 {
     println!("Rust user shell");
+    // Lead a process group that owns the console, and survive Ctrl-C/Ctrl-Z at the prompt.
+    // The handlers are reset to default by `exec` in the children.
+    let shell_pgid = getpid() as usize;
+    setpgid(0, 0);
+    tcsetpgrp(0, shell_pgid);
+    let action = SignalAction {
+        handler: interrupt_handler as usize,
+        mask: SignalFlags::empty(),
+    };
+    let mut old_action = SignalAction::default();
+    for signal in [SignalFlags::SIGINT, SignalFlags::SIGTSTP] {
+        let signum = SignalFlags::to_bit_digit(signal) as i32;
+        sigaction(signum, &action, &mut old_action);
+    }
+    let mut jobs = JobTable::default();
     let mut line: String = String::new();
     print!("{}", LINE_START);
     loop {
         let c = getchar();
         match c {
-            LF | CR => {
-                println!("");
-                if !line.is_empty() {
-                    let split: Vec<_> = line.as_str().split('|').collect();
-                    // cat filea | cat fileb
-                    //  => [filea, fileb]
-                    let process_arguments_list: Vec<_> = split
-                        .iter()
-                        .map(|&cmd| ProcessArguments::new(cmd))
-                        .collect();
-                    let mut valid = true;
-                    for (i, process_args) in process_arguments_list.iter().enumerate() {
-                        if i == 0 {
-                            if !process_args.output.is_empty() {
-                                valid = false;
-                            }
-                        } else if i == process_arguments_list.len() - 1 {
-                            if !process_args.input.is_empty() {
-                                valid = false;
-                            }
-                        } else if !process_args.output.is_empty() || !process_args.input.is_empty()
-                        {
-                            valid = false;
-                        }
-                    }
-                    if process_arguments_list.len() == 1 {
-                        valid = true;
-                    }
-                    if !valid {
-                        println!("Invalid command: Inputs/Outputs cannot be combined correctly!");
-                    } else {
-                        // create pipes
-                        let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
-                        if !process_arguments_list.is_empty() {
-                            for _ in 0..process_arguments_list.len() - 1 {
-                                let mut pipe_fd = [0usize; 2];
-                                pipe(&mut pipe_fd);
-                                pipes_fd.push(pipe_fd);
-                            }
-                        }
-                        let mut children: Vec<_> = Vec::new();
-                        for (i, process_argument) in process_arguments_list.iter().enumerate() {
-                            let pid = fork();
-                            if pid == 0 {
-                                let input = &process_argument.input;
-                                let output = &process_argument.output;
-                                let args_copy = &process_argument.args_copy;
-                                let args_addr = &process_argument.args_addr;
-                                // redirect input
-                                if !input.is_empty() {
-                                    let input_fd = open(input.as_str(), OpenFlags::RDONLY);
-                                    if input_fd == -1 {
-                                        println!("Error when opening file {}", input);
-                                        return -4;
-                                    }
-                                    let input_fd = input_fd as usize;
-                                    close(0);
-                                    assert_eq!(dup(input_fd), 0);
-                                    close(input_fd);
-                                }
-                                // redirect output
-                                if !output.is_empty() {
-                                    let output_fd = open(
-                                        output.as_str(),
-                                        OpenFlags::CREATE | OpenFlags::WRONLY,
-                                    );
-                                    if output_fd == -1 {
-                                        println!("Error when opening file {}", output);
-                                        return -4;
-                                    }
-                                    let output_fd = output_fd as usize;
-                                    close(1);
-                                    assert_eq!(dup(output_fd), 1);
-                                    close(output_fd);
-                                }
-                                // receive input from the previous process
-                                if i > 0 {
-                                    close(0);
-                                    let read_end = pipes_fd.get(i - 1).unwrap()[0];
-                                    assert_eq!(dup(read_end), 0);
-                                }
-                                // send output to the next process
-                                if i < process_arguments_list.len() - 1 {
-                                    close(1);
-                                    let write_end = pipes_fd.get(i).unwrap()[1];
-                                    assert_eq!(dup(write_end), 1);
-                                }
-                                // close all pipe ends inherited from the parent process
-                                for pipe_fd in pipes_fd.iter() {
-                                    close(pipe_fd[0]);
-                                    close(pipe_fd[1]);
-                                }
-                                // execute new application
-                                if exec(args_copy[0].as_str(), args_addr.as_slice()) == -1 {
-                                    println!("Error when executing!");
-                                    return -4;
-                                }
-                                unreachable!();
-                            } else {
-                                children.push(pid);
-                            }
-                        }
-                        for pipe_fd in pipes_fd.iter() {
-                            close(pipe_fd[0]);
-                            close(pipe_fd[1]);
-                        }
-                        let mut exit_code: i32 = 0;
-                        for pid in children.into_iter() {
-                            let exit_pid = waitpid(pid as usize, &mut exit_code);
-                            assert_eq!(pid, exit_pid);
-                            //println!("Shell: Process {} exited with code {}", pid, exit_code);
+            LF => {
+                // `command &` runs in the background
+                let (command, background) = match line.trim().strip_suffix('&') {
+                    Some(command) => (command.trim_end(), true),
+                    None => (line.trim(), false),
+                };
+                if !run_job_command(&mut jobs, command, shell_pgid) {
+                    if let Some(job) = run_pipeline(command, background) {
+                        if background {
+                            let pgid = job.pgid;
+                            let id = jobs.add(job);
+                            println!("[{}] {}", id, pgid);
+                        } else {
+                            jobs.foreground(job, shell_pgid);
                         }
                     }
-                    line.clear();
                 }
+                line.clear();
+                jobs.report();
                 print!("{}", LINE_START);
             }
-            BS | DL => {
-                if !line.is_empty() {
-                    print!("{}", BS as char);
-                    print!(" ");
-                    print!("{}", BS as char);
-                    line.pop();
+            EOF => {
+                if INTERRUPTED.swap(false, Ordering::Relaxed) {
+                    line.clear();
+                    print!("{}", LINE_START);
                 }
             }
             _ => {
-                print!("{}", c as char);
                 line.push(c as char);
             }
         }
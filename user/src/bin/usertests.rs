@@ -11,32 +11,50 @@ extern crate user_lib;
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("filetest_simple\0", "\0", "\0", "\0", 0),
     ("cat\0", "filea\0", "\0", "\0", 0),
+    ("clock_test\0", "\0", "\0", "\0", 0),
     ("cmdline_args\0", "1\0", "2\0", "3\0", 0),
+    ("dir_test\0", "\0", "\0", "\0", 0),
+    ("dir_write_test\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
     ("forktree\0", "\0", "\0", "\0", 0),
+    ("getdents_test\0", "\0", "\0", "\0", 0),
     ("hello_world\0", "\0", "\0", "\0", 0),
     ("huge_write\0", "\0", "\0", "\0", 0),
+    ("link_test\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
     ("mpsc_sem\0", "\0", "\0", "\0", 0),
+    ("pgrp_test\0", "\0", "\0", "\0", 0),
     ("phil_din_mutex\0", "\0", "\0", "\0", 0),
     ("pipe_large_test\0", "\0", "\0", "\0", 0),
     ("pipetest\0", "\0", "\0", "\0", 0),
+    ("priority_test\0", "\0", "\0", "\0", 0),
     ("race_adder_arg\0", "3\0", "\0", "\0", 0),
     ("race_adder_atomic\0", "\0", "\0", "\0", 0),
     ("race_adder_mutex_blocking\0", "\0", "\0", "\0", 0),
     ("race_adder_mutex_spin\0", "\0", "\0", "\0", 0),
     // ("run_pipe_test\0", "\0", "\0", "\0", 0),
+    ("seek_test\0", "\0", "\0", "\0", 0),
+    ("sig_alarm\0", "\0", "\0", "\0", 0),
+    ("sig_cpu_timer\0", "\0", "\0", "\0", 0),
+    ("sig_simple\0", "\0", "\0", "\0", 0),
+    ("sig_stop\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
+    ("sleep_signal\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
+    ("stat_test\0", "\0", "\0", "\0", 0),
+    ("symlink_test\0", "\0", "\0", "\0", 0),
     ("sync_sem\0", "\0", "\0", "\0", 0),
     ("test_condvar\0", "\0", "\0", "\0", 0),
     ("threads_arg\0", "\0", "\0", "\0", 0),
     ("threads\0", "\0", "\0", "\0", 0),
+    ("truncate_test\0", "\0", "\0", "\0", 0),
+    ("tty_termios\0", "\0", "\0", "\0", 0),
+    ("unlink_test\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
@@ -67,8 +67,10 @@ macro_rules! println {
 
 /// Reads one character from standard input and returns the character read.
 ///
+/// The console is in canonical mode by default, so nothing is returned until a line is entered.
+///
 /// # Return
-/// Returns the characters read.
+/// Returns the characters read, or 0 at the end of file(^D).
 pub fn getchar() -> u8 {
     let mut c = [0u8; 1];
     read(STDIN, &mut c);
@@ -13,10 +13,13 @@ mod syscall;
 #[macro_use]
 extern crate bitflags;
 
+use alloc::string::String;
 use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
 use syscall::*;
 
+pub use core::time::Duration;
+
 const USER_HEAP_SIZE: usize = 32768; // 32KiB
 
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
@@ -76,14 +79,29 @@ bitflags! {
         /// Both read and write.
         const RDWR = 1 << 1;
         /// `CREATE` of the file is allowed and should be created if it is not found;
-        /// if it already exists, the file size should be set to zero.
+        /// if it already exists, it is kept as it is(see `TRUNC`).
         const CREATE = 1 << 9;
         /// It should be cleared and the size set back to zero,
         /// i.e. `TRUNC`, when opening the file.
         const TRUNC = 1 << 10;
+        /// It fails if the path is not a directory.
+        const DIRECTORY = 1 << 16;
+        /// It fails if the path is a symbolic link.
+        const NOFOLLOW = 1 << 17;
     }
 }
 
+/// `dirfd` of the `*at` functions(e.g. [`openat`]) meaning the current working directory.
+///
+/// There is no working directory for each process yet, so it is always the root directory.
+pub const AT_FDCWD: isize = -100;
+/// `flags` of [`unlinkat`] to remove a directory.
+pub const AT_REMOVEDIR: u32 = 0x200;
+/// `flags` of [`fstatat`] to report a symbolic link itself instead of its target.
+pub const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+/// `flags` of [`linkat`] to link the target of a symbolic link.
+pub const AT_SYMLINK_FOLLOW: u32 = 0x400;
+
 /// Duplicates the file descriptor reference passed in the argument.
 ///
 /// # Parameter
@@ -98,11 +116,12 @@ pub fn dup(fd: usize) -> isize {
     sys_dup(fd)
 }
 
-/// Opens a regular file and returns an accessible file descriptor.
+/// Opens a file or a directory and returns an accessible file descriptor.
+///
+/// Same as [`openat`] with `AT_FDCWD`.
 ///
 /// # Parameters
-/// - `path`: Describe the filename of the file to be opened (for simplicity,
-/// the file system does not need to support directories, all files are placed in the root(`/`) directory).
+/// - `path`: Path of the file to be opened(e.g. `dir/file\0`, `/dir/file\0`).
 /// - `flags`: Describe the flags to be used when opening the file.
 ///
 /// # Flags
@@ -117,10 +136,14 @@ pub fn dup(fd: usize) -> isize {
 /// |  1(0x002) | read & write | `RDWR` for both read and write.                                           |
 /// |-----------|--------------|---------------------------------------------------------------------------|
 /// |  9(0x200) |       create | `CREATE` of the file is allowed and should be created if it is not found; |
-/// |           |              | if it already exists, the file size should be set to zero.                |
+/// |           |              | if it already exists, it is kept as it is(see `TRUNC`).                   |
 /// |-----------|--------------|---------------------------------------------------------------------------|
 /// | 10(0x400) |        trunc | it should be cleared and the size set back to zero,                       |
-/// |           |              | i.e. `TRUNC`, when opening the file.                                      |
+/// |           |              | i.e. `TRUNC`, when opening the file for writing.                          |
+/// |-----------|--------------|---------------------------------------------------------------------------|
+/// |16(0x10000)|    directory | it fails if the path is not a directory(`DIRECTORY`).                     |
+/// |-----------|--------------|---------------------------------------------------------------------------|
+/// |17(0x20000)|   no follow  | it fails if the path is a symbolic link(`NOFOLLOW`).                      |
 /// |-----------|--------------|---------------------------------------------------------------------------|
 ///
 /// # Return
@@ -145,7 +168,411 @@ pub fn dup(fd: usize) -> isize {
 /// close(fd);
 /// ```
 pub fn open(path: &str, flags: OpenFlags) -> isize {
-    sys_open(path, flags.bits)
+    sys_openat(AT_FDCWD, path, flags.bits)
+}
+
+/// Opens a file or a directory relative to the directory `dirfd`.
+///
+/// # Parameters
+/// - `dirfd`: Directory opened with `OpenFlags::DIRECTORY`, or `AT_FDCWD`.
+/// - `path`: Path of the file to be opened. An absolute path ignores `dirfd`.
+/// - `flags`: See [`open`].
+///
+/// # Return
+/// Conditional branching.
+/// - If `dirfd` is not a directory, or the same errors as [`open`] => -1
+/// - Otherwise => The file descriptor of the file
+pub fn openat(dirfd: isize, path: &str, flags: OpenFlags) -> isize {
+    sys_openat(dirfd, path, flags.bits)
+}
+
+/// Create a directory.
+///
+/// Same as [`mkdirat`] with `AT_FDCWD`.
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdirat(AT_FDCWD, path)
+}
+
+/// Create a directory relative to the directory `dirfd`.
+///
+/// # Parameters
+/// - `dirfd`: Directory opened with `OpenFlags::DIRECTORY`, or `AT_FDCWD`.
+/// - `path`: Path of the directory to be created. An absolute path ignores `dirfd`.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `dirfd` is not a directory, `path` already exists or its parent directory is not found => -1
+pub fn mkdirat(dirfd: isize, path: &str) -> isize {
+    sys_mkdirat(dirfd, path)
+}
+
+/// Remove a file.
+///
+/// Same as [`unlinkat`] with `AT_FDCWD` and no flags.
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
+}
+
+/// Remove an empty directory.
+///
+/// Same as [`unlinkat`] with `AT_FDCWD` and `AT_REMOVEDIR`.
+pub fn rmdir(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, AT_REMOVEDIR)
+}
+
+/// Remove a file(or an empty directory with `AT_REMOVEDIR`) relative to the directory `dirfd`.
+///
+/// The space of the file is reclaimed when no other name refers to it and it is not open.
+///
+/// # Parameters
+/// - `dirfd`: Directory opened with `OpenFlags::DIRECTORY`, or `AT_FDCWD`.
+/// - `path`: Path to be removed. An absolute path ignores `dirfd`.
+/// - `flags`: `AT_REMOVEDIR` to remove a directory, otherwise 0
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `path` is not found, its type does not match `flags` or the directory is not empty => -1
+pub fn unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    sys_unlinkat(dirfd, path, flags)
+}
+
+/// Add a new name(hard link) for an existing file.
+///
+/// Same as [`linkat`] with `AT_FDCWD`.
+pub fn link(oldpath: &str, newpath: &str) -> isize {
+    sys_linkat(AT_FDCWD, oldpath, AT_FDCWD, newpath, 0)
+}
+
+/// Add a new name(hard link) for an existing file, with paths relative to directories.
+///
+/// The file is removed when all of its names are removed and it is not open.
+///
+/// # Parameters
+/// - `olddirfd`/`oldpath`: The existing file(see [`openat`]).
+/// - `newdirfd`/`newpath`: The new name(see [`openat`]).
+/// - `flags`: `AT_SYMLINK_FOLLOW` to link the target of `oldpath` if it is a symbolic link,
+///   otherwise 0 to link the symbolic link itself.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `oldpath` is not found or a directory => -1
+/// - If `newpath` already exists, or its parent directory is not found => -1
+pub fn linkat(olddirfd: isize, oldpath: &str, newdirfd: isize, newpath: &str, flags: u32) -> isize {
+    sys_linkat(olddirfd, oldpath, newdirfd, newpath, flags)
+}
+
+/// Move a file or a directory to another name.
+///
+/// Same as [`renameat`] with `AT_FDCWD`.
+pub fn rename(oldpath: &str, newpath: &str) -> isize {
+    sys_renameat(AT_FDCWD, oldpath, AT_FDCWD, newpath)
+}
+
+/// Move a file or a directory to another name atomically, with paths relative to directories.
+///
+/// An existing `newpath` is replaced if it is the same type(a directory must be empty).
+///
+/// # Parameters
+/// - `olddirfd`/`oldpath`: The file to be moved(see [`openat`]).
+/// - `newdirfd`/`newpath`: The new name(see [`openat`]).
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `oldpath` is not found, or the parent directory of `newpath` is not found => -1
+/// - If a directory is moved under itself => -1
+/// - If `newpath` exists and is a different type or a directory that is not empty => -1
+pub fn renameat(olddirfd: isize, oldpath: &str, newdirfd: isize, newpath: &str) -> isize {
+    sys_renameat(olddirfd, oldpath, newdirfd, newpath)
+}
+
+/// Change the size of a file to `length` bytes.
+///
+/// Shrinking frees the data beyond `length`.
+/// Growing appends a hole that reads as zeros and takes no disk space.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `path` is not found or not a regular file => -1
+/// - If `length` is negative or exceeds the max file size => -1
+pub fn truncate(path: &str, length: isize) -> isize {
+    sys_truncate(path, length)
+}
+
+/// Change the size of an open file to `length` bytes as [`truncate`] does.
+///
+/// The offset of `fd` is not changed.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `fd` is not a regular file opened for writing => -1
+/// - If `length` is negative or exceeds the max file size => -1
+pub fn ftruncate(fd: usize, length: isize) -> isize {
+    sys_ftruncate(fd, length)
+}
+
+/// Create a symbolic link.
+///
+/// Same as [`symlinkat`] with `AT_FDCWD`.
+pub fn symlink(target: &str, linkpath: &str) -> isize {
+    sys_symlinkat(target, AT_FDCWD, linkpath)
+}
+
+/// Create a symbolic link `linkpath` relative to the directory `newdirfd` that refers to `target`.
+///
+/// Paths through the link are resolved with `target`(relative to the directory of the link).
+///
+/// # Parameters
+/// - `target`: Path stored in the link(e.g. `file\0`). It may not exist.
+/// - `newdirfd`/`linkpath`: Path of the new link(see [`openat`]).
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `linkpath` already exists, its parent directory is not found or `target` is empty => -1
+pub fn symlinkat(target: &str, newdirfd: isize, linkpath: &str) -> isize {
+    sys_symlinkat(target, newdirfd, linkpath)
+}
+
+/// Read the target of a symbolic link.
+///
+/// Same as [`readlinkat`] with `AT_FDCWD`.
+pub fn readlink(path: &str, buf: &mut [u8]) -> isize {
+    sys_readlinkat(AT_FDCWD, path, buf)
+}
+
+/// Read the target of a symbolic link relative to the directory `dirfd`.
+///
+/// # Parameters
+/// - `dirfd`/`path`: The symbolic link(see [`openat`]).
+/// - `buf`: Buffer that receives the target(truncated to its length, without a null character).
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found or not a symbolic link => -1
+/// - Otherwise => Number of bytes placed in `buf`
+pub fn readlinkat(dirfd: isize, path: &str, buf: &mut [u8]) -> isize {
+    sys_readlinkat(dirfd, path, buf)
+}
+
+/// Mask of the file type bits of [`Stat::mode`]
+pub const S_IFMT: u32 = 0o170000;
+/// File type: FIFO(pipe)
+pub const S_IFIFO: u32 = 0o010000;
+/// File type: character device(e.g. the console)
+pub const S_IFCHR: u32 = 0o020000;
+/// File type: directory
+pub const S_IFDIR: u32 = 0o040000;
+/// File type: regular file
+pub const S_IFREG: u32 = 0o100000;
+/// File type: symbolic link
+pub const S_IFLNK: u32 = 0o120000;
+
+/// File status of [`fstat`]/[`fstatat`](the layout of `struct stat` of Linux)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    /// Device containing the file
+    pub dev: u64,
+    /// Inode number
+    pub ino: u64,
+    /// File type(`S_IF*`) and permission bits
+    pub mode: u32,
+    /// Number of hard links
+    pub nlink: u32,
+    /// User id of the owner
+    pub uid: u32,
+    /// Group id of the owner
+    pub gid: u32,
+    /// Device id(if it is a special file)
+    pub rdev: u64,
+    __pad1: u64,
+    /// Total size in bytes
+    pub size: i64,
+    /// Block size for the file system I/O
+    pub blksize: i32,
+    __pad2: i32,
+    /// Number of 512-byte blocks allocated
+    pub blocks: i64,
+    /// Time of the last access
+    pub atime: TimeSpec,
+    /// Time of the last modification
+    pub mtime: TimeSpec,
+    /// Time of the last status change
+    pub ctime: TimeSpec,
+    __unused: [u32; 2],
+}
+
+impl Stat {
+    /// File type bits of `mode`(e.g. `S_IFREG`)
+    pub fn file_type(&self) -> u32 {
+        self.mode & S_IFMT
+    }
+}
+
+/// Get the status of an open file.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `fd` is not opened => -1
+pub fn fstat(fd: usize, st: &mut Stat) -> isize {
+    sys_fstat(fd, st as *mut _)
+}
+
+/// Get the status of a file, following a symbolic link.
+///
+/// Same as [`fstatat`] with `AT_FDCWD`.
+pub fn stat(path: &str, st: &mut Stat) -> isize {
+    sys_fstatat(AT_FDCWD, path, st as *mut _, 0)
+}
+
+/// Get the status of a file, reporting a symbolic link itself.
+///
+/// Same as [`fstatat`] with `AT_FDCWD` and `AT_SYMLINK_NOFOLLOW`.
+pub fn lstat(path: &str, st: &mut Stat) -> isize {
+    sys_fstatat(AT_FDCWD, path, st as *mut _, AT_SYMLINK_NOFOLLOW)
+}
+
+/// Get the status of a file relative to the directory `dirfd`.
+///
+/// # Parameters
+/// - `dirfd`/`path`: The file(see [`openat`]).
+/// - `st`: Where the status is stored.
+/// - `flags`: `AT_SYMLINK_NOFOLLOW` to report a symbolic link itself instead of its target,
+///   otherwise 0
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `dirfd` is not a directory or `flags` is unknown => -1
+/// - If `path` is not found => -1
+pub fn fstatat(dirfd: isize, path: &str, st: &mut Stat, flags: u32) -> isize {
+    sys_fstatat(dirfd, path, st as *mut _, flags)
+}
+
+/// File type of [`DirEntry`]: FIFO(pipe)
+pub const DT_FIFO: u8 = 1;
+/// File type of [`DirEntry`]: character device
+pub const DT_CHR: u8 = 2;
+/// File type of [`DirEntry`]: directory
+pub const DT_DIR: u8 = 4;
+/// File type of [`DirEntry`]: regular file
+pub const DT_REG: u8 = 8;
+/// File type of [`DirEntry`]: symbolic link
+pub const DT_LNK: u8 = 10;
+
+/// Read the entries of a directory into `buf` as `struct linux_dirent64` records.
+///
+/// Each call continues from the entry after the last one read.
+/// Use [`opendir`]/[`readdir`] instead to get the entries one by one.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not an open directory, or `buf` is too small for the next record => -1
+/// - If all entries have been read => 0
+/// - Otherwise => Number of bytes of the records
+pub fn getdents64(fd: usize, buf: &mut [u8]) -> isize {
+    sys_getdents64(fd, buf)
+}
+
+/// An entry of a directory read by [`readdir`]
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    /// Inode number
+    pub ino: u64,
+    /// File type(`DT_*`)
+    pub file_type: u8,
+    /// Name of the entry
+    pub name: String,
+}
+
+/// A directory opened by [`opendir`]
+///
+/// It iterates over the entries(including `.` and `..`) and is closed when dropped.
+pub struct Dir {
+    fd: usize,
+    /// Records read by [`getdents64`]
+    buf: [u8; 512],
+    /// Bytes of the records in `buf`
+    len: usize,
+    /// Position of the next record in `buf`
+    pos: usize,
+}
+
+/// Open a directory to read its entries.
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found or not a directory => `None`
+/// - Otherwise => The directory
+///
+/// # Examples
+/// ```
+/// for entry in opendir("/\0").unwrap() {
+///     println!("{}", entry.name);
+/// }
+/// ```
+pub fn opendir(path: &str) -> Option<Dir> {
+    let fd = open(path, OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if fd < 0 {
+        return None;
+    }
+    Some(Dir {
+        fd: fd as usize,
+        buf: [0; 512],
+        len: 0,
+        pos: 0,
+    })
+}
+
+/// Read the next entry of a directory.
+///
+/// # Return
+/// Conditional branching.
+/// - If all entries have been read => `None`
+/// - Otherwise => The entry
+pub fn readdir(dir: &mut Dir) -> Option<DirEntry> {
+    if dir.pos >= dir.len {
+        let len = getdents64(dir.fd, &mut dir.buf);
+        if len <= 0 {
+            return None;
+        }
+        dir.len = len as usize;
+        dir.pos = 0;
+    }
+    // d_ino: u64, d_off: i64, d_reclen: u16, d_type: u8, d_name: null-terminated
+    let record = &dir.buf[dir.pos..dir.len];
+    let ino = u64::from_ne_bytes(record[0..8].try_into().unwrap());
+    let reclen = u16::from_ne_bytes(record[16..18].try_into().unwrap()) as usize;
+    let file_type = record[18];
+    let name = &record[19..reclen];
+    let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    dir.pos += reclen;
+    Some(DirEntry {
+        ino,
+        file_type,
+        name: String::from(core::str::from_utf8(&name[..name_len]).unwrap()),
+    })
+}
+
+impl Iterator for Dir {
+    type Item = DirEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        readdir(self)
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        close(self.fd);
+    }
 }
 
 /// The current process closes the file.
@@ -187,6 +614,7 @@ pub fn pipe(pipe_fd: &mut [usize]) -> isize {
 /// # Return
 /// Conditional branching.
 /// - If an error occurs => -1
+/// - If a background process reads the terminal with `SIGTTIN` blocked => -5(`EIO`)
 /// - otherwise => number of bytes actually read.
 ///
 /// # Examples
@@ -207,7 +635,11 @@ pub fn read(fd: usize, buf: &mut [u8]) -> isize {
 /// - `buf`: indicates the start address of the in-memory buffer.
 ///
 /// # Return
-/// The length of the successful write.
+/// Conditional branching.
+/// - If `fd` is not opened for writing, or it is a directory => -1
+/// - If the file would exceed the max file size => -`EFBIG`
+/// - If the disk is full before anything is written => -`ENOSPC`
+/// - otherwise => The length of the successful write.
 ///
 /// # Examples
 /// ```
@@ -219,6 +651,205 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
 
+/// `whence` of [`lseek`]: The offset is set to `offset` bytes.
+pub const SEEK_SET: usize = 0;
+/// `whence` of [`lseek`]: The offset is set to the current offset plus `offset` bytes.
+pub const SEEK_CUR: usize = 1;
+/// `whence` of [`lseek`]: The offset is set to the size of the file plus `offset` bytes.
+pub const SEEK_END: usize = 2;
+/// Error number of [`write`]/[`pwrite`] beyond the max file size.
+pub const EFBIG: isize = 27;
+/// Error number of [`write`]/[`pwrite`] to a full disk.
+pub const ENOSPC: isize = 28;
+/// Error number of [`lseek`]/[`pread`]/[`pwrite`] on a pipe or the console(illegal seek).
+pub const ESPIPE: isize = 29;
+
+/// Move the offset of a file used by [`read`]/[`write`].
+///
+/// # Parameters
+/// - `fd`: The file descriptor of the file.
+/// - `offset`: Offset in bytes relative to `whence`.
+/// - `whence`: `SEEK_SET`, `SEEK_CUR` or `SEEK_END`
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened, `whence` is unknown or the new offset is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => The new offset from the start of the file
+///
+/// # Examples
+/// ```
+/// // the size of the file
+/// let size = lseek(fd, 0, SEEK_END);
+/// ```
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+
+/// Read a file from `offset` without moving its offset.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened for reading or `offset` is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => number of bytes actually read.
+pub fn pread(fd: usize, buf: &mut [u8], offset: isize) -> isize {
+    sys_pread64(fd, buf, offset)
+}
+
+/// Write a file from `offset` without moving its offset.
+///
+/// `offset` may be beyond the end of the file.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened for writing or `offset` is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - If the file would exceed the max file size => -`EFBIG`
+/// - If the disk is full before anything is written => -`ENOSPC`
+/// - Otherwise => The length of the successful write.
+pub fn pwrite(fd: usize, buf: &[u8], offset: isize) -> isize {
+    sys_pwrite64(fd, buf, offset)
+}
+
+/// Get the current `Termios`(`tcgetattr`)
+pub const TCGETS: usize = 0x5401;
+/// Set the current `Termios`(`tcsetattr`)
+pub const TCSETS: usize = 0x5402;
+/// Get the foreground process group(`tcgetpgrp`)
+pub const TIOCGPGRP: usize = 0x540f;
+/// Set the foreground process group(`tcsetpgrp`)
+pub const TIOCSPGRP: usize = 0x5410;
+
+/// Number of control characters in `Termios::c_cc`
+pub const NCCS: usize = 19;
+/// Index of `c_cc`: interrupt(^C, send `SIGINT`)
+pub const VINTR: usize = 0;
+/// Index of `c_cc`: erase a character(DEL)
+pub const VERASE: usize = 2;
+/// Index of `c_cc`: erase the line(^U)
+pub const VKILL: usize = 3;
+/// Index of `c_cc`: end of file(^D)
+pub const VEOF: usize = 4;
+/// Index of `c_cc`: suspend(^Z, send `SIGTSTP`)
+pub const VSUSP: usize = 10;
+
+bitflags! {
+    /// Input modes(`Termios::c_iflag`)
+    pub struct InputFlags: u32 {
+        /// Translate CR to NL on input
+        const ICRNL = 0o400;
+    }
+}
+
+bitflags! {
+    /// Local modes(`Termios::c_lflag`)
+    pub struct LocalFlags: u32 {
+        /// Send signals for `VINTR`/`VSUSP`
+        const ISIG = 0o1;
+        /// Canonical mode(line editing)
+        const ICANON = 0o2;
+        /// Echo input
+        const ECHO = 0o10;
+        /// Erase the character on the screen for `VERASE`
+        const ECHOE = 0o20;
+    }
+}
+
+/// Terminal attributes
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Termios {
+    /// Input modes([`InputFlags`])
+    pub c_iflag: u32,
+    /// Output modes
+    pub c_oflag: u32,
+    /// Control modes
+    pub c_cflag: u32,
+    /// Local modes([`LocalFlags`])
+    pub c_lflag: u32,
+    /// Line discipline
+    pub c_line: u8,
+    /// Control characters
+    pub c_cc: [u8; NCCS],
+}
+
+impl Termios {
+    /// Local modes
+    pub fn lflag(&self) -> LocalFlags {
+        LocalFlags::from_bits_truncate(self.c_lflag)
+    }
+
+    /// Set the local modes
+    pub fn set_lflag(&mut self, lflag: LocalFlags) {
+        self.c_lflag = lflag.bits();
+    }
+}
+
+/// Control a device through a file descriptor.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened, or the file does not accept `cmd` => -1
+/// - Otherwise => Depends on `cmd`
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_ioctl(fd, cmd, arg)
+}
+
+/// Get the attributes of the terminal `fd`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not a terminal => -1
+/// - Otherwise => 0
+pub fn tcgetattr(fd: usize, termios: &mut Termios) -> isize {
+    sys_ioctl(fd, TCGETS, termios as *mut _ as usize)
+}
+
+/// Set the attributes of the terminal `fd`. They take effect at once.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not a terminal => -1
+/// - Otherwise => 0
+pub fn tcsetattr(fd: usize, termios: &Termios) -> isize {
+    sys_ioctl(fd, TCSETS, termios as *const _ as usize)
+}
+
+/// Get the foreground process group of the terminal `fd`.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not a terminal => -1
+/// - If the current process is in another session, or no group is set => -1
+/// - Otherwise => Process group ID
+pub fn tcgetpgrp(fd: usize) -> isize {
+    let mut pgrp = 0i32;
+    match sys_ioctl(fd, TIOCGPGRP, &mut pgrp as *mut _ as usize) {
+        0 => pgrp as isize,
+        err => err,
+    }
+}
+
+/// Set the foreground process group of the terminal `fd`.
+///
+/// `VINTR`/`VSUSP` typed at the terminal send `SIGINT`/`SIGTSTP` to the group,
+/// and processes of the other groups are stopped by `SIGTTIN` when they read the terminal.
+///
+/// # Parameters
+/// - `pgrp`: Process group ID in the session of the current process
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not a terminal => -1
+/// - If the terminal is not the controlling terminal of the current process => -1
+/// - If there is no process group `pgrp` in the session => -1
+/// - Otherwise => 0
+pub fn tcsetpgrp(fd: usize, pgrp: usize) -> isize {
+    let pgrp = pgrp as i32;
+    sys_ioctl(fd, TIOCSPGRP, &pgrp as *const _ as usize)
+}
+
 /// Exit the application and inform the batch system of the return value.
 ///
 /// # Parameters
@@ -248,6 +879,54 @@ pub fn getpid() -> isize {
     sys_getpid()
 }
 
+/// Set the process group of the current process or its child.
+///
+/// # Parameters
+/// - `pid`: The current process or its child(0 means the current process)
+/// - `pgid`: Process group to join(0 means `pid` itself, i.e. create a new group led by it)
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `pid` is neither the current process nor its living child => -1
+/// - If the process is a session leader or in another session => -1
+/// - If `pgid` is not `pid` and there is no such group in the session => -1
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    sys_setpgid(pid, pgid)
+}
+
+/// Get the process group ID of a process(0 means the current process).
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process of `pid` => -1
+/// - Otherwise => Process group ID
+pub fn getpgid(pid: usize) -> isize {
+    sys_getpgid(pid)
+}
+
+/// Get the session ID of a process(0 means the current process).
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process of `pid` => -1
+/// - Otherwise => Session ID
+pub fn getsid(pid: usize) -> isize {
+    sys_getsid(pid)
+}
+
+/// Create a new session and a new process group led by the current process.
+///
+/// The new session has no controlling terminal.
+///
+/// # Return
+/// Conditional branching.
+/// - If the current process is a process group leader => -1
+/// - Otherwise => New session ID(the pid of the current process)
+pub fn setsid() -> isize {
+    sys_setsid()
+}
+
 /// Create a child process with a new address space that inherits the stack of the parent process.
 /// The current process forks a child process.
 ///
@@ -275,11 +954,9 @@ pub fn exec(path: &str, args: &[*const u8]) -> isize {
 
 /// Wait for any child process to exit.
 ///
-/// When a waiting child process exists but has not yet terminated,
-/// call `yield_` to actively surrender CPU usage,
-/// and then call sys_waitpid again when CPU usage is returned from the kernel to check
-/// whether the waiting child process has terminated,
-/// thereby reducing waste of CPU resources.
+/// The kernel blocks the current thread until a child process exits,
+/// so no CPU time is wasted while waiting.
+/// If the wait is interrupted by a signal, `sys_waitpid` is called again after the signal is handled.
 ///
 /// # Parameter
 /// - `exit_code`: Address where the return value of the child process is stored.
@@ -287,20 +964,10 @@ pub fn exec(path: &str, args: &[*const u8]) -> isize {
 ///
 /// # Return
 /// Conditional branching.
-/// - If not already stopped => call `yield_` & return 0
+/// - If there is no child process to wait => -1
 /// - exit => The process ID of the terminated child process
 pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                // -2: Waiting child process exists but has not yet terminated.
-                // call `yield_` to aggressively surrender CPU usage and reduce waste of CPU resources.
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    waitpid_options(-1, exit_code, WaitFlags::empty())
 }
 
 /// The current process waits for a child process to become a zombie process, collects all resources,
@@ -313,31 +980,239 @@ pub fn wait(exit_code: &mut i32) -> isize {
 ///
 /// # Return
 /// Conditional branching.
-/// - If none of the waiting child processes have exited => execute `yield_` & loop
+/// - If there is no child process to wait => -1
 /// - Otherwise => The process ID of the terminated child process
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    waitpid_options(pid as isize, exit_code, WaitFlags::empty())
+}
+
+bitflags! {
+    /// Options of `waitpid_options`
+    pub struct WaitFlags: u32 {
+        /// Return at once if no child process has exited.
+        const WNOHANG = 1 << 0;
+        /// Also report children stopped by a job control signal.
+        const WUNTRACED = 1 << 1;
+    }
+}
+
+/// `waitpid` with options.
+///
+/// # Parameters
+/// - `pid`: Process ID of the child process to wait. If -1, it means to wait for any child process.
+/// - `exit_code`: Address where the return value of the child process is stored.
+/// - `options`:
+///   - `WNOHANG`: Do not block even if the child process is still running.
+///   - `WUNTRACED`: Also return when a child process is stopped.
+///     Use [`wifstopped`] to tell it from an exit.
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no child process to wait => -1
+/// - If none of the waiting child processes have exited with `WNOHANG` => -2
+/// - Otherwise => The process ID of the terminated(or stopped) child process
+pub fn waitpid_options(pid: isize, exit_code: &mut i32, options: WaitFlags) -> isize {
     loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
+        match sys_waitpid(pid, exit_code as *mut _, options.bits()) {
+            // -2: interrupted by a signal, which has been handled now.
+            -2 if !options.contains(WaitFlags::WNOHANG) => continue,
+            // -1, -2 or a real pid
             exit_pid => return exit_pid,
         }
     }
 }
 
-/// Call `yield` for the milliseconds given in the `period_ms` argument.
+/// Is `exit_code` returned by [`waitpid_options`] the report of a stopped child process?
+pub fn wifstopped(exit_code: i32) -> bool {
+    exit_code & 0xff == 0x7f
+}
+
+/// Signal bit digit that stopped the child process, when [`wifstopped`] is true.
+pub fn wstopsig(exit_code: i32) -> i32 {
+    (exit_code >> 8) & 0xff
+}
+
+/// Sleep for the milliseconds given in the `period_ms` argument.
+///
+/// The kernel blocks the current thread until the deadline, so no CPU time is used while sleeping.
+/// A signal that is not blocked ends the sleep early.
 ///
 /// # Parameter
 /// - `period_ms`: Milliseconds to sleep
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
+    sys_sleep(period_ms);
+}
+
+/// Time value of `gettimeofday`/`getitimer`/`setitimer`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeVal {
+    /// seconds
+    pub sec: usize,
+    /// microseconds
+    pub usec: usize,
+}
+
+/// Time value of `clock_gettime`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSpec {
+    /// seconds
+    pub sec: usize,
+    /// nanoseconds
+    pub nsec: usize,
+}
+
+impl From<TimeSpec> for Duration {
+    fn from(ts: TimeSpec) -> Self {
+        Duration::new(ts.sec as u64, ts.nsec as u32)
+    }
+}
+
+/// Wall-clock time since the UNIX epoch, read from the RTC
+pub const CLOCK_REALTIME: usize = 0;
+/// Time since boot. It never goes back.
+pub const CLOCK_MONOTONIC: usize = 1;
+
+/// Get the time of a clock.
+///
+/// # Parameters
+/// - `clock_id`: `CLOCK_REALTIME` or `CLOCK_MONOTONIC`
+/// - `ts`: Where the time is stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `clock_id` is unknown => -1
+pub fn clock_gettime(clock_id: usize, ts: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, ts as *mut _)
+}
+
+/// Get the wall-clock time in microseconds.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If the wall-clock time cannot be read => the error of `clock_gettime`(`tv` is left as it is)
+pub fn gettimeofday(tv: &mut TimeVal) -> isize {
+    let mut ts = TimeSpec::default();
+    let ret = clock_gettime(CLOCK_REALTIME, &mut ts);
+    if ret < 0 {
+        return ret;
+    }
+    tv.sec = ts.sec;
+    tv.usec = ts.nsec / 1000;
+    0
+}
+
+/// A measurement of the monotonic clock, like `std::time::Instant`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(Duration);
+
+impl Instant {
+    /// Current time of the monotonic clock
+    pub fn now() -> Self {
+        let mut ts = TimeSpec::default();
+        clock_gettime(CLOCK_MONOTONIC, &mut ts);
+        Self(ts.into())
+    }
+
+    /// Time elapsed from `earlier` to `self` (zero if `earlier` is later)
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// Time elapsed since `self` was measured
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
     }
 }
 
+/// A measurement of the wall clock, like `std::time::SystemTime`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SystemTime(Duration);
+
+impl SystemTime {
+    /// Current time of the wall clock
+    pub fn now() -> Self {
+        let mut ts = TimeSpec::default();
+        clock_gettime(CLOCK_REALTIME, &mut ts);
+        Self(ts.into())
+    }
+
+    /// Time elapsed since the UNIX epoch(1970-01-01 00:00:00 UTC)
+    pub fn since_unix_epoch(&self) -> Duration {
+        self.0
+    }
+}
+
+/// Value of an interval timer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimerVal {
+    /// Value reloaded when the timer expires (zero => one-shot)
+    pub it_interval: TimeVal,
+    /// Time until the next expiration (zero => disarmed)
+    pub it_value: TimeVal,
+}
+
+/// Interval timer decremented in real time, delivers `SIGALRM`
+pub const ITIMER_REAL: usize = 0;
+/// Interval timer decremented in user CPU time, delivers `SIGVTALRM`
+pub const ITIMER_VIRTUAL: usize = 1;
+/// Interval timer decremented in user and kernel CPU time, delivers `SIGPROF`
+pub const ITIMER_PROF: usize = 2;
+
+/// Get the value of an interval timer of the current process.
+///
+/// # Parameters
+/// - `which`: `ITIMER_REAL`, `ITIMER_VIRTUAL` or `ITIMER_PROF`
+/// - `curr_value`: Where the current value is stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `which` is unknown => -1
+pub fn getitimer(which: usize, curr_value: &mut ITimerVal) -> isize {
+    sys_getitimer(which, curr_value as *mut _)
+}
+
+/// Arm or disarm an interval timer of the current process.
+///
+/// # Parameters
+/// - `which`: `ITIMER_REAL`, `ITIMER_VIRTUAL` or `ITIMER_PROF`
+/// - `new_value`: New value. Zero `it_value` disarms the timer.
+/// - `old_value`: Where the old value is stored, if given.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `which` is unknown => -1
+pub fn setitimer(which: usize, new_value: &ITimerVal, old_value: Option<&mut ITimerVal>) -> isize {
+    let old_value = old_value.map_or(core::ptr::null_mut(), |v| v as *mut _);
+    sys_setitimer(which, new_value as *const _, old_value)
+}
+
+/// Send `SIGALRM` to the current process after `seconds` seconds.
+///
+/// Any previous alarm is canceled. If `seconds` is 0, no new alarm is scheduled.
+///
+/// # Return
+/// Seconds remaining until the previous alarm would have been delivered (0 if none)
+pub fn alarm(seconds: usize) -> usize {
+    let new_value = ITimerVal {
+        it_interval: TimeVal::default(),
+        it_value: TimeVal {
+            sec: seconds,
+            usec: 0,
+        },
+    };
+    let mut old_value = ITimerVal::default();
+    setitimer(ITIMER_REAL, &new_value, Some(&mut old_value));
+    // round up, as `alarm` of Linux does
+    old_value.it_value.sec + (old_value.it_value.usec > 0) as usize
+}
+
 /// Action for a signal
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -440,37 +1315,55 @@ impl SignalFlags {
     }
 }
 
-/// Send a signal to the process
+/// Send a signal to a process or a process group
 ///
 /// # Parameters
-/// - `pid`: ID of the process
+/// - `pid`:
+///   - `> 0`: ID of the process
+///   - `0`: every process in the process group of the current process
+///   - `< -1`: every process in the process group `-pid`
 /// - `signal`: integer value representing the signal
 ///
 /// # Return
 /// Conditional branching.
 /// - If the bit corresponding to `signum` in the signal of the process control block is successfully
 ///   set to 1. => 0
+/// - If `signal` is 0, nothing is sent and only the existence of the process(group) is checked. => 0
 ///
-/// - No `TaskControlBlock` corresponding to `pid`(1st arg) => -1
+/// - No `TaskControlBlock` corresponding to `pid`(1st arg), or no process in the group => -1
+/// - If `pid` is -1 => -1
 /// - no `signal` corresponding to `signum` => -1
-/// - If the bit of `signum` is already included in `signals` in the `TaskControlBlockInner`
-///   corresponding to `pid` => -1
+///
+/// A signal that is already pending in the process is not queued twice, but it is not an error.
 ///
 /// # Information
 /// It is to send a signal with the value signum to the process with process number pid.
 /// Specifically, it finds the process control block by `pid` and sets the bit corresponding to `signum`
 /// in the signal of that process control block to 1.
-pub fn kill(pid: usize, signal: i32) -> isize {
+pub fn kill(pid: isize, signal: i32) -> isize {
     sys_kill(pid, signal)
 }
 
+/// Set the scheduling priority of the current thread.
+///
+/// The larger `prio` is, the more CPU time the thread gets (with the stride scheduler)
+/// or the earlier it runs (with the static priority scheduler). The default is 16.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => `prio`
+/// - If `prio` is less than 2 => -1
+pub fn set_priority(prio: isize) -> isize {
+    sys_set_priority(prio)
+}
+
 /// Registers a new handler (`action` argument) corresponding to the `signum` given as argument
 /// and writes the original handler to `old_action`.
 ///
 /// # Parameters
 /// - `signum`: A signal bit digit corresponding to the process to be registered.
-/// - `action`: new signal processing configuration
-/// - `old_action`: old signal processing configuration
+/// - `action`: new signal processing configuration(null => only query the current one)
+/// - `old_action`: old signal processing configuration(null => not written)
 ///
 /// # Return
 /// Conditional branching.
@@ -478,12 +1371,8 @@ pub fn kill(pid: usize, signal: i32) -> isize {
 ///
 /// - Failed to get the current task context => -1
 /// - If `signum` exceeds the bit digits of `MAX_SIG` => -1
-/// - If `action` or `old_action` is 0, or `signum` is `SIGKILL(1<<9)` or `SIGSTOP(1<<19)` => -1
-pub fn sigaction(
-    signum: i32,
-    action: *const SignalAction,
-    old_action: *const SignalAction,
-) -> isize {
+/// - If `action` is not null and `signum` is `SIGKILL(1<<9)` or `SIGSTOP(1<<19)` => -1
+pub fn sigaction(signum: i32, action: *const SignalAction, old_action: *mut SignalAction) -> isize {
     sys_sigaction(signum, action, old_action)
 }
 
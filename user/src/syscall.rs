@@ -1,18 +1,42 @@
-use crate::SignalAction;
+use crate::{ITimerVal, SignalAction, Stat, TimeSpec};
 use core::arch::asm;
 
 const SYSCALL_DUP: usize = 24;
-const SYSCALL_OPEN: usize = 56;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_SYMLINKAT: usize = 36;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_RENAMEAT: usize = 38;
+const SYSCALL_TRUNCATE: usize = 45;
+const SYSCALL_FTRUNCATE: usize = 46;
+const SYSCALL_OPENAT: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
+const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PREAD64: usize = 67;
+const SYSCALL_PWRITE64: usize = 68;
+const SYSCALL_READLINKAT: usize = 78;
+const SYSCALL_FSTATAT: usize = 79;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SLEEP: usize = 101;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
 const SYSCALL_SIGACTION: usize = 134;
 const SYSCALL_SIGPROCMASK: usize = 135;
 const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_GETSID: usize = 156;
+const SYSCALL_SETSID: usize = 157;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_FORK: usize = 220;
@@ -47,6 +71,25 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+/// Same as `syscall` with up to 6 arguments(x10-x15).
+#[inline(always)]
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
+            in("x15") args[5],
+            in("x17") id
+        );
+    }
+    ret
+}
+
 /// Duplicates the file descriptor reference passed in the argument.
 /// - syscall ID: 24
 ///
@@ -62,11 +105,27 @@ pub fn sys_dup(fd: usize) -> isize {
     syscall(SYSCALL_DUP, [fd, 0, 0])
 }
 
-/// Opens a regular file and returns an accessible file descriptor.
+/// Control a device through a file descriptor.
+/// - syscall ID: 29
+///
+/// # Parameters
+/// - `fd`: File descriptor of the device.
+/// - `cmd`: Device dependent request code(e.g. `TCGETS`/`TCSETS` for the console).
+/// - `arg`: Device dependent argument, usually the address of a structure.
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened, or the file does not accept `cmd` => -1
+/// - Otherwise => Depends on `cmd`(0 for the console)
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, cmd, arg])
+}
+
+/// Opens a file or a directory and returns an accessible file descriptor.
 /// - syscall ID: 56
 /// # Parameters
-/// - `path`: Describe the filename of the file to be opened (for simplicity,
-/// the file system does not need to support directories, all files are placed in the root(`/`) directory).
+/// - `dirfd`: Directory that a relative `path` starts from(`AT_FDCWD` means the root directory).
+/// - `path`: Path of the file to be opened. An absolute path ignores `dirfd`.
 /// - `flags`: Describe the flags to be used when opening the file.
 ///
 /// # Flags
@@ -86,14 +145,225 @@ pub fn sys_dup(fd: usize) -> isize {
 /// | 10(0x400) |        trunc | it should be cleared and the size set back to zero,                       |
 /// |           |              | i.e. `TRUNC`, when opening the file.                                      |
 /// |-----------|--------------|---------------------------------------------------------------------------|
+/// |16(0x10000)|    directory | it fails if the path is not a directory(`DIRECTORY`).                     |
+/// |-----------|--------------|---------------------------------------------------------------------------|
+/// |17(0x20000)|   no follow  | it fails if the path is a symbolic link(`NOFOLLOW`).                      |
+/// |-----------|--------------|---------------------------------------------------------------------------|
 ///
 /// # Return
 /// Conditional branching.
 /// - if there is an error => -1
 /// - otherwise=> returns the file descriptor of the file normally.
-///               Possible error cause: the file does not exist.
-pub fn sys_open(path: &str, flags: u32) -> isize {
-    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
+///               Possible error cause: the file does not exist, `dirfd` is not a directory,
+///               a directory is opened for writing or `flags` is unknown.
+pub fn sys_openat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_OPENAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+/// Create a directory.
+/// - syscall ID: 34
+///
+/// # Parameters
+/// - `dirfd`: Directory that a relative `path` starts from(`AT_FDCWD` means the root directory).
+/// - `path`: Path of the directory to be created. An absolute path ignores `dirfd`.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `dirfd` is not a directory, `path` already exists or its parent directory is not found => -1
+pub fn sys_mkdirat(dirfd: isize, path: &str) -> isize {
+    syscall(SYSCALL_MKDIRAT, [dirfd as usize, path.as_ptr() as usize, 0])
+}
+
+/// Remove a file or an empty directory.
+/// - syscall ID: 35
+///
+/// # Parameters
+/// - `dirfd`: Directory that a relative `path` starts from(`AT_FDCWD` means the root directory).
+/// - `path`: Path to be removed. An absolute path ignores `dirfd`.
+/// - `flags`: `AT_REMOVEDIR` to remove a directory, otherwise 0
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `path` is not found, its type does not match `flags` or the directory is not empty => -1
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+/// Add a new name(hard link) for an existing file.
+/// - syscall ID: 37
+///
+/// # Parameters
+/// - `olddirfd`/`oldpath`: The existing file(see `sys_openat`).
+/// - `newdirfd`/`newpath`: The new name(see `sys_openat`).
+/// - `flags`: `AT_SYMLINK_FOLLOW` to link the target of a symbolic link, otherwise 0.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `oldpath` is not found or a directory => -1
+/// - If `newpath` already exists, or its parent directory is not found => -1
+pub fn sys_linkat(
+    olddirfd: isize,
+    oldpath: &str,
+    newdirfd: isize,
+    newpath: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_LINKAT,
+        [
+            olddirfd as usize,
+            oldpath.as_ptr() as usize,
+            newdirfd as usize,
+            newpath.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+/// Move a file or a directory to another name atomically.
+/// - syscall ID: 38
+///
+/// # Parameters
+/// - `olddirfd`/`oldpath`: The file to be moved(see `sys_openat`).
+/// - `newdirfd`/`newpath`: The new name(see `sys_openat`).
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `oldpath` is not found, or the parent directory of `newpath` is not found => -1
+/// - If a directory is moved under itself => -1
+/// - If `newpath` exists and is a different type or a directory that is not empty => -1
+pub fn sys_renameat(olddirfd: isize, oldpath: &str, newdirfd: isize, newpath: &str) -> isize {
+    syscall6(
+        SYSCALL_RENAMEAT,
+        [
+            olddirfd as usize,
+            oldpath.as_ptr() as usize,
+            newdirfd as usize,
+            newpath.as_ptr() as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+/// Change the size of a file.
+/// - syscall ID: 45
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `path` is not found or not a regular file, or `length` is invalid => -1
+pub fn sys_truncate(path: &str, length: isize) -> isize {
+    syscall(
+        SYSCALL_TRUNCATE,
+        [path.as_ptr() as usize, length as usize, 0],
+    )
+}
+
+/// Change the size of an open file.
+/// - syscall ID: 46
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `fd` is not a regular file opened for writing, or `length` is invalid => -1
+pub fn sys_ftruncate(fd: usize, length: isize) -> isize {
+    syscall(SYSCALL_FTRUNCATE, [fd, length as usize, 0])
+}
+
+/// Create a symbolic link that refers to `target`.
+/// - syscall ID: 36
+///
+/// # Parameters
+/// - `target`: Path stored in the link. It may not exist.
+/// - `newdirfd`/`linkpath`: Path of the new link(see `sys_openat`).
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `linkpath` already exists, its parent directory is not found or `target` is empty => -1
+pub fn sys_symlinkat(target: &str, newdirfd: isize, linkpath: &str) -> isize {
+    syscall(
+        SYSCALL_SYMLINKAT,
+        [
+            target.as_ptr() as usize,
+            newdirfd as usize,
+            linkpath.as_ptr() as usize,
+        ],
+    )
+}
+
+/// Read the target of a symbolic link.
+/// - syscall ID: 78
+///
+/// # Parameters
+/// - `dirfd`/`path`: The symbolic link(see `sys_openat`).
+/// - `buf`: Buffer that receives the target(truncated to its length, without a null character).
+///
+/// # Return
+/// Conditional branching.
+/// - If `path` is not found or not a symbolic link => -1
+/// - Otherwise => Number of bytes placed in `buf`
+pub fn sys_readlinkat(dirfd: isize, path: &str, buf: &mut [u8]) -> isize {
+    syscall6(
+        SYSCALL_READLINKAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+            0,
+            0,
+        ],
+    )
+}
+
+/// Get the status of a file.
+/// - syscall ID: 79
+///
+/// # Parameters
+/// - `dirfd`/`path`: The file(see `sys_openat`).
+/// - `st`: Where the status is stored.
+/// - `flags`: `AT_SYMLINK_NOFOLLOW` to report a symbolic link itself, otherwise 0
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `path` is not found or `flags` is unknown => -1
+pub fn sys_fstatat(dirfd: isize, path: &str, st: *mut Stat, flags: u32) -> isize {
+    syscall6(
+        SYSCALL_FSTATAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            st as usize,
+            flags as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+/// Get the status of an open file.
+/// - syscall ID: 80
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `fd` is not opened => -1
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, st as usize, 0])
 }
 
 /// The current process closes the file.
@@ -159,6 +429,77 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+/// Read the entries of a directory as `struct linux_dirent64` records.
+/// - syscall ID: 61
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not an open directory, or `buffer` is too small for the next record => -1
+/// - If all entries have been read => 0
+/// - Otherwise => Number of bytes of the records
+pub fn sys_getdents64(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_GETDENTS64,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
+/// Move the offset of a file.
+/// - syscall ID: 62
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened, `whence` is unknown or the new offset is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => The new offset
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall(SYSCALL_LSEEK, [fd, offset as usize, whence])
+}
+
+/// Reads a piece of content from a position of a file without moving its offset.
+/// - syscall ID: 67
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened for reading or `offset` is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => number of bytes actually read.
+pub fn sys_pread64(fd: usize, buffer: &mut [u8], offset: isize) -> isize {
+    syscall6(
+        SYSCALL_PREAD64,
+        [
+            fd,
+            buffer.as_mut_ptr() as usize,
+            buffer.len(),
+            offset as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+/// Writes a buffer to a position of a file without moving its offset.
+/// - syscall ID: 68
+///
+/// # Return
+/// Conditional branching.
+/// - If `fd` is not opened for writing or `offset` is negative => -1
+/// - If `fd` is a pipe or the console => -`ESPIPE`
+/// - Otherwise => The length of the successful write.
+pub fn sys_pwrite64(fd: usize, buffer: &[u8], offset: isize) -> isize {
+    syscall6(
+        SYSCALL_PWRITE64,
+        [
+            fd,
+            buffer.as_ptr() as usize,
+            buffer.len(),
+            offset as usize,
+            0,
+            0,
+        ],
+    )
+}
+
 /// Exit the application and inform the batch system of the return value.
 /// - syscall ID: 93
 ///
@@ -172,6 +513,73 @@ pub fn sys_exit(xstate: i32) -> ! {
     panic!("sys_exit never returns!");
 }
 
+/// Block the current thread for the given milliseconds.
+/// - syscall ID: 101
+///
+/// # Parameters
+/// - `period_ms`: Milliseconds to sleep
+///
+/// # Return
+/// Conditional branching.
+/// - If the deadline has passed => 0
+/// - If a signal woke the thread up before the deadline => -1
+pub fn sys_sleep(period_ms: usize) -> isize {
+    syscall(SYSCALL_SLEEP, [period_ms, 0, 0])
+}
+
+/// Get the value of an interval timer of the current process.
+/// - syscall ID: 102
+///
+/// # Parameters
+/// - `which`: `ITIMER_REAL(0)`, `ITIMER_VIRTUAL(1)` or `ITIMER_PROF(2)`
+/// - `curr_value`: Address where the current value is stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `which` is unknown or `curr_value` is 0 => -1
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> isize {
+    syscall(SYSCALL_GETITIMER, [which, curr_value as usize, 0])
+}
+
+/// Arm or disarm an interval timer of the current process.
+/// - syscall ID: 103
+///
+/// # Parameters
+/// - `which`: `ITIMER_REAL(0)`, `ITIMER_VIRTUAL(1)` or `ITIMER_PROF(2)`
+/// - `new_value`: New value. Zero `it_value` disarms the timer.
+/// - `old_value`: Address where the old value is stored. If this address is 0, it is not stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `which` is unknown or `new_value` is 0 => -1
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> isize {
+    syscall(
+        SYSCALL_SETITIMER,
+        [which, new_value as usize, old_value as usize],
+    )
+}
+
+/// Get the time of a clock.
+/// - syscall ID: 113
+///
+/// # Parameters
+/// - `clock_id`: `CLOCK_REALTIME(0)` or `CLOCK_MONOTONIC(1)`
+/// - `ts`: Address where the time is stored.
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - If `clock_id` is unknown or `ts` is 0 => -1
+pub fn sys_clock_gettime(clock_id: usize, ts: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, ts as usize, 0])
+}
+
 /// The application actively relinquishes ownership of the CPU and switches to another application.
 /// - syscall ID: 124
 ///
@@ -181,11 +589,14 @@ pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
-/// Send a signal to the process
+/// Send a signal to a process or a process group
 /// - syscall ID: 129
 ///
 /// # Parameters
-/// - `pid`: ID of the process
+/// - `pid`:
+///   - `> 0`: ID of the process
+///   - `0`: every process in the process group of the current process
+///   - `< -1`: every process in the process group `-pid`
 /// - `signal`: integer value representing the signal
 ///
 /// # Return
@@ -193,7 +604,8 @@ pub fn sys_yield() -> isize {
 /// - If the bit corresponding to `signum` in the signal of the process control block is successfully
 ///   set to 1. => 0
 ///
-/// - No `TaskControlBlock` corresponding to `pid`(1st arg) => -1
+/// - No `TaskControlBlock` corresponding to `pid`(1st arg), or no process in the group => -1
+/// - If `pid` is -1 => -1
 /// - no `signal` corresponding to `signum` => -1
 /// - If the bit of `signum` is already included in `signals` in the `TaskControlBlockInner`
 ///   corresponding to `pid` => -1
@@ -202,8 +614,8 @@ pub fn sys_yield() -> isize {
 /// It is to send a signal with the value signum to the process with process number pid.
 /// Specifically, it finds the process control block by `pid` and sets the bit corresponding to `signum`
 /// in the signal of that process control block to 1.
-pub fn sys_kill(pid: usize, signal: i32) -> isize {
-    syscall(SYSCALL_KILL, [pid, signal as usize, 0])
+pub fn sys_kill(pid: isize, signal: i32) -> isize {
+    syscall(SYSCALL_KILL, [pid as usize, signal as usize, 0])
 }
 
 // Get current time.
@@ -287,14 +699,19 @@ pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
 /// - `pid`: Process ID of the child process to wait. If -1, it means to wait for any child process.
 /// - `exit_code`: Address where the return value of the child process is stored.
 ///              If this address is 0, it means that there is no need to store the return value.
+/// - `options`: [`crate::WaitFlags`]
 ///
 /// # Return
 /// Conditional branching.
 /// - If there is no child process to wait => -1
-/// - If none of the waiting child processes have exited => -2
-/// - Otherwise => The process ID of the terminated child process
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+/// - If none of the waiting child processes have exited with `WNOHANG` => -2
+/// - If interrupted by a signal => -2
+/// - Otherwise => The process ID of the terminated(or stopped with `WUNTRACED`) child process
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: u32) -> isize {
+    syscall(
+        SYSCALL_WAITPID,
+        [pid as usize, exit_code as usize, options as usize],
+    )
 }
 
 /// Registers a new handler (`action` argument) corresponding to the `signum` given as argument
@@ -316,7 +733,7 @@ pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
 pub fn sys_sigaction(
     signum: i32,
     action: *const SignalAction,
-    old_action: *const SignalAction,
+    old_action: *mut SignalAction,
 ) -> isize {
     syscall(
         SYSCALL_SIGACTION,
@@ -358,6 +775,68 @@ pub fn sys_sigreturn() -> isize {
     syscall(SYSCALL_SIGRETURN, [0, 0, 0])
 }
 
+/// Set the scheduling priority of the current thread.
+/// - syscall ID: 140
+///
+/// # Parameter
+/// - `prio`: New priority(>= 2)
+///
+/// # Return
+/// Conditional branching.
+/// - Success => `prio`
+/// - If `prio` is less than 2 => -1
+pub fn sys_set_priority(prio: isize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0])
+}
+
+/// Set the process group of the current process or its child.
+/// - syscall ID: 154
+///
+/// # Parameters
+/// - `pid`: The current process or its child(0 means the current process)
+/// - `pgid`: Process group to join(0 means `pid` itself)
+///
+/// # Return
+/// Conditional branching.
+/// - Success => 0
+/// - Otherwise => -1
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall(SYSCALL_SETPGID, [pid, pgid, 0])
+}
+
+/// Get the process group ID of a process.
+/// - syscall ID: 155
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process of `pid` => -1
+/// - Otherwise => Process group ID
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall(SYSCALL_GETPGID, [pid, 0, 0])
+}
+
+/// Get the session ID of a process.
+/// - syscall ID: 156
+///
+/// # Return
+/// Conditional branching.
+/// - If there is no process of `pid` => -1
+/// - Otherwise => Session ID
+pub fn sys_getsid(pid: usize) -> isize {
+    syscall(SYSCALL_GETSID, [pid, 0, 0])
+}
+
+/// Create a new session led by the current process.
+/// - syscall ID: 157
+///
+/// # Return
+/// Conditional branching.
+/// - If the current process is a process group leader => -1
+/// - Otherwise => New session ID
+pub fn sys_setsid() -> isize {
+    syscall(SYSCALL_SETSID, [0, 0, 0])
+}
+
 /// Current process creates a new thread.
 /// - syscall ID: 139
 ///